@@ -0,0 +1,316 @@
+//! Инструменты анализа RC4 для аудита ключей и исследований.
+//!
+//! Это диагностика известных слабостей, а не доказательство стойкости:
+//! ключ без найденных признаков по-прежнему используется с RC4,
+//! у которого есть и другие известные атаки.
+
+use crate::Rc4;
+
+/// Признаки известных смещений S-box после KSA для конкретного ключа.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBiasReport {
+    /// `S[0] == 0` после KSA.
+    pub s0_equals_0: bool,
+    /// `S[0] == 1` после KSA.
+    pub s0_equals_1: bool,
+    /// `S[0] == K[0]` — соотношение Руса при i = 0: первый байт ключа
+    /// виден напрямую в S-box (вероятность ~0.37 вместо 1/256).
+    pub s0_encodes_key_byte: bool,
+    /// `S[1] == K[0] + K[1] + 1` — соотношение Руса при i = 1: второй
+    /// байт ключа восстанавливается из S-box.
+    pub s1_encodes_key_byte: bool,
+    /// Число позиций i, для которых выполняется соотношение Руса
+    /// `S[i] == i(i+1)/2 + K[0] + ... + K[i]` (mod 256).
+    pub roos_correlated_positions: usize,
+    /// `S[2] == 0 && S[1] != 2`: второй байт гаммы будет нулевым
+    /// (смещение Мантина–Шамира).
+    pub second_output_byte_zero: bool,
+    /// Ключ имеет форму слабого IV атаки FMS: `(A + 3, 255, X, ...)`,
+    /// где A — индекс атакуемого байта секретной части ключа.
+    pub fms_vulnerable: bool,
+}
+
+impl KeyBiasReport {
+    /// Найден ли хотя бы один признак, напрямую раскрывающий ключ или гамму.
+    pub fn has_findings(&self) -> bool {
+        self.s0_encodes_key_byte
+            || self.s1_encodes_key_byte
+            || self.second_output_byte_zero
+            || self.fms_vulnerable
+    }
+}
+
+impl Rc4 {
+    /// Выполняет KSA для `key` и проверяет полученный S-box на известные
+    /// из литературы смещения:
+    ///
+    /// - A. Roos, "A Class of Weak Keys in the RC4 Stream Cipher" (1995):
+    ///   `S[i]` коррелирует с `i(i+1)/2 + sum(K[0..=i])`;
+    /// - I. Mantin, A. Shamir, "A Practical Attack on Broadcast RC4" (2001):
+    ///   второй байт гаммы равен нулю с вероятностью 2/256;
+    /// - S. Fluhrer, I. Mantin, A. Shamir, "Weaknesses in the Key Scheduling
+    ///   Algorithm of RC4" (2001): ключи вида `(A + 3, 255, X)`.
+    ///
+    /// Паникует при недопустимой длине ключа, как и `new`.
+    pub fn key_bias_test(key: &[u8]) -> KeyBiasReport {
+        let s = *Rc4::new(key).export_state().sbox();
+
+        let mut roos_correlated_positions = 0;
+        let mut expected = 0u8;
+        let mut roos = [false; 2];
+        for (i, &actual) in s.iter().enumerate() {
+            expected = expected
+                .wrapping_add(i as u8)
+                .wrapping_add(key[i % key.len()]);
+            if actual == expected {
+                roos_correlated_positions += 1;
+                if i < 2 {
+                    roos[i] = true;
+                }
+            }
+        }
+
+        let fms_vulnerable = key.len() > 3
+            && key[1] == 255
+            && key[0] >= 3
+            && ((key[0] - 3) as usize) < key.len() - 3;
+
+        KeyBiasReport {
+            s0_equals_0: s[0] == 0,
+            s0_equals_1: s[0] == 1,
+            s0_encodes_key_byte: roos[0],
+            s1_encodes_key_byte: roos[1],
+            roos_correlated_positions,
+            second_output_byte_zero: s[2] == 0 && s[1] != 2,
+            fms_vulnerable,
+        }
+    }
+
+    /// Расстояние Хэмминга между S-box после KSA для `key_a` и `key_b`:
+    /// число позиций, где значения различаются. Для независимых случайных
+    /// ключей ожидаемое значение — 255 (у случайной перестановки в среднем
+    /// одна неподвижная точка), малые значения говорят о сильно связанных
+    /// ключах. Паникует при недопустимой длине ключа, как и `new`.
+    pub fn key_schedule_diff(key_a: &[u8], key_b: &[u8]) -> usize {
+        let a = Rc4::new(key_a).export_state();
+        let b = Rc4::new(key_b).export_state();
+        a.sbox()
+            .iter()
+            .zip(b.sbox())
+            .filter(|(x, y)| x != y)
+            .count()
+    }
+}
+
+/// Состояние PRGA на одном шаге; см. `Rc4::prga_debug_trace`.
+#[cfg(feature = "debug-trace")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrgaStep {
+    /// Номер шага с нуля.
+    pub step: usize,
+    /// Счетчики после шага.
+    pub i: u8,
+    pub j: u8,
+    /// `S[i]` и `S[j]` до обмена.
+    pub si: u8,
+    pub sj: u8,
+    /// Индекс байта гаммы `t = S[i] + S[j]`.
+    pub t: u8,
+    /// Байт гаммы `S[t]`.
+    pub k: u8,
+    pub input: u8,
+    /// `input ^ k`.
+    pub output: u8,
+}
+
+#[cfg(feature = "debug-trace")]
+impl Rc4 {
+    /// Пошаговый протокол PRGA над `data` для отладки совместимости.
+    /// Работает на копии состояния: `self` не меняется, и следующий вызов
+    /// `process` обработает те же байты гаммы. Протокол занимает 10 байт
+    /// памяти на байт данных.
+    pub fn prga_debug_trace(&self, data: &[u8]) -> Vec<PrgaStep> {
+        let state = self.export_state();
+        let mut s = *state.sbox();
+        let (mut i, mut j) = (state.i(), state.j());
+
+        data.iter()
+            .enumerate()
+            .map(|(step, &input)| {
+                i = i.wrapping_add(1);
+                let si = s[i as usize];
+                j = j.wrapping_add(si);
+                let sj = s[j as usize];
+                s.swap(i as usize, j as usize);
+                let t = si.wrapping_add(sj);
+                let k = s[t as usize];
+                PrgaStep {
+                    step,
+                    i,
+                    j,
+                    si,
+                    sj,
+                    t,
+                    k,
+                    input,
+                    output: input ^ k,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Распределение первого байта гаммы по `samples` ключам длины `key_len`;
+/// см. `keystream_byte_distribution_at`.
+#[cfg(feature = "stats")]
+pub fn keystream_byte_distribution(samples: usize, key_len: usize) -> [u64; 256] {
+    keystream_byte_distribution_at(samples, key_len, 0)
+}
+
+/// Генерирует `samples` ключей длины `key_len`, берет у каждого байт гаммы
+/// с номером `offset` (с нуля) и возвращает число появлений каждого значения.
+///
+/// При равномерном распределении каждое значение встречалось бы примерно
+/// `samples / 256` раз; например, при `offset = 1` ноль встречается вдвое
+/// чаще (смещение Мантина–Шамира). Ключи берутся из гаммы RC4 с
+/// фиксированным ключом, поэтому результат воспроизводим между запусками.
+///
+/// Паникует при недопустимой длине ключа, как и `Rc4::new`.
+#[cfg(feature = "stats")]
+pub fn keystream_byte_distribution_at(samples: usize, key_len: usize, offset: usize) -> [u64; 256] {
+    let mut key_source = Rc4::new(b"keystream_byte_distribution");
+    let mut key = vec![0u8; key_len];
+    let mut counts = [0u64; 256];
+
+    for _ in 0..samples {
+        key_source.fill_keystream(&mut key);
+        let mut cipher = Rc4::new(&key);
+        cipher.skip(offset);
+        counts[cipher.next_byte() as usize] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Одинаковые ключи дают расстояние 0, случайные — около 255
+    #[test]
+    fn test_key_schedule_diff() {
+        assert_eq!(Rc4::key_schedule_diff(b"Key", b"Key"), 0);
+        assert!(Rc4::key_schedule_diff(b"Key", b"Kez") > 0);
+
+        let mut keys = Rc4::new(b"random keys");
+        let pairs = 200;
+        let total: usize = (0..pairs)
+            .map(|_| {
+                let (a, b) = (keys.keystream_to_vec(16), keys.keystream_to_vec(16));
+                let diff = Rc4::key_schedule_diff(&a, &b);
+                assert!(diff >= 240, "distance {} for {:02x?} / {:02x?}", diff, a, b);
+                diff
+            })
+            .sum();
+        let mean = total as f64 / pairs as f64;
+        assert!((254.0..=256.0).contains(&mean), "mean distance {}", mean);
+    }
+
+    /// Ключ вида (A + 3, 255, X) помечается как уязвимый к FMS
+    #[test]
+    fn test_fms_weak_key() {
+        let report = Rc4::key_bias_test(&[3, 255, 7, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        assert!(report.fms_vulnerable);
+        assert!(report.has_findings());
+
+        assert!(!Rc4::key_bias_test(&[3, 254, 7, 0x11, 0x22]).fms_vulnerable);
+        assert!(!Rc4::key_bias_test(&[200, 255, 7, 0x11, 0x22]).fms_vulnerable);
+    }
+
+    /// Флаг нулевого второго байта согласован с реальной гаммой
+    #[test]
+    fn test_second_output_byte_zero_matches_keystream() {
+        for n in 0..2000u32 {
+            let key = n.to_le_bytes();
+            let report = Rc4::key_bias_test(&key);
+            if report.second_output_byte_zero {
+                assert_eq!(Rc4::new(key).keystream_to_vec(2)[1], 0);
+            }
+        }
+    }
+
+    /// Соотношение Руса при i = 0 выполняется намного чаще, чем 1/256
+    #[test]
+    fn test_roos_bias_is_observable() {
+        let keys = Rc4::new(b"key source").keystream_to_vec(16 * 1000);
+        let hits = keys
+            .chunks(16)
+            .filter(|key| Rc4::key_bias_test(key).s0_encodes_key_byte)
+            .count();
+
+        // Ожидается ~37%; при равномерном распределении было бы ~4 из 1000
+        assert!(hits > 250, "only {} of 1000 keys", hits);
+    }
+
+    /// Протокол PRGA совпадает с шагами, посчитанными вручную по S-box,
+    /// и с эталонным шифротекстом "Key"/"Plaintext"
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn test_prga_debug_trace() {
+        let rc4 = Rc4::new(b"Key");
+        let trace = rc4.prga_debug_trace(b"Plaintext");
+        let outputs: Vec<u8> = trace.iter().map(|step| step.output).collect();
+        assert_eq!(
+            outputs,
+            [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+        );
+
+        // Первые два шага вручную: i = 1, j = S[1]; затем i = 2, j += S[2]
+        let mut s = *rc4.export_state().sbox();
+        let (si, j1) = (s[1], s[1]);
+        let sj = s[j1 as usize];
+        s.swap(1, j1 as usize);
+        let t = si.wrapping_add(sj);
+        let first = PrgaStep {
+            step: 0,
+            i: 1,
+            j: j1,
+            si,
+            sj,
+            t,
+            k: s[t as usize],
+            input: b'P',
+            output: b'P' ^ s[t as usize],
+        };
+        assert_eq!(trace[0], first);
+
+        let si = s[2];
+        let j2 = j1.wrapping_add(si);
+        let sj = s[j2 as usize];
+        s.swap(2, j2 as usize);
+        let t = si.wrapping_add(sj);
+        assert_eq!((trace[1].i, trace[1].j), (2, j2));
+        assert_eq!((trace[1].si, trace[1].sj, trace[1].t), (si, sj, t));
+        assert_eq!(trace[1].k, s[t as usize]);
+
+        // Исходный шифр не изменился
+        let mut rc4 = rc4;
+        assert_eq!(rc4.apply(b"Plaintext"), outputs);
+    }
+
+    /// Второй байт гаммы равен нулю примерно вдвое чаще, чем остальные значения
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_second_byte_distribution_bias() {
+        let samples = 100_000;
+        let counts = keystream_byte_distribution_at(samples, 16, 1);
+        assert_eq!(counts.iter().sum::<u64>(), samples as u64);
+
+        // Ожидается ~781 против ~390 для остальных значений
+        let others = (samples as u64 - counts[0]) / 255;
+        assert!(counts[0] > others * 3 / 2, "{} vs {}", counts[0], others);
+        assert_eq!(
+            keystream_byte_distribution(10, 5),
+            keystream_byte_distribution_at(10, 5, 0)
+        );
+    }
+}
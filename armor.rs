@@ -0,0 +1,379 @@
+//! ASCII-броня в стиле OpenPGP для вставки шифротекста в письма и тикеты.
+//!
+//! ```text
+//! -----BEGIN RC4 MESSAGE-----
+//! Version: rc4 armor 1
+//!
+//! <Base64, 64 символа в строке>
+//! =<CRC-24 в Base64>
+//! -----END RC4 MESSAGE-----
+//! ```
+//!
+//! Контрольная сумма CRC-24 (RFC 4880, раздел 6.1) обнаруживает
+//! повреждение при копировании, но не защищает от подделки.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::base64;
+
+pub const BEGIN_LINE: &str = "-----BEGIN RC4 MESSAGE-----";
+pub const END_LINE: &str = "-----END RC4 MESSAGE-----";
+const VERSION: &str = "rc4 armor 1";
+
+/// Байт данных в одной строке: 48 байт дают 64 символа Base64.
+const LINE_BYTES: usize = 48;
+
+const CRC24_INIT: u32 = 0x00b7_04ce;
+const CRC24_POLY: u32 = 0x0186_4cfb;
+
+fn crc24_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0x00ff_ffff
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Кодирует записываемые байты в броню. Заголовок пишется при создании,
+/// контрольная сумма и завершающая строка — в `finish`, без которого
+/// результат неполон.
+pub struct ArmorWriter<W: Write> {
+    inner: W,
+    line: [u8; LINE_BYTES],
+    line_len: usize,
+    crc: u32,
+}
+
+impl<W: Write> ArmorWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        write!(inner, "{}\nVersion: {}\n\n", BEGIN_LINE, VERSION)?;
+        Ok(ArmorWriter {
+            inner,
+            line: [0; LINE_BYTES],
+            line_len: 0,
+            crc: CRC24_INIT,
+        })
+    }
+
+    /// Дописывает последнюю строку, контрольную сумму и `END`,
+    /// возвращает внутренний writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_line()?;
+        let crc = self.crc.to_be_bytes();
+        writeln!(self.inner, "={}", base64::encode(&crc[1..]))?;
+        writeln!(self.inner, "{}", END_LINE)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn flush_line(&mut self) -> io::Result<()> {
+        if self.line_len > 0 {
+            let encoded = base64::encode(&self.line[..self.line_len]);
+            writeln!(self.inner, "{}", encoded)?;
+            self.line_len = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ArmorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(LINE_BYTES - self.line_len);
+        self.line[self.line_len..self.line_len + n].copy_from_slice(&buf[..n]);
+        self.line_len += n;
+        self.crc = crc24_update(self.crc, &buf[..n]);
+        if self.line_len == LINE_BYTES {
+            self.flush_line()?;
+        }
+        Ok(n)
+    }
+
+    /// Сбрасывает только полные строки: неполная строка остается в буфере
+    /// до `finish`, иначе в середине брони появилась бы строка с `=`.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadState {
+    Start,
+    Body,
+    Done,
+}
+
+/// Разбирает броню и отдает исходные байты. Терпим к CRLF, пробелам по
+/// краям строк и пустым строкам до и после брони. Ошибка `InvalidData`
+/// возвращается при поврежденном Base64, отсутствующей или неверной
+/// контрольной сумме и отсутствии строки `END`.
+pub struct ArmorReader<R: Read> {
+    inner: BufReader<R>,
+    state: ReadState,
+    headers: Vec<(String, String)>,
+    line: String,
+    /// Символы Base64, не образовавшие полной группы из 4.
+    carry: String,
+    decoded: Vec<u8>,
+    pos: usize,
+    padded: bool,
+    crc: u32,
+}
+
+impl<R: Read> ArmorReader<R> {
+    pub fn new(inner: R) -> Self {
+        ArmorReader {
+            inner: BufReader::new(inner),
+            state: ReadState::Start,
+            headers: Vec::new(),
+            line: String::new(),
+            carry: String::new(),
+            decoded: Vec::new(),
+            pos: 0,
+            padded: false,
+            crc: CRC24_INIT,
+        }
+    }
+
+    /// Заголовки брони (`Version` и другие); заполняются при первом чтении.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Читает следующую строку без пробелов по краям; `None` в конце потока.
+    fn next_line(&mut self) -> io::Result<Option<&str>> {
+        self.line.clear();
+        if self.inner.read_line(&mut self.line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.line.trim()))
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        loop {
+            match self.next_line()? {
+                None => return Err(invalid("armor header not found")),
+                Some("") => continue,
+                Some(BEGIN_LINE) => break,
+                Some(_) => return Err(invalid("input does not start with an RC4 armor header")),
+            }
+        }
+
+        // Заголовки "Ключ: значение" до пустой строки; ':' нет в алфавите
+        // Base64, поэтому строка без него уже относится к данным
+        loop {
+            let Some(line) = self.next_line()? else {
+                return Err(invalid("armor ends before the message body"));
+            };
+            if line.is_empty() {
+                return Ok(());
+            }
+            match line.split_once(':') {
+                Some((key, value)) => {
+                    let header = (key.trim().to_string(), value.trim().to_string());
+                    self.headers.push(header);
+                }
+                None => {
+                    let line = line.to_string();
+                    return self.decode_body_line(&line);
+                }
+            }
+        }
+    }
+
+    fn decode_body_line(&mut self, line: &str) -> io::Result<()> {
+        if self.padded {
+            return Err(invalid("armor contains data after base64 padding"));
+        }
+        self.carry.push_str(line);
+        let complete = self.carry.len() - self.carry.len() % 4;
+        let bytes = base64::decode(&self.carry[..complete])
+            .map_err(|e| invalid(format!("corrupted armor: {}", e)))?;
+        self.padded = self.carry[..complete].ends_with('=');
+        self.carry.drain(..complete);
+
+        self.crc = crc24_update(self.crc, &bytes);
+        self.decoded = bytes;
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn verify_checksum(&mut self, encoded: &str) -> io::Result<()> {
+        if !self.carry.is_empty() {
+            return Err(invalid("armor body is truncated"));
+        }
+        let checksum = base64::decode(encoded)
+            .map_err(|e| invalid(format!("corrupted armor checksum: {}", e)))?;
+        if checksum.len() != 3 {
+            return Err(invalid(format!(
+                "armor checksum must be 3 bytes, got {}",
+                checksum.len()
+            )));
+        }
+        let expected = u32::from_be_bytes([0, checksum[0], checksum[1], checksum[2]]);
+        if expected != self.crc {
+            return Err(invalid("armor checksum mismatch"));
+        }
+
+        loop {
+            match self.next_line()? {
+                Some("") => continue,
+                Some(END_LINE) => {
+                    self.state = ReadState::Done;
+                    return Ok(());
+                }
+                _ => return Err(invalid("armor end line not found after checksum")),
+            }
+        }
+    }
+
+    /// Продвигает разбор до появления новых данных или конца брони.
+    fn fill(&mut self) -> io::Result<()> {
+        if self.state == ReadState::Start {
+            self.read_header()?;
+            self.state = ReadState::Body;
+            if self.pos < self.decoded.len() {
+                return Ok(());
+            }
+        }
+
+        while self.state == ReadState::Body && self.pos == self.decoded.len() {
+            let Some(line) = self.next_line()? else {
+                return Err(invalid("armor end line not found"));
+            };
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(checksum) = line.strip_prefix('=') {
+                let checksum = checksum.to_string();
+                self.verify_checksum(&checksum)?;
+            } else if line.starts_with("-----") {
+                return Err(invalid("armor checksum is missing"));
+            } else {
+                let line = line.to_string();
+                self.decode_body_line(&line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ArmorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.decoded.len() && self.state != ReadState::Done {
+            self.fill()?;
+        }
+        let n = buf.len().min(self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Похоже ли начало данных на броню: первые непробельные символы — дефисы.
+pub fn looks_armored(prefix: &[u8]) -> bool {
+    prefix
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rc4;
+
+    fn armor(data: &[u8]) -> String {
+        let mut writer = ArmorWriter::new(Vec::new()).unwrap();
+        // Нерегулярные порции проверяют буферизацию строк
+        for chunk in data.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    fn dearmor(text: &str) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        ArmorReader::new(text.as_bytes()).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    /// Броня разбирается обратно для данных любой длины
+    #[test]
+    fn test_roundtrip() {
+        for len in [0, 1, 2, 3, 47, 48, 49, 96, 1000] {
+            let data = Rc4::new(b"armor").keystream_to_vec(len);
+            let text = armor(&data);
+            assert!(text.starts_with(BEGIN_LINE));
+            assert!(text.ends_with(&format!("{}\n", END_LINE)));
+            assert!(text.lines().all(|line| line.len() <= 64));
+            assert_eq!(dearmor(&text).unwrap(), data, "len {}", len);
+        }
+
+        let text = armor(b"x");
+        let mut reader = ArmorReader::new(text.as_bytes());
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(
+            reader.headers(),
+            [("Version".to_string(), VERSION.to_string())]
+        );
+    }
+
+    /// CRLF и пробелы по краям не мешают разбору
+    #[test]
+    fn test_crlf_and_whitespace() {
+        let data = Rc4::new(b"armor").keystream_to_vec(200);
+        let text = armor(&data)
+            .lines()
+            .map(|line| format!("  {}\t", line))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        let text = format!("\r\n\n{}\r\n\r\n", text);
+        assert_eq!(dearmor(&text).unwrap(), data);
+        assert!(looks_armored(text.as_bytes()));
+        assert!(!looks_armored(b" \nplain text"));
+    }
+
+    /// Поврежденный Base64 и неверная контрольная сумма обнаруживаются
+    #[test]
+    fn test_corruption_detected() {
+        let data = Rc4::new(b"armor").keystream_to_vec(200);
+        let text = armor(&data);
+        let lines: Vec<&str> = text.lines().collect();
+        let rebuild = |index: usize, line: &str| {
+            let mut lines = lines.clone();
+            lines[index] = line;
+            lines.join("\n")
+        };
+
+        // Недопустимый символ в данных
+        let broken = lines[3].replacen(|_| true, "!", 1);
+        assert!(dearmor(&rebuild(3, &broken)).is_err());
+
+        // Измененный, но допустимый символ: ловит CRC-24
+        let first = if lines[3].starts_with('A') { "B" } else { "A" };
+        let changed = format!("{}{}", first, &lines[3][1..]);
+        let err = dearmor(&rebuild(3, &changed)).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"), "{}", err);
+
+        // Контрольная сумма неверной длины
+        let checksum = lines.len() - 2;
+        let err = dearmor(&rebuild(checksum, "=AAAAAAAA")).unwrap_err();
+        assert!(err.to_string().contains("must be 3 bytes"), "{}", err);
+
+        // Нет контрольной суммы или строки END
+        let mut without_checksum = lines.clone();
+        without_checksum.remove(checksum);
+        assert!(dearmor(&without_checksum.join("\n")).is_err());
+        assert!(dearmor(&lines[..lines.len() - 1].join("\n")).is_err());
+        assert!(dearmor("not armored").is_err());
+    }
+}
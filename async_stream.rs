@@ -0,0 +1,142 @@
+//! Адаптер `futures::Stream`, расшифровывающий поток блоков по мере поступления.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+
+use crate::Rc4;
+
+/// Поток, накладывающий гамму на каждый блок внутреннего потока по порядку.
+///
+/// Границы блоков сохраняются, а результат не зависит от того, как данные
+/// были разбиты на блоки. Ошибки внутреннего потока передаются как есть,
+/// состояние шифра на них не продвигается.
+pub struct Rc4Stream<S> {
+    inner: S,
+    cipher: Rc4,
+}
+
+impl<S> Rc4Stream<S> {
+    pub fn into_inner(self) -> (S, Rc4) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<S, E> Stream for Rc4Stream<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                let mut buf = BytesMut::from(&chunk[..]);
+                this.cipher.process(&mut buf);
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            other => other,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Расширение для потоков `Result<Bytes, E>`.
+///
+/// Внутренний поток должен быть `Unpin`; для остальных потоков
+/// достаточно обернуть их в `Box::pin`.
+pub trait Rc4StreamExt: Sized {
+    fn rc4_apply(self, cipher: Rc4) -> Rc4Stream<Self>;
+}
+
+impl<S, E> Rc4StreamExt for S
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+{
+    fn rc4_apply(self, cipher: Rc4) -> Rc4Stream<Self> {
+        Rc4Stream {
+            inner: self,
+            cipher,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::stream::{self, StreamExt};
+
+    fn data() -> Vec<u8> {
+        (0..500u32).map(|n| (n * 13 % 256) as u8).collect()
+    }
+
+    fn split(data: &[u8], sizes: &[usize]) -> Vec<Result<Bytes, &'static str>> {
+        let mut rest = data;
+        let mut chunks = Vec::new();
+        for &size in sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let n = size.min(rest.len());
+            chunks.push(Ok(Bytes::copy_from_slice(&rest[..n])));
+            rest = &rest[n..];
+        }
+        chunks
+    }
+
+    /// Результат не зависит от разбиения на блоки, границы сохраняются
+    #[test]
+    fn test_matches_apply_for_any_chunking() {
+        let expected = Rc4::new(b"Key").apply(data());
+
+        for sizes in [&[1usize][..], &[7, 0, 100], &[500], &[3, 250, 1]] {
+            let chunks = split(&data(), sizes);
+            let lengths: Vec<usize> = chunks.iter().map(|c| c.as_ref().unwrap().len()).collect();
+
+            let output: Vec<Bytes> = block_on(
+                stream::iter(chunks)
+                    .rc4_apply(Rc4::new(b"Key"))
+                    .map(|c| c.unwrap())
+                    .collect(),
+            );
+
+            assert_eq!(output.iter().map(|c| c.len()).collect::<Vec<_>>(), lengths);
+            assert_eq!(output.concat(), expected);
+        }
+    }
+
+    /// Ошибка в середине передается дальше и не сдвигает состояние шифра
+    #[test]
+    fn test_error_item_does_not_advance_cipher() {
+        let data = data();
+        let expected = Rc4::new(b"Key").apply(&data);
+
+        let items = vec![
+            Ok(Bytes::copy_from_slice(&data[..100])),
+            Err("network error"),
+            Ok(Bytes::copy_from_slice(&data[100..])),
+        ];
+        let output: Vec<Result<Bytes, &str>> =
+            block_on(stream::iter(items).rc4_apply(Rc4::new(b"Key")).collect());
+
+        assert_eq!(output[1], Err("network error"));
+        let mut joined = output[0].clone().unwrap().to_vec();
+        joined.extend_from_slice(&output[2].clone().unwrap());
+        assert_eq!(joined, expected);
+    }
+
+    /// Адаптер остается Send, если Send внутренний поток
+    #[test]
+    fn test_stream_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let stream = stream::iter(split(&data(), &[10])).rc4_apply(Rc4::new(b"Key"));
+        assert_send(&stream);
+    }
+}
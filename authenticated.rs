@@ -0,0 +1,219 @@
+//! Потоковая расшифровка с проверкой HMAC-тега в конце потока.
+//!
+//! Формат: `ciphertext || tag`, где `tag = HMAC-SHA256(mac_key, ciphertext)`
+//! (encrypt-then-MAC). Ключ шифрования и ключ MAC должны быть независимыми.
+
+use std::io::{self, Read};
+
+use crate::sha256::HmacSha256;
+use crate::Rc4;
+
+/// Длина HMAC-тега в конце потока.
+pub const HMAC_TAG_LEN: usize = 32;
+
+/// Сколько байт шифротекста перед тегом выдается только после проверки.
+const FINAL_CHUNK: usize = 8 * 1024;
+/// Порция чтения из источника.
+const READ_CHUNK: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Streaming,
+    Verified,
+    Failed,
+}
+
+/// Расшифровывает поток `ciphertext || tag`, проверяя тег по мере чтения.
+///
+/// Чтобы узнать, где кончается шифротекст, читатель держит в буфере
+/// последние `HMAC_TAG_LEN + 8 КиБ` байт источника: тег и последнюю порцию
+/// шифротекста. Эта порция выдается только после того, как источник
+/// закончился и тег совпал; при несовпадении или обрыве потока `read`
+/// возвращает `InvalidData` и больше ничего не выдает.
+///
+/// Более ранние байты выдаются до проверки — иначе пришлось бы держать в
+/// памяти весь поток. Вызывающая сторона должна считать весь вывод
+/// недостоверным, пока `read` не вернул `Ok(0)`, и отбрасывать его при
+/// ошибке (например, писать во временный файл и переименовывать после
+/// успешного завершения).
+pub struct Rc4HmacReader<R: Read> {
+    inner: R,
+    cipher: Rc4,
+    mac: Option<HmacSha256>,
+    /// Прочитанный, но еще не выданный шифротекст вместе с кандидатом в тег.
+    pending: Vec<u8>,
+    /// Проверенный и расшифрованный конец потока.
+    ready: Vec<u8>,
+    ready_pos: usize,
+    state: State,
+}
+
+impl<R: Read> Rc4HmacReader<R> {
+    pub fn new(inner: R, cipher: Rc4, mac_key: &[u8]) -> Self {
+        Rc4HmacReader {
+            inner,
+            cipher,
+            mac: Some(HmacSha256::new(mac_key)),
+            pending: Vec::with_capacity(HMAC_TAG_LEN + FINAL_CHUNK + READ_CHUNK),
+            ready: Vec::new(),
+            ready_pos: 0,
+            state: State::Streaming,
+        }
+    }
+
+    /// Проверен ли тег (весь поток прочитан и подлинен).
+    pub fn is_verified(&self) -> bool {
+        self.state == State::Verified
+    }
+
+    fn fail(&mut self, message: &str) -> io::Error {
+        self.state = State::Failed;
+        self.pending.clear();
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    }
+
+    /// Источник закончился: сверяет тег и готовит последнюю порцию.
+    fn finish(&mut self) -> io::Result<()> {
+        if self.pending.len() < HMAC_TAG_LEN {
+            return Err(self.fail("stream is shorter than its authentication tag"));
+        }
+
+        let (ciphertext, tag) = self.pending.split_at(self.pending.len() - HMAC_TAG_LEN);
+        let mut mac = self.mac.take().expect("tag is verified once");
+        mac.update(ciphertext);
+        if !Rc4::constant_time_equals(&mac.finalize(), tag) {
+            return Err(self.fail("authentication tag mismatch"));
+        }
+
+        self.pending.truncate(self.pending.len() - HMAC_TAG_LEN);
+        self.ready = std::mem::take(&mut self.pending);
+        self.cipher.process(&mut self.ready);
+        self.state = State::Verified;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Rc4HmacReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            match self.state {
+                State::Verified => {
+                    let rest = &self.ready[self.ready_pos..];
+                    let n = rest.len().min(buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    self.ready_pos += n;
+                    return Ok(n);
+                }
+                State::Failed => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "stream failed authentication",
+                    ))
+                }
+                State::Streaming => {}
+            }
+
+            // Все, что лежит перед удерживаемым хвостом, можно выдать
+            let releasable = self
+                .pending
+                .len()
+                .saturating_sub(HMAC_TAG_LEN + FINAL_CHUNK);
+            if releasable > 0 {
+                let n = releasable.min(buf.len());
+                let mac = self.mac.as_mut().expect("mac is present while streaming");
+                mac.update(&self.pending[..n]);
+                buf[..n].copy_from_slice(&self.pending[..n]);
+                self.cipher.process(&mut buf[..n]);
+                self.pending.drain(..n);
+                return Ok(n);
+            }
+
+            let filled = self.pending.len();
+            self.pending.resize(filled + READ_CHUNK, 0);
+            let result = self.inner.read(&mut self.pending[filled..]);
+            self.pending
+                .truncate(filled + result.as_ref().map_or(0, |&n| n));
+            match result {
+                Ok(0) => self.finish()?,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::hmac_sha256;
+
+    fn seal(plaintext: &[u8]) -> Vec<u8> {
+        let mut sealed = Rc4::new(b"enc key").apply(plaintext);
+        let tag = hmac_sha256(b"mac key", &sealed);
+        sealed.extend_from_slice(&tag);
+        sealed
+    }
+
+    fn open(sealed: &[u8], read_size: usize) -> (Vec<u8>, io::Result<()>) {
+        let mut reader = Rc4HmacReader::new(sealed, Rc4::new(b"enc key"), b"mac key");
+        let mut output = Vec::new();
+        let mut buf = vec![0u8; read_size];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => {
+                    assert!(reader.is_verified());
+                    return (output, Ok(()));
+                }
+                Ok(n) => output.extend_from_slice(&buf[..n]),
+                Err(e) => return (output, Err(e)),
+            }
+        }
+    }
+
+    /// Подлинный поток расшифровывается целиком при любом размере чтения
+    #[test]
+    fn test_valid_stream() {
+        for len in [0, 1, 100, FINAL_CHUNK, 100_000] {
+            let plaintext = Rc4::new(b"plaintext").keystream_to_vec(len);
+            let sealed = seal(&plaintext);
+            for read_size in [1, 7, 4096, 65_536] {
+                let (output, result) = open(&sealed, read_size);
+                result.unwrap();
+                assert!(output == plaintext, "len {}, read size {}", len, read_size);
+            }
+        }
+    }
+
+    /// Измененный или обрезанный поток: ошибка, и последняя порция
+    /// открытого текста не выдается
+    #[test]
+    fn test_tampered_and_truncated_streams() {
+        let plaintext = Rc4::new(b"plaintext").keystream_to_vec(50_000);
+        let sealed = seal(&plaintext);
+        let held_back = plaintext.len() - FINAL_CHUNK;
+
+        let mut tampered = sealed.clone();
+        tampered[plaintext.len() - 1] ^= 1;
+        let truncated = &sealed[..sealed.len() - 1];
+        let without_tag = &sealed[..plaintext.len()];
+
+        for broken in [&tampered[..], truncated, without_tag, &sealed[..10]] {
+            let (output, result) = open(broken, 4096);
+            let err = result.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(output.len() <= held_back);
+            assert!(output == plaintext[..output.len()]);
+        }
+
+        // После ошибки читатель больше ничего не выдает
+        let mut reader = Rc4HmacReader::new(&tampered[..], Rc4::new(b"enc key"), b"mac key");
+        let mut sink = Vec::new();
+        assert!(reader.read_to_end(&mut sink).is_err());
+        assert!(reader.read(&mut [0u8; 16]).is_err());
+    }
+}
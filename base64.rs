@@ -0,0 +1,144 @@
+//! Base64 (RFC 4648, стандартный алфавит с дополнением `=`).
+
+use std::fmt;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Ошибка разбора Base64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base64Error {
+    /// Длина не кратна 4.
+    Length(usize),
+    /// Символ вне алфавита в указанной позиции.
+    Character { position: usize, found: char },
+    /// `=` не в конце последней группы или ненулевые лишние биты.
+    Padding,
+}
+
+impl fmt::Display for Base64Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Base64Error::Length(len) => {
+                write!(f, "base64 length {} is not a multiple of 4", len)
+            }
+            Base64Error::Character { position, found } => {
+                write!(
+                    f,
+                    "invalid base64 character {:?} at position {}",
+                    found, position
+                )
+            }
+            Base64Error::Padding => f.write_str("invalid base64 padding"),
+        }
+    }
+}
+
+impl std::error::Error for Base64Error {}
+
+/// Кодирует байты в Base64 с дополнением.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let b = [
+            group[0],
+            *group.get(1).unwrap_or(&0),
+            *group.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        for k in 0..4 {
+            if k <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * k) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Разбирает Base64 без пробелов и переводов строк.
+pub fn decode(text: &str) -> Result<Vec<u8>, Base64Error> {
+    let chars = text.as_bytes();
+    if !chars.len().is_multiple_of(4) {
+        return Err(Base64Error::Length(chars.len()));
+    }
+
+    let mut out = Vec::with_capacity(chars.len() / 4 * 3);
+    let groups = chars.len() / 4;
+    for (g, group) in chars.chunks_exact(4).enumerate() {
+        let padding = group.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && g + 1 != groups) {
+            return Err(Base64Error::Padding);
+        }
+
+        let mut n = 0u32;
+        for (k, &c) in group[..4 - padding].iter().enumerate() {
+            let value = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => {
+                    let position = g * 4 + k;
+                    return Err(Base64Error::Character {
+                        position,
+                        found: text
+                            .get(position..)
+                            .and_then(|rest| rest.chars().next())
+                            .unwrap_or(char::REPLACEMENT_CHARACTER),
+                    });
+                }
+            };
+            n |= (value as u32) << (18 - 6 * k);
+        }
+
+        let bytes = n.to_be_bytes();
+        let len = 3 - padding;
+        // Биты, не попавшие в выходные байты, должны быть нулевыми
+        if bytes[1 + len..].iter().any(|&b| b != 0) {
+            return Err(Base64Error::Padding);
+        }
+        out.extend_from_slice(&bytes[1..1 + len]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Векторы из RFC 4648
+    #[test]
+    fn test_rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(encode(plain.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        }
+    }
+
+    /// Некорректный ввод отклоняется
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("Zm9"), Err(Base64Error::Length(3)));
+        assert!(matches!(
+            decode("Zm!v"),
+            Err(Base64Error::Character {
+                position: 2,
+                found: '!'
+            })
+        ));
+        assert_eq!(decode("Zg==Zm9v"), Err(Base64Error::Padding));
+        assert_eq!(decode("Z==="), Err(Base64Error::Padding));
+        assert_eq!(decode("Zh=="), Err(Base64Error::Padding));
+    }
+}
@@ -0,0 +1,430 @@
+//! Бенчмарки шифра, пригодные для вызова из `main`, тестов и примеров.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::json::Value;
+#[cfg(feature = "unsafe-opt")]
+use crate::PrgaVariant;
+use crate::{xor_in_place, Rc4};
+
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Шифрует буфер каждого размера из `sizes` методом `process` и возвращает
+/// пары (размер в байтах, скорость в МБ/с). Время KSA не учитывается.
+pub fn benchmark(sizes: &[usize], key: &[u8]) -> Vec<(usize, f64)> {
+    sizes
+        .iter()
+        .map(|&size| {
+            let mut buffer = vec![0u8; size];
+            let mut rc4 = Rc4::new(key);
+
+            let start = Instant::now();
+            rc4.process(&mut buffer);
+            let seconds = start.elapsed().as_secs_f64();
+
+            // Не даем оптимизатору выбросить работу
+            std::hint::black_box(&buffer);
+            (size, throughput_mb_s(size, seconds))
+        })
+        .collect()
+}
+
+/// Результаты `benchmark` в виде JSON-объектов
+/// `{"size_bytes":..., "mb_per_s":...}`, по одному на размер. `Display`
+/// каждого объекта — одна строка JSON Lines (так их печатает
+/// `rc4 demo --json`).
+pub fn benchmark_json(sizes: &[usize], key: &[u8]) -> Vec<Value> {
+    benchmark(sizes, key)
+        .into_iter()
+        .map(|(size, mb_s)| {
+            Value::Object(vec![
+                ("size_bytes".to_string(), Value::Number(size as f64)),
+                ("mb_per_s".to_string(), Value::Number(mb_s)),
+            ])
+        })
+        .collect()
+}
+
+/// Оценивает скорость `process` на этой машине: шифрует буфер в `sample_mb`
+/// МиБ (не меньше 1) и возвращает МБ/с. Буфер заранее заполняется, чтобы в
+/// замер не попали первые обращения к страницам; KSA не учитывается.
+/// Пригодно для выбора размера порции во время работы; 1 МиБ измеряется
+/// за миллисекунды, но результат шумнее, чем у больших выборок.
+pub fn measure_throughput_mb_s(key: &[u8], sample_mb: usize) -> f64 {
+    let size = sample_mb.max(1) << 20;
+    let mut buffer = vec![1u8; size];
+    let mut rc4 = Rc4::new(key);
+
+    let start = Instant::now();
+    rc4.process(std::hint::black_box(&mut buffer));
+    let seconds = start.elapsed().as_secs_f64();
+
+    std::hint::black_box(&buffer);
+    throughput_mb_s(size, seconds)
+}
+
+/// Сравнивает `rounds` независимых проходов KSA + `process` по буферу `chunk`
+/// байт с однократной генерацией гаммы и последующим `xor_in_place`.
+/// Возвращает время обоих вариантов в секундах.
+pub fn benchmark_keystream_reuse(rounds: usize, chunk: usize, key: &[u8]) -> (f64, f64) {
+    let candidates: Vec<Vec<u8>> = (0..rounds).map(|n| vec![n as u8; chunk]).collect();
+
+    // Вариант А: для каждого кандидата заново запускаем KSA + process
+    let start = Instant::now();
+    for candidate in &candidates {
+        let mut data = candidate.clone();
+        Rc4::new(key).process(&mut data);
+        std::hint::black_box(&data);
+    }
+    let process_secs = start.elapsed().as_secs_f64();
+
+    // Вариант Б: гамма вычисляется один раз, затем только XOR
+    let start = Instant::now();
+    let keystream = Rc4::new(key).keystream_to_vec(chunk);
+    for candidate in &candidates {
+        let mut data = candidate.clone();
+        xor_in_place(&mut data, &keystream).unwrap();
+        std::hint::black_box(&data);
+    }
+    let xor_secs = start.elapsed().as_secs_f64();
+
+    (process_secs, xor_secs)
+}
+
+/// Длины ключей для сравнения `Rc4::benchmark_ksa` (examples/ksa_timing.rs).
+pub const KSA_TIMING_KEY_LENGTHS: [usize; 5] = [1, 16, 64, 128, 256];
+
+impl Rc4 {
+    /// Среднее время одного KSA с ключом `key` по `iterations` запускам
+    /// (`Duration::ZERO` при нуле запусков). Паникует при недопустимой
+    /// длине ключа, как `Rc4::new`.
+    ///
+    /// KSA — ровно 256 шагов перемешивания при любой длине ключа, поэтому
+    /// время от длины ключа почти не зависит (и уж точно не растет
+    /// квадратично); длина влияет только на индекс `i % key_len`. Чтобы
+    /// оптимизатор не выбросил KSA, байт каждого S-box XOR-ится в общий
+    /// аккумулятор.
+    pub fn benchmark_ksa(key: &[u8], iterations: u32) -> Duration {
+        if iterations == 0 {
+            return Duration::ZERO;
+        }
+
+        let mut acc = 0u8;
+        let start = Instant::now();
+        for n in 0..iterations {
+            let cipher = Rc4::new(std::hint::black_box(key));
+            acc ^= cipher.s[n as u8 as usize];
+        }
+        let total = start.elapsed();
+
+        std::hint::black_box(acc);
+        total / iterations
+    }
+}
+
+/// Сравнивает ядра PRGA: для каждого размера из `sizes` возвращает
+/// (размер, МБ/с безопасного ядра, МБ/с ядра без проверок границ). Оба
+/// ядра измеряются на побайтном пути, лучший из `iterations` проходов.
+/// Только с feature `unsafe-opt`.
+#[cfg(feature = "unsafe-opt")]
+pub fn benchmark_prga_variants(sizes: &[usize], iterations: usize) -> Vec<(usize, f64, f64)> {
+    let measure = |size: usize, variant: PrgaVariant| {
+        let mut buffer = vec![0u8; size];
+        let mut rc4 = Rc4::new(b"BenchmarkKey");
+        let mut best = f64::INFINITY;
+        for _ in 0..iterations.max(1) {
+            let start = Instant::now();
+            rc4.process_bytes_with(std::hint::black_box(&mut buffer), variant);
+            best = best.min(start.elapsed().as_secs_f64());
+        }
+        std::hint::black_box(&buffer);
+        size as f64 / MB / best
+    };
+
+    sizes
+        .iter()
+        .map(|&size| {
+            let checked = measure(size, PrgaVariant::Checked);
+            let unchecked = measure(size, PrgaVariant::Unchecked);
+            (size, checked, unchecked)
+        })
+        .collect()
+}
+
+/// Результаты `run_report` для одного размера буфера.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeResult {
+    pub size: usize,
+    pub iterations: usize,
+    /// Время каждого прохода в секундах.
+    pub seconds: Vec<f64>,
+    pub min_mb_s: f64,
+    pub median_mb_s: f64,
+    pub mean_mb_s: f64,
+    pub stddev_mb_s: f64,
+}
+
+/// Машиночитаемый отчет бенчмарка: `to_json`, `to_csv`, а `Display` —
+/// таблица для человека. С feature `serde` реализует
+/// `Serialize`/`Deserialize` с теми же именами полей, что и в `to_json`, и
+/// читает JSON обратно через `from_json`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchReport {
+    pub crate_version: String,
+    /// Модель процессора, если ее удалось определить (Linux: /proc/cpuinfo).
+    pub cpu_model: Option<String>,
+    /// Время `Rc4::new` для 16-байтового ключа в наносекундах.
+    pub ksa_ns: f64,
+    pub results: Vec<SizeResult>,
+}
+
+/// Измеряет `process` для каждого размера из `sizes` по `iterations` проходов.
+/// Буфер выделяется и затрагивается заранее, поэтому в замер не попадают
+/// аллокация и первые обращения к страницам.
+pub fn run_report(sizes: &[usize], iterations: usize) -> BenchReport {
+    let results = sizes
+        .iter()
+        .map(|&size| {
+            let mut buffer = vec![0u8; size];
+            let mut rc4 = Rc4::new(b"BenchmarkKey");
+            rc4.process(&mut buffer);
+
+            let seconds: Vec<f64> = (0..iterations)
+                .map(|_| {
+                    let start = Instant::now();
+                    rc4.process(&mut buffer);
+                    let elapsed = start.elapsed().as_secs_f64();
+                    std::hint::black_box(&buffer);
+                    elapsed
+                })
+                .collect();
+
+            let mut speeds: Vec<f64> = seconds.iter().map(|&t| throughput_mb_s(size, t)).collect();
+            speeds.sort_by(f64::total_cmp);
+            let mean = speeds.iter().sum::<f64>() / speeds.len().max(1) as f64;
+            let variance =
+                speeds.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / speeds.len().max(1) as f64;
+            let median = match speeds.len() {
+                0 => 0.0,
+                n if n % 2 == 1 => speeds[n / 2],
+                n => (speeds[n / 2 - 1] + speeds[n / 2]) / 2.0,
+            };
+
+            SizeResult {
+                size,
+                iterations,
+                seconds,
+                min_mb_s: speeds.first().copied().unwrap_or(0.0),
+                median_mb_s: median,
+                mean_mb_s: mean,
+                stddev_mb_s: variance.sqrt(),
+            }
+        })
+        .collect();
+
+    BenchReport {
+        crate_version: option_env!("CARGO_PKG_VERSION")
+            .unwrap_or("unknown")
+            .to_string(),
+        cpu_model: cpu_model(),
+        ksa_ns: Rc4::benchmark_ksa(&[0x5a; 16], 10_000).as_secs_f64() * 1e9,
+        results,
+    }
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("model name")?.split_once(':'))
+        .map(|(_, model)| model.trim().to_string())
+}
+
+impl BenchReport {
+    pub fn to_json(&self) -> String {
+        let number = |n: f64| Value::Number(n);
+        let results = self
+            .results
+            .iter()
+            .map(|r| {
+                Value::Object(vec![
+                    ("size".to_string(), number(r.size as f64)),
+                    ("iterations".to_string(), number(r.iterations as f64)),
+                    (
+                        "seconds".to_string(),
+                        Value::Array(r.seconds.iter().map(|&t| number(t)).collect()),
+                    ),
+                    ("min_mb_s".to_string(), number(r.min_mb_s)),
+                    ("median_mb_s".to_string(), number(r.median_mb_s)),
+                    ("mean_mb_s".to_string(), number(r.mean_mb_s)),
+                    ("stddev_mb_s".to_string(), number(r.stddev_mb_s)),
+                ])
+            })
+            .collect();
+
+        Value::Object(vec![
+            (
+                "crate_version".to_string(),
+                Value::String(self.crate_version.clone()),
+            ),
+            (
+                "cpu_model".to_string(),
+                self.cpu_model.clone().map_or(Value::Null, Value::String),
+            ),
+            ("ksa_ns".to_string(), number(self.ksa_ns)),
+            ("results".to_string(), Value::Array(results)),
+        ])
+        .to_string()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<BenchReport, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// CSV с заголовком: строка на каждый размер, общие поля повторяются,
+    /// времена проходов перечислены через `;`.
+    pub fn to_csv(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+        let mut csv = String::from(
+            "size,iterations,min_mb_s,median_mb_s,mean_mb_s,stddev_mb_s,seconds,\
+             ksa_ns,crate_version,cpu_model\n",
+        );
+        for r in &self.results {
+            let seconds: Vec<String> = r.seconds.iter().map(f64::to_string).collect();
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}\n",
+                r.size,
+                r.iterations,
+                r.min_mb_s,
+                r.median_mb_s,
+                r.mean_mb_s,
+                r.stddev_mb_s,
+                seconds.join(";"),
+                self.ksa_ns,
+                quote(&self.crate_version),
+                quote(self.cpu_model.as_deref().unwrap_or("")),
+            ));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "rc4 {} on {}",
+            self.crate_version,
+            self.cpu_model.as_deref().unwrap_or("unknown CPU")
+        )?;
+        writeln!(f, "KSA (16-byte key): {:.1} ns", self.ksa_ns)?;
+        writeln!(
+            f,
+            "{:>12} | {:>5} | {:>10} | {:>10} | {:>10} | {:>8}",
+            "Size (KB)", "Iters", "Min MB/s", "Median", "Mean", "Stddev"
+        )?;
+        for r in &self.results {
+            writeln!(
+                f,
+                "{:>12} | {:>5} | {:>10.2} | {:>10.2} | {:>10.2} | {:>8.2}",
+                r.size / 1024,
+                r.iterations,
+                r.min_mb_s,
+                r.median_mb_s,
+                r.mean_mb_s,
+                r.stddev_mb_s
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn throughput_mb_s(size: usize, seconds: f64) -> f64 {
+    // Для очень маленьких буферов таймер может вернуть 0
+    (size as f64 / MB) / seconds.max(1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Бенчмарк на 1 КБ возвращает одну запись с положительной скоростью
+    #[test]
+    fn test_benchmark_smoke() {
+        let results = benchmark(&[1024], b"BenchmarkKey");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, 1024);
+        assert!(results[0].1 > 0.0);
+    }
+
+    /// Строки JSON Lines бенчмарка разбираются и содержат оба поля
+    #[test]
+    fn test_benchmark_json_lines() {
+        let lines: Vec<String> = benchmark_json(&[1024, 4096], b"BenchmarkKey")
+            .iter()
+            .map(Value::to_string)
+            .collect();
+        assert_eq!(lines.len(), 2);
+        for (line, size) in lines.iter().zip([1024, 4096]) {
+            assert!(!line.contains('\n'));
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["size_bytes"].as_u64(), Some(size));
+            let mb_s = value["mb_per_s"].as_f64().unwrap();
+            assert!(mb_s > 0.0 && mb_s.is_finite());
+        }
+    }
+
+    /// Среднее время KSA положительно; без запусков — ноль
+    #[test]
+    fn test_benchmark_ksa_smoke() {
+        for key_len in KSA_TIMING_KEY_LENGTHS {
+            assert!(Rc4::benchmark_ksa(&[0x5a; 256][..key_len], 100) > Duration::ZERO);
+        }
+        assert_eq!(Rc4::benchmark_ksa(b"Key", 0), Duration::ZERO);
+    }
+
+    /// Оценка скорости на 1 МиБ положительна и конечна
+    #[test]
+    fn test_measure_throughput_smoke() {
+        let mb_s = measure_throughput_mb_s(b"BenchmarkKey", 1);
+        assert!(mb_s > 0.0 && mb_s.is_finite());
+        assert!(measure_throughput_mb_s(b"BenchmarkKey", 0) > 0.0);
+    }
+
+    /// Отчет крошечного бенчмарка: статистика, JSON, CSV и таблица
+    #[test]
+    fn test_report_formats() {
+        let report = run_report(&[1024, 4096], 3);
+        assert_eq!(report.results.len(), 2);
+        for r in &report.results {
+            assert_eq!(r.iterations, 3);
+            assert_eq!(r.seconds.len(), 3);
+            assert!(r.min_mb_s > 0.0 && r.min_mb_s <= r.median_mb_s);
+            assert!(r.median_mb_s > 0.0 && r.mean_mb_s > 0.0);
+        }
+        assert!(report.ksa_ns > 0.0);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["results"][1]["size"].as_u64(), Some(4096));
+        assert_eq!(json["results"][0]["seconds"].as_array().unwrap().len(), 3);
+        assert_eq!(json["ksa_ns"].as_f64(), Some(report.ksa_ns));
+
+        let csv = report.to_csv();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.starts_with("size,iterations,"));
+        assert!(report.to_string().contains("Median"));
+    }
+
+    /// JSON-отчет читается обратно без потерь
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_report_json_roundtrip() {
+        let report = run_report(&[1024, 4096], 3);
+        assert_eq!(BenchReport::from_json(&report.to_json()).unwrap(), report);
+        assert!(BenchReport::from_json("{}").is_err());
+    }
+}
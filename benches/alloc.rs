@@ -0,0 +1,69 @@
+//! Сколько байт выделяется в куче при записи шифротекста в `io::sink()`:
+//! `apply` + `write_all` против `apply_to_writer`.
+//!
+//! ```text
+//! cargo bench --bench alloc
+//! ```
+//!
+//! Счетчик — глобальный аллокатор этого бинарника; библиотека его не
+//! видит. Проверка того же утверждения — тест `tests/alloc.rs`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rust_rc4::Rc4;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+struct Counting;
+
+// SAFETY: все вызовы передаются System без изменений
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Байт, запрошенных у аллокатора за время `f`. Других потоков в
+/// процессе нет, поэтому счетчик общий.
+fn allocated_by(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    f();
+    ALLOCATED.load(Ordering::Relaxed) - before
+}
+
+fn main() {
+    println!(
+        "{:>10} | {:>16} | {:>16}",
+        "Size (KB)", "apply+write_all", "apply_to_writer"
+    );
+    for size in [64 * 1024, 16 * 1024 * 1024] {
+        let data = vec![0u8; size];
+        let mut sink = io::sink();
+
+        let apply = allocated_by(|| {
+            let ciphertext = Rc4::new(b"BenchmarkKey").apply(&data);
+            sink.write_all(&ciphertext).unwrap();
+        });
+        let writer = allocated_by(|| {
+            Rc4::new(b"BenchmarkKey")
+                .apply_to_writer(&data, &mut sink)
+                .unwrap();
+        });
+        println!("{:>10} | {:>16} | {:>16}", size / 1024, apply, writer);
+    }
+}
@@ -0,0 +1,91 @@
+//! Стоимость расписания ключа (KSA) для коротких сообщений по длинам ключа.
+//!
+//! ```text
+//! cargo bench --bench key_schedule
+//! cargo bench --bench key_schedule --features rekeying
+//! ```
+//!
+//! Без внешних зависимостей: лучший из `ROUNDS` проходов по `Instant`, в
+//! наносекундах на операцию. Ключи и сообщение — фиксированные массивы,
+//! поэтому их подготовка в замер не попадает.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rust_rc4::Rc4;
+
+/// WEP-40, WEP-104, 128 бит, 256 бит, 104 байта и максимальная длина.
+const KEY_LENGTHS: [usize; 6] = [5, 13, 16, 32, 104, 256];
+const ROUNDS: usize = 7;
+const ITERATIONS: usize = 100_000;
+
+fn best_ns(mut run: impl FnMut()) -> f64 {
+    let mut best = f64::INFINITY;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            run();
+        }
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    best * 1e9 / ITERATIONS as f64
+}
+
+fn main() {
+    let key_material: [u8; 256] = std::array::from_fn(|n| (n as u8).wrapping_mul(167) ^ 0x5a);
+    let message = [0x42u8; 64];
+
+    print!(
+        "{:>8} | {:>8} | {:>8} | {:>10} | {:>12}",
+        "Key len", "new", "rekey", "new + 64 B", "clone + 64 B"
+    );
+    if cfg!(feature = "rekeying") {
+        print!(" | {:>16}", "rekey_with_nonce");
+    }
+    println!();
+
+    for key_len in KEY_LENGTHS {
+        let key = &key_material[..key_len];
+
+        let new = best_ns(|| {
+            black_box(Rc4::new(black_box(key)));
+        });
+
+        let mut cipher = Rc4::new(key);
+        let rekey = best_ns(|| {
+            cipher.rekey(black_box(key)).unwrap();
+            black_box(&cipher);
+        });
+
+        let new_and_encrypt = best_ns(|| {
+            let mut data = message;
+            Rc4::new(black_box(key)).process(&mut data);
+            black_box(&data);
+        });
+
+        let precomputed = Rc4::new(key);
+        let clone_and_encrypt = best_ns(|| {
+            let mut data = message;
+            black_box(&precomputed).clone().process(&mut data);
+            black_box(&data);
+        });
+
+        print!(
+            "{:>8} | {:>8.1} | {:>8.1} | {:>10.1} | {:>12.1}",
+            key_len, new, rekey, new_and_encrypt, clone_and_encrypt
+        );
+        #[cfg(feature = "rekeying")]
+        {
+            let mut n = 0u32;
+            let rekey_with_nonce = best_ns(|| {
+                n = n.wrapping_add(1);
+                cipher
+                    .rekey_with_nonce(&n.to_le_bytes()[..key_len.min(4)])
+                    .unwrap();
+                black_box(&cipher);
+            });
+            print!(" | {:>16.1}", rekey_with_nonce);
+        }
+        println!();
+    }
+}
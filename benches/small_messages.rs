@@ -0,0 +1,53 @@
+//! Накладные расходы `process` на коротких сообщениях (16, 32 и 64 байта)
+//! и контроль, что большой буфер не стал медленнее.
+//!
+//! ```text
+//! cargo bench --bench small_messages
+//! ```
+//!
+//! Без внешних зависимостей: лучший из `ROUNDS` проходов по `Instant`. Для
+//! коротких сообщений время определяется вызовом и загрузкой/сохранением
+//! состояния, а не самим PRGA.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rust_rc4::Rc4;
+
+const ROUNDS: usize = 7;
+const CALLS: usize = 2_000_000;
+const MB: f64 = 1024.0 * 1024.0;
+
+fn best_secs(mut run: impl FnMut()) -> f64 {
+    let mut best = f64::INFINITY;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    best
+}
+
+fn main() {
+    println!("{:>8} | {:>12}", "Size (B)", "ns per call");
+    for size in [16, 32, 64] {
+        let mut message = [0u8; 64];
+        let message = &mut message[..size];
+        let mut cipher = Rc4::new(b"BenchmarkKey");
+        let secs = best_secs(|| {
+            for _ in 0..CALLS {
+                cipher.process(black_box(&mut *message));
+            }
+        });
+        println!("{:>8} | {:>12.2}", size, secs * 1e9 / CALLS as f64);
+    }
+
+    let mut buffer = vec![0u8; 1 << 20];
+    let mut cipher = Rc4::new(b"BenchmarkKey");
+    let secs = best_secs(|| cipher.process(black_box(&mut buffer)));
+    println!(
+        "{:>8} | {:>12.1} MB/s",
+        "1 MiB",
+        buffer.len() as f64 / MB / secs
+    );
+}
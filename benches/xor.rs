@@ -0,0 +1,57 @@
+//! Повторное `process` против однократной гаммы и `xor_in_place` много раз.
+//!
+//! ```text
+//! cargo bench --bench xor
+//! cargo bench --bench xor --features simd
+//! ```
+//!
+//! Без внешних зависимостей: лучший из `ROUNDS` проходов по `Instant`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rust_rc4::{xor_in_place, Rc4};
+
+const MB: f64 = 1024.0 * 1024.0;
+const ROUNDS: usize = 7;
+/// Байт на проход: короткие буферы повторяются до этого объема.
+const BYTES_PER_ROUND: usize = 16 << 20;
+
+fn best_mb_s(size: usize, mut run: impl FnMut()) -> f64 {
+    let repeats = (BYTES_PER_ROUND / size).max(1);
+    let mut best = f64::INFINITY;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..repeats {
+            run();
+        }
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    (size * repeats) as f64 / MB / best
+}
+
+fn main() {
+    println!(
+        "xor_in_place: {}",
+        if cfg!(feature = "simd") {
+            "simd"
+        } else {
+            "u64 words"
+        }
+    );
+    println!(
+        "{:>10} | {:>14} | {:>18}",
+        "Size (B)", "process MB/s", "xor_in_place MB/s"
+    );
+    for size in [64, 1024, 64 * 1024, 1 << 20] {
+        let mut data = vec![0x5au8; size];
+        let mut cipher = Rc4::new(b"BenchmarkKey");
+        let process = best_mb_s(size, || cipher.process(black_box(&mut data)));
+
+        let keystream = Rc4::new(b"BenchmarkKey").keystream_to_vec(size);
+        let xor = best_mb_s(size, || {
+            xor_in_place(black_box(&mut data), black_box(&keystream)).unwrap()
+        });
+        println!("{:>10} | {:>14.1} | {:>18.1}", size, process, xor);
+    }
+}
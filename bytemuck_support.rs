@@ -0,0 +1,90 @@
+//! Шифрование POD-структур через крейт `bytemuck`.
+
+use bytemuck::Pod;
+
+use crate::{Rc4, Rc4Error};
+
+impl Rc4 {
+    /// Шифрует байтовое представление `value` (как `bytemuck::bytes_of`).
+    pub fn encrypt_struct<T: Pod>(&mut self, value: &T) -> Vec<u8> {
+        self.apply(bytemuck::bytes_of(value))
+    }
+
+    /// Расшифровывает `ciphertext` в значение `T`. Длина проверяется до
+    /// расшифровки: при несовпадении с `size_of::<T>()` возвращается
+    /// `SizeMismatch`, а состояние шифра не меняется. Выравнивание
+    /// `ciphertext` не требуется.
+    pub fn decrypt_struct<T: Pod>(&mut self, ciphertext: &[u8]) -> Result<T, Rc4Error> {
+        let expected = std::mem::size_of::<T>();
+        if ciphertext.len() != expected {
+            return Err(Rc4Error::SizeMismatch {
+                len: ciphertext.len(),
+                expected,
+            });
+        }
+        let mut value = T::zeroed();
+        let bytes = bytemuck::bytes_of_mut(&mut value);
+        bytes.copy_from_slice(ciphertext);
+        self.process(bytes);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Zeroable, Pod)]
+    #[repr(C)]
+    struct Packet {
+        sequence: u32,
+        flags: u16,
+        kind: u8,
+        ttl: u8,
+        position: [f32; 3],
+        payload: [u8; 8],
+    }
+
+    /// Структура переживает шифрование и расшифровку поле в поле
+    #[test]
+    fn test_struct_roundtrip() {
+        let packet = Packet {
+            sequence: 0xdead_beef,
+            flags: 0x0102,
+            kind: 7,
+            ttl: 64,
+            position: [1.5, -2.25, 1e9],
+            payload: *b"payload!",
+        };
+
+        let ciphertext = Rc4::new(b"Key").encrypt_struct(&packet);
+        assert_eq!(ciphertext.len(), std::mem::size_of::<Packet>());
+        assert!(ciphertext[..] != bytemuck::bytes_of(&packet)[..]);
+
+        let decrypted: Packet = Rc4::new(b"Key").decrypt_struct(&ciphertext).unwrap();
+        assert_eq!(decrypted.sequence, packet.sequence);
+        assert_eq!(decrypted.flags, packet.flags);
+        assert_eq!(decrypted.kind, packet.kind);
+        assert_eq!(decrypted.ttl, packet.ttl);
+        assert_eq!(decrypted.position, packet.position);
+        assert_eq!(decrypted.payload, packet.payload);
+    }
+
+    /// Неверная длина отклоняется до расшифровки и не сдвигает поток
+    #[test]
+    fn test_decrypt_struct_size_mismatch() {
+        let mut cipher = Rc4::new(b"Key");
+        let ciphertext = Rc4::new(b"Key").encrypt_struct(&0x1234_5678u32);
+
+        assert_eq!(
+            cipher.decrypt_struct::<u64>(&ciphertext),
+            Err(Rc4Error::SizeMismatch {
+                len: 4,
+                expected: 8
+            })
+        );
+        assert_eq!(cipher.bytes_processed(), 0);
+        assert_eq!(cipher.decrypt_struct::<u32>(&ciphertext), Ok(0x1234_5678));
+    }
+}
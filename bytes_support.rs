@@ -0,0 +1,98 @@
+//! Интеграция с буферами крейта `bytes`.
+
+use bytes::buf::Chain;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::Rc4;
+
+impl Rc4 {
+    /// Шифрует содержимое `BytesMut` на месте.
+    pub fn process_bytes(&mut self, buf: &mut BytesMut) {
+        self.process(buf);
+    }
+
+    /// Шифрует цепочку из двух буферов как единый поток, сегмент за сегментом,
+    /// без склеивания в непрерывный буфер.
+    pub fn process_chain(&mut self, chain: &mut Chain<BytesMut, BytesMut>) {
+        self.process(chain.first_mut());
+        self.process(chain.last_mut());
+    }
+
+    /// Шифрует `src` и дописывает шифротекст в `dst`.
+    ///
+    /// `dst` может быть несмежным (например, `chain_mut` из нескольких
+    /// буферов): запись идет через `put_slice`, который заполняет каждый
+    /// `chunk_mut` по очереди. Промежуточный буфер — 8 КБ на стеке.
+    /// Паникует, если в `dst` не хватает места (как и `BufMut::put_slice`).
+    pub fn put_encrypted<B: BufMut + ?Sized>(&mut self, src: &[u8], dst: &mut B) {
+        let mut scratch = [0u8; 8192];
+        for chunk in src.chunks(scratch.len()) {
+            let buf = &mut scratch[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            self.process(buf);
+            dst.put_slice(buf);
+        }
+    }
+
+    /// Аллоцирующий вариант: возвращает шифротекст как `Bytes`.
+    pub fn encrypt_to_bytes(&mut self, src: &[u8]) -> Bytes {
+        Bytes::from(self.apply(src))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Buf;
+
+    fn plaintext() -> Vec<u8> {
+        (0..300u32).map(|n| (n % 7 + n / 3) as u8).collect()
+    }
+
+    /// Цепочка из двух BytesMut дает тот же результат, что и непрерывный буфер
+    #[test]
+    fn test_process_chain_matches_contiguous() {
+        let data = plaintext();
+        let expected = Rc4::new(b"Key").apply(&data);
+
+        let mut chain = BytesMut::from(&data[..123]).chain(BytesMut::from(&data[123..]));
+        Rc4::new(b"Key").process_chain(&mut chain);
+
+        let mut actual = BytesMut::new();
+        actual.put(chain);
+        assert_eq!(&actual[..], &expected[..]);
+    }
+
+    /// Запись в несмежный BufMut совпадает с непрерывным случаем
+    #[test]
+    fn test_put_encrypted_into_chain() {
+        let data = plaintext();
+        let expected = Rc4::new(b"Key").apply(&data);
+
+        let mut first = [0u8; 10];
+        let mut second = vec![0u8; data.len() - 10];
+        {
+            let mut dst = (&mut first[..]).chain_mut(&mut second[..]);
+            Rc4::new(b"Key").put_encrypted(&data, &mut dst);
+        }
+
+        assert_eq!(first[..], expected[..10]);
+        assert_eq!(second[..], expected[10..]);
+    }
+
+    /// process_bytes и encrypt_to_bytes согласованы с apply
+    #[test]
+    fn test_bytes_helpers() {
+        let data = plaintext();
+        let expected = Rc4::new(b"Key").apply(&data);
+
+        let mut buf = BytesMut::from(&data[..]);
+        Rc4::new(b"Key").process_bytes(&mut buf);
+        assert_eq!(&buf[..], &expected[..]);
+
+        assert_eq!(
+            Rc4::new(b"Key").encrypt_to_bytes(&data),
+            Bytes::from(expected)
+        );
+    }
+}
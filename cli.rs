@@ -0,0 +1,1795 @@
+//! Командная строка: шифрование и расшифровка файлов.
+//!
+//! ```text
+//! rc4 encrypt --key-file k.bin [--suffix .rc4] [--threads N] [--force] FILE...
+//! rc4 decrypt --key-file k.bin [--glob 'logs/**/*.rc4'] FILE...
+//! rc4 encrypt --key-file k.bin -o out.rc4 FILE
+//! rc4 decrypt --key-env RC4_KEY FILE
+//! rc4 encrypt --key-file k.bin --armor -o note.asc note.txt
+//! tar cf - dir | rc4 encrypt --key-file k.bin | ssh host 'cat > backup.rc4'
+//! rc4 bench --json --sizes 65536,1048576 --iterations 10
+//! rc4 keystream --key fixture-seed 1048576 > testdata.bin
+//! ```
+//!
+//! Ключ лучше передавать через файл, переменную окружения или `--prompt`:
+//! `--key TEXT` виден в истории оболочки и в выводе `ps`.
+//!
+//! Файлы обрабатываются параллельно; ошибка на одном файле не прерывает
+//! остальные. Код возврата ненулевой, если хотя бы один файл не обработан.
+//! Результат сначала пишется во временный файл и появляется под целевым
+//! именем только после успешного завершения (см. `process_to_path`).
+//!
+//! Вход `-` (или отсутствие входов) — stdin, выход `-` — stdout; для stdin
+//! выход по умолчанию — stdout. Поток идет через буфер фиксированного
+//! размера, поэтому память не зависит от объема данных. В stdout попадают
+//! только данные: вся диагностика идет в stderr. Если читатель закрыл канал
+//! раньше времени, процесс молча завершается с кодом 0, как `keystream`.
+//!
+//! Подробность диагностики задают `-q` (только ошибки), `-v` (параметры
+//! обработки) и `-vv` (контрольные точки `--resume`); ключ в диагностику
+//! не попадает ни на каком уровне.
+//!
+//! С `--checksum` к открытому тексту перед шифрованием дописывается его
+//! CRC-32, и расшифровка с `--checksum` проверяет ее (код возврата 2 при
+//! несовпадении): так обнаруживаются неверный ключ и случайные повреждения.
+//! От намеренного изменения шифротекста CRC не защищает.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::LevelFilter;
+
+use crate::armor::{self, ArmorReader, ArmorWriter};
+use crate::crc32::Crc32;
+use crate::{bench, hex, resume, Rc4};
+
+/// Код возврата при ошибке в аргументах командной строки (EX_USAGE).
+pub const EXIT_USAGE: i32 = 64;
+/// Код возврата, если хотя бы один файл не удалось обработать.
+pub const EXIT_FAILURE: i32 = 1;
+/// Код возврата, если контрольная сумма хотя бы одного файла не совпала.
+pub const EXIT_CHECKSUM: i32 = 2;
+
+const DEFAULT_SUFFIX: &str = ".rc4";
+const BUFFER_SIZE: usize = 256 * 1024;
+/// Имя входа и выхода для stdin/stdout.
+const STDIO: &str = "-";
+
+const DEFAULT_BENCH_SIZES: [usize; 3] = [64 * 1024, 1024 * 1024, 16 * 1024 * 1024];
+const DEFAULT_BENCH_ITERATIONS: usize = 5;
+
+const USAGE: &str = "\
+usage: rc4 <encrypt|decrypt> KEY-SOURCE [options] [FILE... | -]
+       rc4 bench [--json | --csv] [--sizes N,N,...] [--iterations N]
+       rc4 keystream (--key TEXT | --key-file PATH | --key-env VAR) LENGTH
+       rc4 demo [--json]
+
+key sources (exactly one):
+  --key-file PATH   read the key from PATH
+  --key-env VAR     use the value of environment variable VAR (UTF-8 bytes)
+  --prompt          read the key from the terminal (twice when encrypting)
+  --key TEXT        use TEXT (UTF-8 bytes); visible in shell history and ps
+
+options:
+  --key-file-format raw|hex
+                    how to read --key-file: raw bytes (default) or hex digits
+  -o, --output PATH output path (single input only); - is stdout, the default
+                    when reading stdin (- or no FILE)
+  --suffix SUFFIX   output suffix; encrypt appends it, decrypt strips it (default .rc4)
+  --glob PATTERN    add inputs matching PATTERN (*, ?, [..], ** supported); repeatable
+  --threads N       number of worker threads (default: CPU count)
+  --force           overwrite existing output files
+  --no-atomic       write directly to the output instead of a temporary file
+                    renamed into place on success
+  --resume          record progress in <output>.progress and continue an
+                    interrupted run from the last checkpoint
+  --checkpoint-mib N  progress checkpoint interval for --resume (default 64)
+  --armor           (encrypt) write ASCII-armored Base64 instead of binary
+  --input-encoding auto|binary|armor
+                    (decrypt) input format; auto detects armor by leading dashes
+  --checksum        (encrypt) append a CRC-32 of the plaintext inside the ciphertext;
+                    (decrypt) verify and strip it, exit code 2 on mismatch
+  --no-verify       (decrypt) strip the checksum without verifying it
+  -q, --quiet       report errors only
+  -v, --verbose     also report processing parameters; -vv adds checkpoints
+
+keystream:
+  writes LENGTH bytes of keystream to stdout: reproducible pseudo-random test
+  data (the same key always gives the same bytes); not for secrets
+
+bench options:
+  --json, --csv     machine-readable report instead of a table
+  --sizes N,N,...   buffer sizes in bytes (default 65536,1048576,16777216)
+  --iterations N    passes per buffer size (default 5)
+
+demo options:
+  --json            print only the buffer-size benchmark, one JSON object
+                    ({\"size_bytes\":N,\"mb_per_s\":X}) per line";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Encrypt,
+    Decrypt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    Text(String),
+    File(PathBuf, KeyFileFormat),
+    Env(String),
+    Prompt,
+}
+
+impl KeySource {
+    /// Откуда взят ключ — для диагностики, без самого ключа.
+    fn describe(&self) -> String {
+        match self {
+            KeySource::Text(_) => "--key".to_string(),
+            KeySource::File(path, KeyFileFormat::Raw) => format!("file {}", path.display()),
+            KeySource::File(path, KeyFileFormat::Hex) => {
+                format!("hex file {}", path.display())
+            }
+            KeySource::Env(var) => format!("environment variable {}", var),
+            KeySource::Prompt => "the terminal".to_string(),
+        }
+    }
+}
+
+/// Контрольная сумма открытого текста в конце шифротекста (`--checksum`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumMode {
+    /// Данные без контрольной суммы.
+    None,
+    /// Шифрование: дописать CRC-32 открытого текста.
+    Append,
+    /// Расшифровка: проверить и отрезать CRC-32.
+    Verify,
+    /// Расшифровка: отрезать CRC-32 без проверки (восстановление
+    /// поврежденных данных).
+    Strip,
+}
+
+/// Контрольная сумма расшифрованных данных не совпала.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumMismatch;
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("wrong key or corrupted data (checksum mismatch)")
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+fn is_checksum_mismatch(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<ChecksumMismatch>())
+}
+
+/// Формат файла ключа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFileFormat {
+    /// Содержимое файла целиком и есть ключ.
+    Raw,
+    /// Шестнадцатеричные цифры; пробельные символы и переводы строк
+    /// (в том числе CRLF) игнорируются.
+    Hex,
+}
+
+/// Формат входных файлов при расшифровке.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// Броня, если файл начинается с дефисов, иначе двоичный шифротекст.
+    Auto,
+    Binary,
+    Armor,
+}
+
+/// Байты ключа, затираемые при освобождении. С feature `secrecy` ключ с
+/// момента чтения хранится в `secrecy::SecretSlice`, который затирает себя
+/// сам; без нее — в `Vec`, затираемом здесь.
+#[cfg(not(feature = "secrecy"))]
+pub struct KeyBuffer(Vec<u8>);
+#[cfg(feature = "secrecy")]
+pub struct KeyBuffer(secrecy::SecretSlice<u8>);
+
+impl KeyBuffer {
+    #[cfg(not(feature = "secrecy"))]
+    fn new(bytes: Vec<u8>) -> Self {
+        KeyBuffer(bytes)
+    }
+
+    /// `Vec::into_boxed_slice` перевыделяет память при лишней емкости и
+    /// освобождает старый буфер незатертым, поэтому такой ключ копируется
+    /// в буфер точного размера, а исходный затирается.
+    #[cfg(feature = "secrecy")]
+    fn new(mut bytes: Vec<u8>) -> Self {
+        use zeroize::Zeroize;
+
+        let exact: Box<[u8]> = if bytes.capacity() == bytes.len() {
+            bytes.into_boxed_slice()
+        } else {
+            let exact = Box::from(&bytes[..]);
+            bytes.zeroize();
+            exact
+        };
+        KeyBuffer(secrecy::SecretBox::new(exact))
+    }
+
+    #[cfg(not(feature = "secrecy"))]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[cfg(feature = "secrecy")]
+    pub fn as_bytes(&self) -> &[u8] {
+        secrecy::ExposeSecret::expose_secret(&self.0)
+    }
+}
+
+#[cfg(not(feature = "secrecy"))]
+impl Drop for KeyBuffer {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            // Волатильная запись не удаляется оптимизатором как "мертвая"
+            unsafe { std::ptr::write_volatile(byte, 0) };
+        }
+        std::sync::atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Разобранные аргументы команд `encrypt`/`decrypt`.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub mode: Mode,
+    pub key: KeySource,
+    pub inputs: Vec<PathBuf>,
+    pub globs: Vec<String>,
+    pub output: Option<PathBuf>,
+    pub suffix: String,
+    pub threads: usize,
+    pub write: WriteOptions,
+    /// Самый подробный уровень диагностики, который выводится.
+    pub verbosity: LevelFilter,
+}
+
+/// Ошибка разбора аргументов; выводится вместе со справкой.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn usage_error<T>(message: impl Into<String>) -> Result<T, UsageError> {
+    Err(UsageError(message.into()))
+}
+
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, UsageError> {
+        let mode = match args.first().map(String::as_str) {
+            Some("encrypt") => Mode::Encrypt,
+            Some("decrypt") => Mode::Decrypt,
+            Some(other) => return usage_error(format!("unknown command '{}'", other)),
+            None => return usage_error("missing command"),
+        };
+
+        let mut key = None;
+        let mut key_file_format = None;
+        let mut input_encoding = None;
+        let mut checksum = false;
+        let mut no_verify = false;
+        let mut quiet = false;
+        let mut verbose = 0;
+        let mut options = Options {
+            mode,
+            key: KeySource::Text(String::new()),
+            inputs: Vec::new(),
+            globs: Vec::new(),
+            output: None,
+            suffix: DEFAULT_SUFFIX.to_string(),
+            threads: default_threads(),
+            write: WriteOptions::default(),
+            verbosity: LevelFilter::Info,
+        };
+
+        let mut rest = args[1..].iter();
+        while let Some(arg) = rest.next() {
+            let mut value = |name: &str| match rest.next() {
+                Some(v) => Ok(v.clone()),
+                None => usage_error(format!("{} requires a value", name)),
+            };
+
+            match arg.as_str() {
+                "--key-file" | "--key" | "--key-env" | "--prompt" if key.is_some() => {
+                    return usage_error(format!(
+                        "{} conflicts with another key source; \
+                         use only one of --key-file, --key-env, --prompt, --key",
+                        arg
+                    ))
+                }
+                "--key-file" => key = Some(KeySource::File(value(arg)?.into(), KeyFileFormat::Raw)),
+                "--key-env" => key = Some(KeySource::Env(value(arg)?)),
+                "--prompt" => key = Some(KeySource::Prompt),
+                "--key" => key = Some(KeySource::Text(value(arg)?)),
+                "--key-file-format" => {
+                    key_file_format = Some(match value(arg)?.as_str() {
+                        "raw" => KeyFileFormat::Raw,
+                        "hex" => KeyFileFormat::Hex,
+                        other => {
+                            return usage_error(format!(
+                                "--key-file-format expects raw or hex, got '{}'",
+                                other
+                            ))
+                        }
+                    })
+                }
+                "-o" | "--output" => options.output = Some(value(arg)?.into()),
+                "--suffix" => options.suffix = value(arg)?,
+                "--glob" => options.globs.push(value(arg)?),
+                "--threads" => {
+                    options.threads = match value(arg)?.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => return usage_error("--threads expects a positive integer"),
+                    }
+                }
+                "--force" => options.write.force = true,
+                "--no-atomic" => options.write.atomic = false,
+                "--resume" => options.write.resume = true,
+                "--armor" => options.write.armor = true,
+                "--input-encoding" => {
+                    input_encoding = Some(match value(arg)?.as_str() {
+                        "auto" => InputEncoding::Auto,
+                        "binary" => InputEncoding::Binary,
+                        "armor" => InputEncoding::Armor,
+                        other => {
+                            return usage_error(format!(
+                                "--input-encoding expects auto, binary or armor, got '{}'",
+                                other
+                            ))
+                        }
+                    })
+                }
+                "--checkpoint-mib" => {
+                    options.write.checkpoint_every = match value(arg)?.parse::<u64>() {
+                        Ok(n) if n > 0 => n * 1024 * 1024,
+                        _ => return usage_error("--checkpoint-mib expects a positive integer"),
+                    }
+                }
+                "--checksum" => checksum = true,
+                "--no-verify" => no_verify = true,
+                "-q" | "--quiet" => quiet = true,
+                "-v" | "--verbose" => verbose += 1,
+                "-vv" => verbose += 2,
+                "--" => options.inputs.extend(rest.by_ref().map(PathBuf::from)),
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return usage_error(format!("unknown option '{}'", flag))
+                }
+                input => options.inputs.push(input.into()),
+            }
+        }
+
+        options.key = match (key, key_file_format) {
+            (Some(KeySource::File(path, _)), Some(format)) => KeySource::File(path, format),
+            (Some(_), Some(_)) => return usage_error("--key-file-format requires --key-file"),
+            (Some(key), None) => key,
+            (None, _) => {
+                return usage_error("a key is required (--key-file, --key-env, --prompt or --key)")
+            }
+        };
+        if options.suffix.is_empty() {
+            return usage_error("--suffix must not be empty");
+        }
+        if options.inputs.is_empty() && options.globs.is_empty() {
+            options.inputs.push(STDIO.into());
+        }
+        let stdin = options.inputs.iter().any(|input| is_stdio(input));
+        if stdin && (options.inputs.len() != 1 || !options.globs.is_empty()) {
+            return usage_error("- (stdin) cannot be combined with other inputs");
+        }
+        let stdout = options.output.as_deref().is_some_and(is_stdio) || stdin;
+        if options.write.resume && stdout {
+            return usage_error("--resume requires file input and output, not stdin/stdout");
+        }
+        options.write.input_encoding = match (mode, input_encoding) {
+            (Mode::Encrypt, Some(_)) => {
+                return usage_error("--input-encoding is only valid for decrypt")
+            }
+            (Mode::Encrypt, None) => InputEncoding::Binary,
+            (Mode::Decrypt, encoding) => encoding.unwrap_or(InputEncoding::Auto),
+        };
+        if options.write.armor && mode == Mode::Decrypt {
+            return usage_error("--armor is only valid for encrypt");
+        }
+        if options.write.resume && options.write.armor {
+            return usage_error("--resume cannot be combined with --armor");
+        }
+        if options.write.resume && !options.write.atomic {
+            return usage_error("--resume cannot be combined with --no-atomic");
+        }
+        if options.output.is_some() && (options.inputs.len() != 1 || !options.globs.is_empty()) {
+            return usage_error("--output requires exactly one input file");
+        }
+        options.write.checksum = match (mode, checksum, no_verify) {
+            (_, false, false) => ChecksumMode::None,
+            (_, false, true) => return usage_error("--no-verify requires --checksum"),
+            (Mode::Encrypt, true, true) => {
+                return usage_error("--no-verify is only valid for decrypt")
+            }
+            (Mode::Encrypt, true, false) => ChecksumMode::Append,
+            (Mode::Decrypt, true, false) => ChecksumMode::Verify,
+            (Mode::Decrypt, true, true) => ChecksumMode::Strip,
+        };
+        if checksum && options.write.resume {
+            return usage_error("--checksum cannot be combined with --resume");
+        }
+        options.verbosity = match (quiet, verbose) {
+            (true, 0) => LevelFilter::Error,
+            (true, _) => return usage_error("--quiet cannot be combined with --verbose"),
+            (false, 0) => LevelFilter::Info,
+            (false, 1) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        };
+        Ok(options)
+    }
+}
+
+/// Итог обработки одного файла.
+pub struct FileReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub result: io::Result<u64>,
+    pub elapsed: Duration,
+}
+
+/// Формат отчета `rc4 bench`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Параметры `rc4 bench`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchOptions {
+    pub format: ReportFormat,
+    pub sizes: Vec<usize>,
+    pub iterations: usize,
+}
+
+impl BenchOptions {
+    /// Разбирает аргументы после `bench`.
+    pub fn parse(args: &[String]) -> Result<BenchOptions, UsageError> {
+        let mut format = None;
+        let mut options = BenchOptions {
+            format: ReportFormat::Table,
+            sizes: DEFAULT_BENCH_SIZES.to_vec(),
+            iterations: DEFAULT_BENCH_ITERATIONS,
+        };
+
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            let mut value = |name: &str| match rest.next() {
+                Some(v) => Ok(v.clone()),
+                None => usage_error(format!("{} requires a value", name)),
+            };
+
+            match arg.as_str() {
+                "--json" | "--csv" if format.is_some() => {
+                    return usage_error("--json and --csv are mutually exclusive")
+                }
+                "--json" => format = Some(ReportFormat::Json),
+                "--csv" => format = Some(ReportFormat::Csv),
+                "--sizes" => {
+                    options.sizes = value(arg)?
+                        .split(',')
+                        .map(|size| match size.trim().parse() {
+                            Ok(n) if n > 0 => Ok(n),
+                            _ => usage_error("--sizes expects comma-separated positive integers"),
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "--iterations" => {
+                    options.iterations = match value(arg)?.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => return usage_error("--iterations expects a positive integer"),
+                    }
+                }
+                other => return usage_error(format!("unknown bench option '{}'", other)),
+            }
+        }
+
+        options.format = format.unwrap_or(ReportFormat::Table);
+        Ok(options)
+    }
+}
+
+fn run_bench(args: &[String]) -> i32 {
+    let options = match BenchOptions::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            log::error!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let report = bench::run_report(&options.sizes, options.iterations);
+    match options.format {
+        ReportFormat::Table => print!("{}", report),
+        ReportFormat::Json => println!("{}", report.to_json()),
+        ReportFormat::Csv => print!("{}", report.to_csv()),
+    }
+    0
+}
+
+/// Параметры `rc4 keystream`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeystreamOptions {
+    pub key: KeySource,
+    pub length: u64,
+}
+
+impl KeystreamOptions {
+    /// Разбирает аргументы после `keystream`.
+    pub fn parse(args: &[String]) -> Result<KeystreamOptions, UsageError> {
+        let mut key = None;
+        let mut length = None;
+
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            let mut value = |name: &str| match rest.next() {
+                Some(v) => Ok(v.clone()),
+                None => usage_error(format!("{} requires a value", name)),
+            };
+
+            match arg.as_str() {
+                "--key" | "--key-file" | "--key-env" if key.is_some() => {
+                    return usage_error("use only one of --key, --key-file, --key-env")
+                }
+                "--key" => key = Some(KeySource::Text(value(arg)?)),
+                "--key-file" => key = Some(KeySource::File(value(arg)?.into(), KeyFileFormat::Raw)),
+                "--key-env" => key = Some(KeySource::Env(value(arg)?)),
+                flag if flag.starts_with('-') => {
+                    return usage_error(format!("unknown keystream option '{}'", flag))
+                }
+                _ if length.is_some() => return usage_error("keystream takes a single LENGTH"),
+                number => match number.parse() {
+                    Ok(n) => length = Some(n),
+                    Err(_) => return usage_error("LENGTH must be a non-negative integer"),
+                },
+            }
+        }
+
+        Ok(KeystreamOptions {
+            key: key.ok_or(UsageError(
+                "a key is required (--key, --key-file or --key-env)".into(),
+            ))?,
+            length: length.ok_or(UsageError("missing LENGTH".into()))?,
+        })
+    }
+}
+
+/// Пишет `length` байт гаммы `cipher` в `out` порциями по `BUFFER_SIZE`
+/// через `fill_keystream`, не выделяя память под весь объем.
+pub fn write_keystream(cipher: &mut Rc4, mut length: u64, mut out: impl Write) -> io::Result<()> {
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    while length > 0 {
+        let chunk = &mut buffer[..length.min(BUFFER_SIZE as u64) as usize];
+        cipher.fill_keystream(chunk);
+        out.write_all(chunk)?;
+        length -= chunk.len() as u64;
+    }
+    out.flush()
+}
+
+fn run_keystream(args: &[String]) -> i32 {
+    let options = match KeystreamOptions::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            log::error!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    let cipher = load_key(&options.key, Mode::Encrypt).and_then(|key| {
+        Rc4::try_new(key.as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    });
+    let mut cipher = match cipher {
+        Ok(cipher) => cipher,
+        Err(e) => {
+            log::error!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+
+    match write_keystream(&mut cipher, options.length, io::stdout().lock()) {
+        // Потребитель вроде `head -c` закрыл канал раньше — это не ошибка
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            log::error!("{}", e);
+            EXIT_FAILURE
+        }
+        _ => 0,
+    }
+}
+
+/// Точка входа CLI: разбирает аргументы, обрабатывает файлы и возвращает
+/// код возврата процесса. Диагностика идет через фасад `log`; порог
+/// (`log::set_max_level`) выставляется по `-q`/`-v`, сам журнал
+/// устанавливает вызывающий (бинарник `rc4` — `logging::init`).
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        Some("bench") => return run_bench(&args[1..]),
+        Some("keystream") => return run_keystream(&args[1..]),
+        _ => {}
+    }
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            log::error!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    log::set_max_level(options.verbosity);
+
+    if let KeySource::Text(_) = options.key {
+        log::warn!(
+            "--key exposes the key in shell history and process listings; \
+             prefer --key-file, --key-env or --prompt"
+        );
+    }
+    let key = match load_key(&options.key, options.mode) {
+        Ok(key) => key,
+        Err(e) => {
+            log::error!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+    let key = key.as_bytes();
+    if let Err(e) = Rc4::try_new(key) {
+        log::error!("invalid key: {}", e);
+        return EXIT_USAGE;
+    }
+    log::debug!("key: {} bytes from {}", key.len(), options.key.describe());
+
+    let mut inputs = options.inputs.clone();
+    for pattern in &options.globs {
+        match expand_glob(pattern) {
+            Ok(paths) if paths.is_empty() => {
+                log::warn!("pattern '{}' matched no files", pattern)
+            }
+            Ok(paths) => {
+                log::debug!("pattern '{}' matched {} files", pattern, paths.len());
+                inputs.extend(paths)
+            }
+            Err(e) => {
+                log::error!("cannot expand '{}': {}", pattern, e);
+                return EXIT_FAILURE;
+            }
+        }
+    }
+    inputs.sort();
+    let before_dedup = inputs.len();
+    inputs.dedup();
+    if inputs.len() < before_dedup {
+        log::debug!("skipping {} duplicate inputs", before_dedup - inputs.len());
+    }
+
+    let jobs: Vec<(PathBuf, PathBuf)> = inputs
+        .into_iter()
+        .map(|input| {
+            let output = match &options.output {
+                Some(output) => output.clone(),
+                None if is_stdio(&input) => PathBuf::from(STDIO),
+                None => output_path(&input, options.mode, &options.suffix),
+            };
+            (input, output)
+        })
+        .collect();
+
+    let write = options.write;
+    log::debug!(
+        "{} files, {} threads, {} KiB buffers",
+        jobs.len(),
+        options.threads.clamp(1, jobs.len().max(1)),
+        BUFFER_SIZE / 1024
+    );
+    log::debug!(
+        "write options: force={} atomic={} armor={} input-encoding={:?}",
+        write.force,
+        write.atomic,
+        write.armor,
+        write.input_encoding
+    );
+    if write.resume {
+        log::debug!(
+            "resume enabled, checkpoint every {} MiB",
+            write.checkpoint_every / (1024 * 1024)
+        );
+    }
+
+    let reports = process_files(&jobs, key, options.threads, write);
+    // Читатель закрыл stdout раньше (`| head -c`): как и `keystream`,
+    // завершаемся тихо и успешно
+    if let [report] = &reports[..] {
+        if is_broken_pipe(report) {
+            return 0;
+        }
+    }
+
+    let failed = reports.iter().filter(|r| r.result.is_err()).count();
+    log::info!("{} succeeded, {} failed", reports.len() - failed, failed);
+
+    let mismatched = reports
+        .iter()
+        .any(|r| r.result.as_ref().is_err_and(is_checksum_mismatch));
+    if mismatched {
+        EXIT_CHECKSUM
+    } else if failed == 0 {
+        0
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+/// Читает ключ из выбранного источника. Промежуточные копии (содержимое
+/// файла в hex, строка из терминала) затираются так же, как сам ключ.
+fn load_key(source: &KeySource, mode: Mode) -> io::Result<KeyBuffer> {
+    match source {
+        KeySource::Text(text) => Ok(KeyBuffer::new(text.as_bytes().to_vec())),
+        KeySource::File(path, format) => {
+            let raw = KeyBuffer::new(fs::read(path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("cannot read key file {}: {}", path.display(), e),
+                )
+            })?);
+            match format {
+                KeyFileFormat::Raw => Ok(raw),
+                KeyFileFormat::Hex => decode_hex_key(raw.as_bytes()).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid hex key file {}: {}", path.display(), e),
+                    )
+                }),
+            }
+        }
+        KeySource::Env(name) => match std::env::var(name) {
+            Ok(value) => Ok(KeyBuffer::new(value.into_bytes())),
+            Err(e) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("cannot read key from ${}: {}", name, e),
+            )),
+        },
+        KeySource::Prompt => prompt_key(mode),
+    }
+}
+
+fn decode_hex_key(contents: &[u8]) -> Result<KeyBuffer, String> {
+    let text = std::str::from_utf8(contents).map_err(|_| "file is not text".to_string())?;
+    let digits = KeyBuffer::new(text.bytes().filter(|b| !b.is_ascii_whitespace()).collect());
+    // Удаление ASCII-символов из UTF-8 оставляет корректный UTF-8
+    let digits = std::str::from_utf8(digits.as_bytes()).expect("valid UTF-8");
+    hex::decode(digits)
+        .map(KeyBuffer::new)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "prompt")]
+fn prompt_key(mode: Mode) -> io::Result<KeyBuffer> {
+    let key = KeyBuffer::new(rpassword::prompt_password("key: ")?.into_bytes());
+    if mode == Mode::Encrypt {
+        let confirm = KeyBuffer::new(rpassword::prompt_password("repeat key: ")?.into_bytes());
+        if !Rc4::constant_time_equals(key.as_bytes(), confirm.as_bytes()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "keys do not match",
+            ));
+        }
+    }
+    Ok(key)
+}
+
+#[cfg(not(feature = "prompt"))]
+fn prompt_key(_mode: Mode) -> io::Result<KeyBuffer> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--prompt is not available: built without the `prompt` feature",
+    ))
+}
+
+/// Имя выходного файла: `encrypt` дописывает суффикс, `decrypt` отрезает
+/// его (или дописывает `.dec`, если входной файл не оканчивается суффиксом).
+pub fn output_path(input: &Path, mode: Mode, suffix: &str) -> PathBuf {
+    let name = input.as_os_str().to_string_lossy();
+    match mode {
+        Mode::Encrypt => PathBuf::from(format!("{}{}", name, suffix)),
+        Mode::Decrypt => match name.strip_suffix(suffix) {
+            Some(stripped) if !stripped.is_empty() && !stripped.ends_with('/') => {
+                PathBuf::from(stripped)
+            }
+            _ => PathBuf::from(format!("{}.dec", name)),
+        },
+    }
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO
+}
+
+/// Читатель stdout закрыл канал: завершаемся без сообщения об ошибке.
+fn is_broken_pipe(report: &FileReport) -> bool {
+    is_stdio(&report.output)
+        && matches!(&report.result, Err(e) if e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Как записывать выходные файлы.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Перезаписывать существующий файл назначения.
+    pub force: bool,
+    /// Писать во временный файл и переименовывать его только при успехе.
+    pub atomic: bool,
+    /// Сохранять прогресс и продолжать прерванную обработку (см. `resume`).
+    pub resume: bool,
+    /// Интервал между контрольными точками в режиме `resume`, в байтах.
+    pub checkpoint_every: u64,
+    /// Писать результат в ASCII-броне (см. `armor`).
+    pub armor: bool,
+    /// Как читать входные файлы.
+    pub input_encoding: InputEncoding,
+    /// Контрольная сумма открытого текста в конце шифротекста.
+    pub checksum: ChecksumMode,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            force: false,
+            atomic: true,
+            resume: false,
+            checkpoint_every: 64 * 1024 * 1024,
+            armor: false,
+            input_encoding: InputEncoding::Binary,
+            checksum: ChecksumMode::None,
+        }
+    }
+}
+
+/// Обрабатывает пары (вход, выход) в `threads` потоках. Отчеты
+/// возвращаются в порядке `jobs` независимо от порядка завершения; начало и
+/// итог каждого файла попадают в журнал сразу, в порядке обработки.
+pub fn process_files(
+    jobs: &[(PathBuf, PathBuf)],
+    key: &[u8],
+    threads: usize,
+    write: WriteOptions,
+) -> Vec<FileReport> {
+    let next = AtomicUsize::new(0);
+    let reports: Mutex<Vec<Option<FileReport>>> =
+        Mutex::new((0..jobs.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((input, output)) = jobs.get(index) else {
+                    break;
+                };
+                log::info!("started {} -> {}", input.display(), output.display());
+                let start = Instant::now();
+                let result = process_file(input, output, key, write);
+                let elapsed = start.elapsed();
+                match &result {
+                    Ok(bytes) => log::info!(
+                        "ok      {} -> {} ({} bytes, {:.1} MB/s)",
+                        input.display(),
+                        output.display(),
+                        bytes,
+                        *bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(1e-9)
+                    ),
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe && is_stdio(output) => {
+                        log::debug!("stdout closed by the reader")
+                    }
+                    Err(e) => log::error!("{}: {}", input.display(), e),
+                }
+                reports.lock().unwrap()[index] = Some(FileReport {
+                    input: input.clone(),
+                    output: output.clone(),
+                    result,
+                    elapsed,
+                });
+            });
+        }
+    });
+
+    reports
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|report| report.expect("every job is processed"))
+        .collect()
+}
+
+/// Шифрует (или расшифровывает — операция симметрична) один файл.
+pub fn process_file(
+    input: &Path,
+    output: &Path,
+    key: &[u8],
+    write: WriteOptions,
+) -> io::Result<u64> {
+    let source: Box<dyn Read> = if is_stdio(input) {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(input)?)
+    };
+    let mut source = BufReader::with_capacity(BUFFER_SIZE, source);
+    let armored = match write.input_encoding {
+        InputEncoding::Binary => false,
+        InputEncoding::Armor => true,
+        InputEncoding::Auto => {
+            let armored = armor::looks_armored(source.fill_buf()?);
+            log::debug!("{}: armored input: {}", input.display(), armored);
+            armored
+        }
+    };
+
+    if write.resume {
+        if armored {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "--resume does not support armored input",
+            ));
+        }
+        return resume::process_resumable(input, output, key, write, |offset| {
+            log::trace!("{}: checkpoint at {} bytes", input.display(), offset);
+            ControlFlow::Continue(())
+        });
+    }
+    let mut cipher =
+        Rc4::try_new(key).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if armored {
+        process_to_path(&mut cipher, ArmorReader::new(source), output, write)
+    } else {
+        process_to_path(&mut cipher, source, output, write)
+    }
+}
+
+/// Пропускает `input` через шифр в файл `output` (`-` — в stdout).
+///
+/// В атомарном режиме данные пишутся во временный файл `.<имя>.partial`
+/// рядом с целевым, после успеха выполняется `fsync` и переименование
+/// поверх цели. При ошибке временный файл удаляется, поэтому под целевым
+/// именем никогда не оказывается обрезанный результат. При аварийном
+/// завершении процесса (например, по Ctrl-C) может остаться только
+/// `.partial`-файл, который не спутать с готовым.
+pub fn process_to_path<R: Read>(
+    cipher: &mut Rc4,
+    input: R,
+    output: &Path,
+    write: WriteOptions,
+) -> io::Result<u64> {
+    if is_stdio(output) {
+        return encode_stream(cipher, input, io::stdout().lock(), write);
+    }
+    if !write.atomic {
+        let destination = open_destination(output, output, write.force)?;
+        return encode_stream(cipher, input, destination, write);
+    }
+
+    if !write.force && output.exists() {
+        return Err(already_exists(output));
+    }
+    let mut partial = AtomicOutput::create(output)?;
+    let total = encode_stream(cipher, input, &mut partial.file, write)?;
+    partial.commit()?;
+    Ok(total)
+}
+
+/// `process_stream` с контрольной суммой и ASCII-броней на выходе, если
+/// они включены в `write`.
+fn encode_stream<R: Read, W: Write>(
+    cipher: &mut Rc4,
+    input: R,
+    output: W,
+    write: WriteOptions,
+) -> io::Result<u64> {
+    let checksummed = |output: &mut dyn Write| match write.checksum {
+        ChecksumMode::None => process_stream(cipher, input, output),
+        ChecksumMode::Append => seal_stream(cipher, input, output),
+        ChecksumMode::Verify => open_stream(cipher, input, output, true),
+        ChecksumMode::Strip => open_stream(cipher, input, output, false),
+    };
+    if !write.armor {
+        let mut output = output;
+        return checksummed(&mut output);
+    }
+    let mut armored = ArmorWriter::new(output)?;
+    let total = checksummed(&mut armored)?;
+    armored.finish()?;
+    Ok(total)
+}
+
+/// Длина CRC-32 в конце открытого текста.
+const CHECKSUM_LEN: usize = 4;
+
+/// Шифрует `input` и дописывает зашифрованную CRC-32 (little-endian)
+/// открытого текста, вычисленную за тот же проход. Возвращает длину
+/// открытого текста.
+fn seal_stream<R: Read, W: Write>(
+    cipher: &mut Rc4,
+    mut input: R,
+    mut output: W,
+) -> io::Result<u64> {
+    let mut crc = Crc32::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut total = 0u64;
+    loop {
+        let n = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        crc.update(&buffer[..n]);
+        cipher.process(&mut buffer[..n]);
+        output.write_all(&buffer[..n])?;
+        total += n as u64;
+    }
+    let mut trailer = crc.finalize().to_le_bytes();
+    cipher.process(&mut trailer);
+    output.write_all(&trailer)?;
+    output.flush()?;
+    Ok(total)
+}
+
+/// Расшифровывает результат `seal_stream`: последние `CHECKSUM_LEN` байт
+/// удерживаются до конца потока и сверяются с CRC-32 расшифрованного
+/// текста, если `verify`. Открытый текст пишется в `output` до проверки,
+/// поэтому при ошибке его нужно отбросить (атомарный режим так и делает).
+fn open_stream<R: Read, W: Write>(
+    cipher: &mut Rc4,
+    mut input: R,
+    mut output: W,
+    verify: bool,
+) -> io::Result<u64> {
+    let mut crc = Crc32::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE + CHECKSUM_LEN];
+    // Первые `held` байт буфера — кандидат в контрольную сумму
+    let mut held = 0;
+    let mut total = 0u64;
+    loop {
+        let n = match input.read(&mut buffer[held..]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let filled = held + n;
+        let release = filled.saturating_sub(CHECKSUM_LEN);
+        cipher.process(&mut buffer[..release]);
+        crc.update(&buffer[..release]);
+        output.write_all(&buffer[..release])?;
+        total += release as u64;
+        buffer.copy_within(release..filled, 0);
+        held = filled - release;
+    }
+    output.flush()?;
+
+    if held < CHECKSUM_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "input is too short to contain a checksum",
+        ));
+    }
+    let mut trailer = [0u8; CHECKSUM_LEN];
+    trailer.copy_from_slice(&buffer[..CHECKSUM_LEN]);
+    cipher.process(&mut trailer);
+    if verify && u32::from_le_bytes(trailer) != crc.finalize() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ChecksumMismatch));
+    }
+    Ok(total)
+}
+
+pub(crate) fn already_exists(output: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!(
+            "{} already exists (use --force to overwrite)",
+            output.display()
+        ),
+    )
+}
+
+fn open_destination(path: &Path, target: &Path, force: bool) -> io::Result<File> {
+    let mut open = fs::OpenOptions::new();
+    open.write(true);
+    if force {
+        open.create(true).truncate(true);
+    } else {
+        open.create_new(true);
+    }
+    open.open(path).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            already_exists(target)
+        } else {
+            e
+        }
+    })
+}
+
+/// Временный файл рядом с целевым; удаляется при drop, если не был
+/// подтвержден через `commit`.
+struct AtomicOutput {
+    file: File,
+    temp: PathBuf,
+    target: PathBuf,
+    committed: bool,
+}
+
+/// Путь временного файла для `target`: `.<имя>.partial` в том же каталоге.
+pub(crate) fn partial_path(target: &Path) -> io::Result<PathBuf> {
+    let name = target.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", target.display()),
+        )
+    })?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(".partial");
+    Ok(target.with_file_name(temp_name))
+}
+
+impl AtomicOutput {
+    fn create(target: &Path) -> io::Result<Self> {
+        let temp = partial_path(target)?;
+
+        // Остаток от прерванного запуска перезаписывается
+        let file = open_destination(&temp, target, true)?;
+        Ok(AtomicOutput {
+            file,
+            temp,
+            target: target.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    fn commit(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.temp, &self.target)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AtomicOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Пропускает весь `input` через шифр в `output` буфером фиксированного
+/// размера. Возвращает число обработанных байт.
+pub fn process_stream<R: Read, W: Write>(
+    cipher: &mut Rc4,
+    mut input: R,
+    mut output: W,
+) -> io::Result<u64> {
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut total = 0u64;
+    loop {
+        let n = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        cipher.process(&mut buffer[..n]);
+        output.write_all(&buffer[..n])?;
+        total += n as u64;
+    }
+    output.flush()?;
+    Ok(total)
+}
+
+/// Раскрывает шаблон пути крейтом `glob`: `*`, `?` и `[...]` в пределах
+/// одного компонента, `**` — любое число каталогов. Результат отсортирован;
+/// возвращаются только файлы.
+pub fn expand_glob(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let paths = glob::glob(pattern).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut matches = Vec::new();
+    for path in paths {
+        let path = path.map_err(io::Error::from)?;
+        if path.is_file() {
+            matches.push(path);
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Временный каталог, удаляемый при выходе из теста
+    pub(crate) struct TempDir(pub PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(name: &str) -> TempDir {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "rc4-{}-{}-{}",
+                name,
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        pub(crate) fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+
+        pub(crate) fn arg(&self, name: &str) -> String {
+            self.path(name).to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    pub(crate) fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn file_contents(n: usize) -> Vec<u8> {
+        Rc4::new(format!("content-{}", n).as_bytes()).keystream_to_vec(1000 + n * 4099)
+    }
+
+    /// Ошибка на одном файле не прерывает остальные, код возврата ненулевой
+    #[test]
+    fn test_failure_does_not_abort_others() {
+        let dir = TempDir::new("partial");
+        fs::write(dir.path("good.log"), b"good").unwrap();
+        fs::write(dir.path("exists.log"), b"exists").unwrap();
+        fs::write(dir.path("exists.log.rc4"), b"old output").unwrap();
+
+        let code = run(&args(&[
+            "encrypt",
+            "--key",
+            "Key",
+            &dir.arg("missing.log"),
+            &dir.arg("exists.log"),
+            &dir.arg("good.log"),
+        ]));
+        assert_eq!(code, EXIT_FAILURE);
+        assert_eq!(
+            fs::read(dir.path("good.log.rc4")).unwrap(),
+            Rc4::new(b"Key").apply(b"good")
+        );
+        // Без --force существующий файл не перезаписывается
+        assert_eq!(fs::read(dir.path("exists.log.rc4")).unwrap(), b"old output");
+
+        let code = run(&args(&[
+            "encrypt",
+            "--key",
+            "Key",
+            "--force",
+            &dir.arg("exists.log"),
+        ]));
+        assert_eq!(code, 0);
+        assert_eq!(
+            fs::read(dir.path("exists.log.rc4")).unwrap(),
+            Rc4::new(b"Key").apply(b"exists")
+        );
+    }
+
+    /// Источник, отдающий `remaining` байт и затем ошибку
+    struct FailingReader {
+        remaining: usize,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(io::Error::other("source became unreadable"));
+            }
+            let n = buf.len().min(self.remaining);
+            buf[..n].fill(0x5A);
+            self.remaining -= n;
+            Ok(n)
+        }
+    }
+
+    /// Источник `remaining` байт гаммы: данные без буфера на весь объем
+    struct KeystreamReader {
+        cipher: Rc4,
+        remaining: usize,
+    }
+
+    impl Read for KeystreamReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.remaining);
+            self.cipher.fill_keystream(&mut buf[..n]);
+            self.remaining -= n;
+            Ok(n)
+        }
+    }
+
+    /// Сверяет поступающие данные с гаммой того же ключа, не накапливая их,
+    /// и запоминает самую большую запись
+    struct KeystreamChecker {
+        cipher: Rc4,
+        checked: usize,
+        largest_write: usize,
+    }
+
+    impl Write for KeystreamChecker {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            assert!(
+                buf == self.cipher.keystream_to_vec(buf.len()),
+                "at {}",
+                self.checked
+            );
+            self.checked += buf.len();
+            self.largest_write = self.largest_write.max(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// 16 МиБ (64 буфера) проходят encrypt | decrypt через канал ОС
+    /// порциями не больше буфера; закрытый читатель дает BrokenPipe
+    #[test]
+    fn test_pipe_roundtrip_constant_memory() {
+        const LEN: usize = 64 * BUFFER_SIZE;
+        let encrypt = WriteOptions {
+            checksum: ChecksumMode::Append,
+            ..WriteOptions::default()
+        };
+        let decrypt = WriteOptions {
+            checksum: ChecksumMode::Verify,
+            ..WriteOptions::default()
+        };
+
+        let (reader, writer) = io::pipe().unwrap();
+        let source = KeystreamReader {
+            cipher: Rc4::new(b"data"),
+            remaining: LEN,
+        };
+        let producer =
+            thread::spawn(move || encode_stream(&mut Rc4::new(b"Key"), source, writer, encrypt));
+        let mut checker = KeystreamChecker {
+            cipher: Rc4::new(b"data"),
+            checked: 0,
+            largest_write: 0,
+        };
+        let total = encode_stream(&mut Rc4::new(b"Key"), reader, &mut checker, decrypt).unwrap();
+        assert_eq!(producer.join().unwrap().unwrap(), LEN as u64);
+        assert_eq!((total, checker.checked), (LEN as u64, LEN));
+        assert!(checker.largest_write <= BUFFER_SIZE);
+
+        let (reader, writer) = io::pipe().unwrap();
+        drop(reader);
+        let source = KeystreamReader {
+            cipher: Rc4::new(b"data"),
+            remaining: BUFFER_SIZE,
+        };
+        let err = encode_stream(&mut Rc4::new(b"Key"), source, writer, encrypt).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+
+        // Без входов читается stdin; stdin не смешивается с файлами и --resume
+        let options = Options::parse(&args(&["encrypt", "--key", "k"])).unwrap();
+        assert_eq!(options.inputs, [PathBuf::from("-")]);
+        for bad in [
+            &["encrypt", "--key", "k", "-", "a"][..],
+            &["encrypt", "--key", "k", "--glob", "*.log", "-"],
+            &["encrypt", "--key", "k", "--resume"],
+            &["encrypt", "--key", "k", "--resume", "-o", "-", "a"],
+        ] {
+            assert!(Options::parse(&args(bad)).is_err(), "{:?}", bad);
+        }
+    }
+
+    /// Сбой посреди чтения не оставляет ни цели, ни временного файла
+    #[test]
+    fn test_atomic_output_cleaned_up_on_failure() {
+        let dir = TempDir::new("atomic");
+        let target = dir.path("out.rc4");
+        let reader = FailingReader {
+            remaining: 3 * BUFFER_SIZE + 17,
+        };
+
+        let err = process_to_path(
+            &mut Rc4::new(b"Key"),
+            reader,
+            &target,
+            WriteOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "source became unreadable");
+        assert!(!target.exists());
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 0);
+
+        // Существующая цель остается нетронутой даже с --force
+        fs::write(&target, b"previous").unwrap();
+        let write = WriteOptions {
+            force: true,
+            ..WriteOptions::default()
+        };
+        let reader = FailingReader { remaining: 10 };
+        assert!(process_to_path(&mut Rc4::new(b"Key"), reader, &target, write).is_err());
+        assert_eq!(fs::read(&target).unwrap(), b"previous");
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    /// Успешная атомарная запись и режим --no-atomic дают одинаковый результат
+    #[test]
+    fn test_atomic_and_direct_write() {
+        let dir = TempDir::new("direct");
+        fs::write(dir.path("in.log"), b"some data").unwrap();
+
+        assert_eq!(
+            run(&args(&["encrypt", "--key", "Key", &dir.arg("in.log")])),
+            0
+        );
+        let atomic = fs::read(dir.path("in.log.rc4")).unwrap();
+
+        let code = run(&args(&[
+            "encrypt",
+            "--key",
+            "Key",
+            "--no-atomic",
+            "-o",
+            &dir.arg("direct.rc4"),
+            &dir.arg("in.log"),
+        ]));
+        assert_eq!(code, 0);
+        assert_eq!(fs::read(dir.path("direct.rc4")).unwrap(), atomic);
+        assert!(!dir.path(".in.log.rc4.partial").exists());
+    }
+
+    /// Ошибка CLI посреди файла не оставляет временных файлов
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_cleans_up_temp_file_on_error() {
+        let dir = TempDir::new("cli-error");
+        // Каталог открывается как файл, но чтение из него завершается ошибкой
+        fs::create_dir(dir.path("input")).unwrap();
+
+        let code = run(&args(&["encrypt", "--key", "Key", &dir.arg("input")]));
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(!dir.path("input.rc4").exists());
+        assert!(!dir.path(".input.rc4.partial").exists());
+    }
+
+    /// Ошибки в аргументах дают код EXIT_USAGE
+    #[test]
+    fn test_usage_errors() {
+        assert_eq!(run(&args(&["encrypt", "file"])), EXIT_USAGE);
+        assert_eq!(run(&args(&["frobnicate"])), EXIT_USAGE);
+        assert_eq!(
+            run(&args(&["encrypt", "--key", "k", "--threads", "0", "f"])),
+            EXIT_USAGE
+        );
+        assert_eq!(
+            run(&args(&["encrypt", "--key", "k", "--key-file", "p", "f"])),
+            EXIT_USAGE
+        );
+        assert_eq!(
+            run(&args(&["encrypt", "--key", "k", "-o", "out", "a", "b"])),
+            EXIT_USAGE
+        );
+    }
+
+    /// --checksum: верный ключ проходит, неверный ключ и измененный бит дают
+    /// код 2 без выходного файла, --no-verify отрезает сумму без проверки
+    #[test]
+    fn test_checksum() {
+        let dir = TempDir::new("checksum");
+        let plaintext = file_contents(3);
+        fs::write(dir.path("data.bin"), &plaintext).unwrap();
+        let code = run(&args(&[
+            "encrypt",
+            "--key",
+            "Key",
+            "--checksum",
+            &dir.arg("data.bin"),
+        ]));
+        assert_eq!(code, 0);
+        let sealed = fs::read(dir.path("data.bin.rc4")).unwrap();
+        assert_eq!(sealed.len(), plaintext.len() + CHECKSUM_LEN);
+        assert_eq!(
+            Rc4::new(b"Key").apply(&sealed)[..plaintext.len()],
+            plaintext
+        );
+        fs::remove_file(dir.path("data.bin")).unwrap();
+
+        let decrypt = |key: &str, extra: &[&str]| {
+            let mut list = vec!["decrypt", "--force", "--key", key, "--checksum"];
+            list.extend_from_slice(extra);
+            let input = dir.arg("data.bin.rc4");
+            list.push(&input);
+            run(&args(&list))
+        };
+
+        assert_eq!(decrypt("Wrong", &[]), EXIT_CHECKSUM);
+        assert!(!dir.path("data.bin").exists());
+
+        let mut flipped = sealed.clone();
+        flipped[100] ^= 0x10;
+        fs::write(dir.path("data.bin.rc4"), &flipped).unwrap();
+        assert_eq!(decrypt("Key", &[]), EXIT_CHECKSUM);
+        assert!(!dir.path("data.bin").exists());
+
+        // Восстановление поврежденного файла: только измененный байт
+        assert_eq!(decrypt("Key", &["--no-verify"]), 0);
+        let recovered = fs::read(dir.path("data.bin")).unwrap();
+        assert_eq!(recovered.len(), plaintext.len());
+        assert_eq!(recovered[100], plaintext[100] ^ 0x10);
+
+        fs::write(dir.path("data.bin.rc4"), &sealed).unwrap();
+        assert_eq!(decrypt("Key", &[]), 0);
+        assert_eq!(fs::read(dir.path("data.bin")).unwrap(), plaintext);
+
+        fs::write(dir.path("data.bin.rc4"), &sealed[..3]).unwrap();
+        assert_eq!(decrypt("Key", &[]), EXIT_FAILURE);
+        assert_eq!(decrypt("Key", &["--resume"]), EXIT_USAGE);
+        let encrypt_no_verify = ["encrypt", "--key", "Key", "--checksum", "--no-verify", "f"];
+        assert_eq!(run(&args(&encrypt_no_verify)), EXIT_USAGE);
+    }
+
+    /// KeyBuffer хранит ключ без изменений независимо от емкости исходного
+    /// Vec (с `secrecy` — через SecretSlice)
+    #[test]
+    fn test_key_buffer_contents() {
+        let mut spare = Vec::with_capacity(64);
+        spare.extend_from_slice(b"secret key");
+        assert_eq!(KeyBuffer::new(spare).as_bytes(), b"secret key");
+        assert_eq!(KeyBuffer::new(b"exact".to_vec()).as_bytes(), b"exact");
+        assert_eq!(KeyBuffer::new(Vec::new()).as_bytes(), b"");
+    }
+
+    /// keystream: разбор аргументов и потоковая запись гаммы порциями
+    #[test]
+    fn test_keystream_command() {
+        let options = KeystreamOptions::parse(&args(&["--key", "seed", "1000"])).unwrap();
+        assert_eq!(options.key, KeySource::Text("seed".into()));
+        assert_eq!(options.length, 1000);
+        for bad in [
+            &["--key", "seed"][..],
+            &["1000"],
+            &["--key", "a", "--key-env", "B", "1"],
+            &["--key", "seed", "-5"],
+            &["--key", "seed", "1", "2"],
+        ] {
+            assert!(KeystreamOptions::parse(&args(bad)).is_err(), "{:?}", bad);
+        }
+
+        let length = 3 * BUFFER_SIZE + 17;
+        let mut out = Vec::new();
+        write_keystream(&mut Rc4::new(b"seed"), length as u64, &mut out).unwrap();
+        assert!(out == Rc4::new(b"seed").keystream_to_vec(length));
+        assert_eq!(run(&args(&["keystream", "--key", "seed"])), EXIT_USAGE);
+    }
+
+    /// Разбор параметров bench и запуск крошечного бенчмарка
+    #[test]
+    fn test_bench_options() {
+        let options = BenchOptions::parse(&args(&["--csv", "--sizes", "1024, 4096"])).unwrap();
+        assert_eq!(options.format, ReportFormat::Csv);
+        assert_eq!(options.sizes, [1024, 4096]);
+        assert_eq!(options.iterations, DEFAULT_BENCH_ITERATIONS);
+
+        for bad in [
+            &["--json", "--csv"][..],
+            &["--sizes", "1024,0"],
+            &["--iterations", "x"],
+            &["--key", "k"],
+        ] {
+            assert!(BenchOptions::parse(&args(bad)).is_err(), "{:?}", bad);
+        }
+        assert_eq!(run(&args(&["bench", "--json", "--json"])), EXIT_USAGE);
+        assert_eq!(
+            run(&args(&[
+                "bench",
+                "--json",
+                "--sizes",
+                "256",
+                "--iterations",
+                "2"
+            ])),
+            0
+        );
+    }
+
+    /// Ключ из файла (raw и hex с переводом строки) и из окружения дает
+    /// тот же шифротекст, что и --key
+    #[test]
+    fn test_key_sources() {
+        let dir = TempDir::new("key-sources");
+        let data = file_contents(1);
+        let expected = Rc4::new(b"Key\x00\xff").apply(&data);
+        fs::write(dir.path("raw.key"), b"Key\x00\xff").unwrap();
+        fs::write(dir.path("hex.key"), b"4b6579\n00FF\r\n").unwrap();
+        fs::write(dir.path("bad.key"), b"4b65790").unwrap();
+        std::env::set_var("RC4_CLI_TEST_KEY", "Key");
+
+        let cases: [&[&str]; 3] = [
+            &["--key-file", &dir.arg("raw.key")],
+            &[
+                "--key-file",
+                &dir.arg("hex.key"),
+                "--key-file-format",
+                "hex",
+            ],
+            &[
+                "--key-file-format",
+                "hex",
+                "--key-file",
+                &dir.arg("hex.key"),
+            ],
+        ];
+        for (n, key_args) in cases.iter().enumerate() {
+            let input = dir.path(&format!("data{}", n));
+            fs::write(&input, &data).unwrap();
+            let mut list = vec!["encrypt"];
+            list.extend_from_slice(key_args);
+            list.push(input.to_str().unwrap());
+            assert_eq!(run(&args(&list)), 0, "{:?}", key_args);
+            assert_eq!(
+                fs::read(dir.path(&format!("data{}.rc4", n))).unwrap(),
+                expected
+            );
+        }
+
+        let input = dir.arg("env");
+        fs::write(&input, &data).unwrap();
+        let code = run(&args(&["encrypt", "--key-env", "RC4_CLI_TEST_KEY", &input]));
+        assert_eq!(code, 0);
+        assert_eq!(
+            fs::read(dir.path("env.rc4")).unwrap(),
+            Rc4::new(b"Key").apply(&data)
+        );
+
+        let bad = [
+            "encrypt",
+            "--key-file",
+            &dir.arg("bad.key"),
+            "--key-file-format",
+            "hex",
+            &input,
+        ];
+        assert_eq!(run(&args(&bad)), EXIT_USAGE);
+        let unset = ["encrypt", "--key-env", "RC4_CLI_TEST_UNSET", &input];
+        assert_eq!(run(&args(&unset)), EXIT_USAGE);
+    }
+
+    /// Несколько источников ключа или формат без файла — ошибка разбора
+    #[test]
+    fn test_key_source_conflicts() {
+        let conflicts: [&[&str]; 4] = [
+            &["encrypt", "--key-env", "A", "--prompt", "f"],
+            &["encrypt", "--key-file", "k", "--key-env", "A", "f"],
+            &["encrypt", "--key", "k", "--key-file-format", "hex", "f"],
+            &[
+                "encrypt",
+                "--key-file",
+                "k",
+                "--key-file-format",
+                "base64",
+                "f",
+            ],
+        ];
+        for list in conflicts {
+            assert!(Options::parse(&args(list)).is_err(), "{:?}", list);
+        }
+        let err = Options::parse(&args(&["decrypt", "--prompt", "--key", "k", "f"])).unwrap_err();
+        assert!(err.0.contains("--key conflicts"), "{}", err);
+        assert_eq!(
+            Options::parse(&args(&["decrypt", "--prompt", "f"]))
+                .unwrap()
+                .key,
+            KeySource::Prompt
+        );
+    }
+
+    /// --armor дает текстовый файл, который decrypt распознает сам
+    /// или по --input-encoding armor
+    #[test]
+    fn test_armor_roundtrip() {
+        let dir = TempDir::new("armor");
+        let data = file_contents(2);
+        fs::write(dir.path("note"), &data).unwrap();
+
+        let code = run(&args(&[
+            "encrypt",
+            "--key",
+            "Key",
+            "--armor",
+            &dir.arg("note"),
+        ]));
+        assert_eq!(code, 0);
+        let armored = fs::read_to_string(dir.path("note.rc4")).unwrap();
+        assert!(armored.starts_with(armor::BEGIN_LINE));
+
+        fs::remove_file(dir.path("note")).unwrap();
+        let code = run(&args(&["decrypt", "--key", "Key", &dir.arg("note.rc4")]));
+        assert_eq!(code, 0);
+        assert_eq!(fs::read(dir.path("note")).unwrap(), data);
+
+        // CRLF и пробелы, как после копирования из письма
+        let pasted = format!("\r\n  {}\r\n", armored.replace('\n', "\r\n"));
+        fs::write(dir.path("pasted.rc4"), pasted).unwrap();
+        let list = [
+            "decrypt",
+            "--key",
+            "Key",
+            "--input-encoding",
+            "armor",
+            &dir.arg("pasted.rc4"),
+        ];
+        assert_eq!(run(&args(&list)), 0);
+        assert_eq!(fs::read(dir.path("pasted")).unwrap(), data);
+
+        // Двоичный режим не пытается снимать броню
+        let list = [
+            "decrypt",
+            "--key",
+            "Key",
+            "--input-encoding",
+            "binary",
+            "-o",
+            &dir.arg("raw"),
+            &dir.arg("note.rc4"),
+        ];
+        assert_eq!(run(&args(&list)), 0);
+        assert_ne!(fs::read(dir.path("raw")).unwrap(), data);
+
+        assert!(Options::parse(&args(&["decrypt", "--key", "k", "--armor", "f"])).is_err());
+        let list = ["encrypt", "--key", "k", "--input-encoding", "armor", "f"];
+        assert!(Options::parse(&args(&list)).is_err());
+    }
+
+    /// Имена выходных файлов
+    #[test]
+    fn test_output_path() {
+        let path = Path::new("logs/a.log");
+        assert_eq!(
+            output_path(path, Mode::Encrypt, ".rc4"),
+            Path::new("logs/a.log.rc4")
+        );
+        assert_eq!(
+            output_path(Path::new("logs/a.log.rc4"), Mode::Decrypt, ".rc4"),
+            Path::new("logs/a.log")
+        );
+        assert_eq!(
+            output_path(path, Mode::Decrypt, ".rc4"),
+            Path::new("logs/a.log.dec")
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Разностные тесты против крейта `rc4` из RustCrypto (dev-зависимость,
+//! включается feature `compat-tests`, в том числе через `--all-features`).
+//!
+//! Случайные ключи, сообщения и разбиения на порции порождает отдельный
+//! ГПСЧ splitmix64 с фиксированным зерном, чтобы не проверять шифр им же.
+//! Размер ключа у RustCrypto задается типом, поэтому длины ключей выбираются
+//! из фиксированного набора.
+
+use rc4::consts::{U1, U13, U16, U256, U32, U5, U64, U7};
+use rc4::{KeyInit, StreamCipher};
+
+use crate::Rc4;
+
+const KEY_LENGTHS: [usize; 8] = [1, 5, 7, 13, 16, 32, 64, 256];
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+
+    /// Случайное разбиение `len` на порции от 1 до 300 байт.
+    fn chunks(&mut self, mut len: usize) -> Vec<usize> {
+        let mut chunks = Vec::new();
+        while len > 0 {
+            let chunk = (1 + self.below(300)).min(len);
+            chunks.push(chunk);
+            len -= chunk;
+        }
+        chunks
+    }
+}
+
+fn apply_in_chunks(mut apply: impl FnMut(&mut [u8]), data: &mut [u8], chunks: &[usize]) {
+    let mut rest = data;
+    for &chunk in chunks {
+        let (head, tail) = rest.split_at_mut(chunk);
+        apply(head);
+        rest = tail;
+    }
+}
+
+/// Шифрует `data` реализацией RustCrypto порциями `chunks`.
+fn theirs(key: &[u8], data: &mut [u8], chunks: &[usize]) {
+    macro_rules! dispatch {
+        ($($len:literal => $size:ty),*) => {
+            match key.len() {
+                $($len => {
+                    let mut cipher = rc4::Rc4::<$size>::new_from_slice(key).unwrap();
+                    apply_in_chunks(|chunk| cipher.apply_keystream(chunk), data, chunks);
+                })*
+                other => panic!("no RustCrypto instantiation for {}-byte keys", other),
+            }
+        };
+    }
+    dispatch!(1 => U1, 5 => U5, 7 => U7, 13 => U13, 16 => U16, 32 => U32, 64 => U64, 256 => U256);
+}
+
+/// Тысячи случайных ключей, сообщений и разбиений дают одинаковый результат
+#[test]
+fn test_matches_rustcrypto_random_chunking() {
+    let mut rng = SplitMix64(0x005e_ed0f_7e57);
+    for _ in 0..3000 {
+        let key_len = KEY_LENGTHS[rng.below(KEY_LENGTHS.len())];
+        let key = rng.bytes(key_len);
+        let message_len = rng.below(2048);
+        let message = rng.bytes(message_len);
+
+        let mut expected = message.clone();
+        theirs(&key, &mut expected, &[message.len()]);
+
+        let mut ours = message.clone();
+        let mut cipher = Rc4::new(&key);
+        let chunks = rng.chunks(message_len);
+        apply_in_chunks(|chunk| cipher.process(chunk), &mut ours, &chunks);
+        assert!(ours == expected, "mismatch for key {:02x?}", key);
+
+        let mut theirs_chunked = message;
+        let chunks = rng.chunks(message_len);
+        theirs(&key, &mut theirs_chunked, &chunks);
+        assert!(theirs_chunked == expected);
+    }
+}
+
+/// Гамма совпадает и после 1 МиБ: расхождение в обновлении состояния
+/// может проявиться только поздно
+#[test]
+fn test_matches_rustcrypto_at_large_offsets() {
+    let mut rng = SplitMix64(42);
+    for &key_len in &KEY_LENGTHS {
+        let key = rng.bytes(key_len);
+        let len = (1 << 20) + 4096;
+
+        let mut expected = vec![0u8; len];
+        theirs(&key, &mut expected, &[len]);
+
+        let mut cipher = Rc4::new(&key);
+        cipher.skip(1 << 20);
+        assert!(
+            cipher.keystream_to_vec(4096) == expected[1 << 20..],
+            "mismatch after 1 MiB for {}-byte key",
+            key_len
+        );
+    }
+}
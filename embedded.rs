@@ -0,0 +1,241 @@
+//! Адаптеры для трейтов `embedded-io` (feature `embedded-io`).
+//!
+//! Модуль использует только `core` и не выделяет память, поэтому подходит
+//! для `no_std`-целей. Проверка сборки под микроконтроллер:
+//!
+//! ```text
+//! rustup target add thumbv7em-none-eabihf
+//! cargo build --target thumbv7em-none-eabihf --no-default-features --features embedded-io
+//! ```
+
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::Rc4;
+
+/// Расшифровывает данные, читаемые из внутреннего источника.
+///
+/// Каждый прочитанный байт расшифровывается ровно один раз; короткие
+/// чтения и ошибки внутреннего источника передаются как есть.
+pub struct Rc4Reader<R> {
+    inner: R,
+    cipher: Rc4,
+}
+
+impl<R> Rc4Reader<R> {
+    pub fn new(inner: R, cipher: Rc4) -> Self {
+        Rc4Reader { inner, cipher }
+    }
+
+    pub fn into_inner(self) -> (R, Rc4) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<R: ErrorType> ErrorType for Rc4Reader<R> {
+    type Error = R::Error;
+}
+
+impl<R: Read> Read for Rc4Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.cipher.process(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Размер внутреннего буфера записи (без аллокаций, на стеке владельца).
+const WRITER_BUF: usize = 64;
+
+/// Шифрует данные перед записью во внутренний приемник.
+///
+/// Зашифрованные, но не принятые приемником байты хранятся во внутреннем
+/// буфере, поэтому частичная запись не рассинхронизирует шифр: `write`
+/// сообщает о принятии байтов сразу после шифрования, а ошибка приемника
+/// проявится при следующем `write` или `flush`.
+pub struct Rc4Writer<W> {
+    inner: W,
+    cipher: Rc4,
+    pending: [u8; WRITER_BUF],
+    start: usize,
+    end: usize,
+}
+
+impl<W> Rc4Writer<W> {
+    pub fn new(inner: W, cipher: Rc4) -> Self {
+        Rc4Writer {
+            inner,
+            cipher,
+            pending: [0; WRITER_BUF],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Возвращает приемник и шифр. Неотправленные байты теряются —
+    /// перед вызовом следует выполнить `flush`.
+    pub fn into_inner(self) -> (W, Rc4) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<W: Write> Rc4Writer<W> {
+    fn write_pending(&mut self) -> Result<(), W::Error> {
+        while self.start < self.end {
+            // Контракт embedded-io: write блокируется, пока не запишет хотя бы байт
+            self.start += self.inner.write(&self.pending[self.start..self.end])?;
+        }
+        self.start = 0;
+        self.end = 0;
+        Ok(())
+    }
+}
+
+impl<W: ErrorType> ErrorType for Rc4Writer<W> {
+    type Error = W::Error;
+}
+
+impl<W: Write> Write for Rc4Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_pending()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(WRITER_BUF);
+        self.pending[..n].copy_from_slice(&buf[..n]);
+        self.cipher.process(&mut self.pending[..n]);
+        self.end = n;
+
+        // Байты уже учтены в состоянии шифра: ошибку отложим до следующего вызова
+        if let Ok(written) = self.inner.write(&self.pending[..n]) {
+            self.start = written;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.write_pending()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_io::ErrorKind;
+
+    #[derive(Debug, PartialEq)]
+    struct MockError;
+
+    impl embedded_io::Error for MockError {
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    /// Источник, отдающий не больше `step` байт за раз, и ошибку в конце
+    struct MemReader<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl ErrorType for MemReader<'_> {
+        type Error = MockError;
+    }
+
+    impl Read for MemReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, MockError> {
+            if self.data.is_empty() {
+                return Err(MockError);
+            }
+            let n = buf.len().min(self.data.len()).min(self.step);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    /// Приемник, принимающий не больше `step` байт за раз и иногда
+    /// возвращающий ошибку
+    struct MemWriter {
+        data: Vec<u8>,
+        step: usize,
+        fail_every: usize,
+        calls: usize,
+    }
+
+    impl ErrorType for MemWriter {
+        type Error = MockError;
+    }
+
+    impl Write for MemWriter {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, MockError> {
+            self.calls += 1;
+            if self.fail_every != 0 && self.calls.is_multiple_of(self.fail_every) {
+                return Err(MockError);
+            }
+            let n = buf.len().min(self.step);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> Result<(), MockError> {
+            Ok(())
+        }
+    }
+
+    fn plaintext() -> Vec<u8> {
+        (0..300u32).map(|n| (n * 31 % 256) as u8).collect()
+    }
+
+    /// Короткие чтения расшифровываются корректно, ошибка передается как есть
+    #[test]
+    fn test_reader_short_reads() {
+        let ciphertext = Rc4::new(b"Key").apply(plaintext());
+        let mut reader = Rc4Reader::new(
+            MemReader {
+                data: &ciphertext,
+                step: 7,
+            },
+            Rc4::new(b"Key"),
+        );
+
+        let mut output = Vec::new();
+        let mut buf = [0u8; 20];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(n) => output.extend_from_slice(&buf[..n]),
+                Err(e) => {
+                    assert_eq!(e, MockError);
+                    break;
+                }
+            }
+        }
+        assert_eq!(output, plaintext());
+    }
+
+    /// Частичные записи и ошибки приемника не рассинхронизируют шифр
+    #[test]
+    fn test_writer_short_writes_and_errors() {
+        let sink = MemWriter {
+            data: Vec::new(),
+            step: 5,
+            fail_every: 4,
+            calls: 0,
+        };
+        let mut writer = Rc4Writer::new(sink, Rc4::new(b"Key"));
+
+        let data = plaintext();
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            match writer.write(&rest[..rest.len().min(100)]) {
+                Ok(n) => rest = &rest[n..],
+                Err(MockError) => continue,
+            }
+        }
+        while writer.flush().is_err() {}
+
+        let (sink, _) = writer.into_inner();
+        assert_eq!(sink.data, Rc4::new(b"Key").apply(&data));
+    }
+}
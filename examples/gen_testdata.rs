@@ -0,0 +1,58 @@
+//! Воспроизводимые тестовые данные: `LENGTH` байт гаммы RC4 с ключом `KEY`
+//! в stdout. Один и тот же ключ всегда дает одни и те же байты.
+//!
+//! ```text
+//! cargo run --example gen_testdata -- fixture-seed 1048576 > data.bin
+//! cargo run --example gen_testdata -- fixture-seed 100000000000 | head -c 16 | xxd
+//! ```
+//!
+//! Гамма пишется порциями через `Rc4::fill_keystream`, поэтому память не
+//! зависит от `LENGTH`. Если читатель закрыл канал раньше (`head -c`),
+//! программа завершается тихо с кодом 0 — как `rc4 keystream`. Для ключей
+//! и nonce эти байты не годятся: это детерминированные данные, а не
+//! случайные.
+
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use rust_rc4::Rc4;
+
+const CHUNK: usize = 64 * 1024;
+
+fn generate(key: &[u8], mut length: u64, mut out: impl Write) -> io::Result<()> {
+    let mut cipher =
+        Rc4::try_new(key).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut buffer = vec![0u8; CHUNK];
+    while length > 0 {
+        let chunk = &mut buffer[..length.min(CHUNK as u64) as usize];
+        cipher.fill_keystream(chunk);
+        out.write_all(chunk)?;
+        length -= chunk.len() as u64;
+    }
+    out.flush()
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (key, length) = match &args[..] {
+        [key, length] => match length.parse::<u64>() {
+            Ok(length) => (key, length),
+            Err(_) => {
+                eprintln!("error: LENGTH must be a non-negative integer");
+                return ExitCode::from(64);
+            }
+        },
+        _ => {
+            eprintln!("usage: gen_testdata KEY LENGTH");
+            return ExitCode::from(64);
+        }
+    };
+
+    match generate(key.as_bytes(), length, io::stdout().lock()) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+        _ => ExitCode::SUCCESS,
+    }
+}
@@ -0,0 +1,32 @@
+//! Время KSA в зависимости от длины ключа (`Rc4::benchmark_ksa`).
+//!
+//! ```text
+//! cargo run --release --example ksa_timing [-- ITERATIONS]
+//! ```
+//!
+//! KSA — 256 шагов при любой длине ключа, поэтому строки таблицы должны
+//! почти совпадать; заметная разница означает шум измерения, а не
+//! зависимость от длины.
+
+use rust_rc4::bench::KSA_TIMING_KEY_LENGTHS;
+use rust_rc4::Rc4;
+
+fn main() {
+    let iterations = match std::env::args().nth(1).map(|arg| arg.parse::<u32>()) {
+        None => 100_000,
+        Some(Ok(n)) => n,
+        Some(Err(_)) => {
+            eprintln!("usage: ksa_timing [ITERATIONS]");
+            std::process::exit(64);
+        }
+    };
+
+    println!("{:>8} | {:>12}", "Key len", "ns per KSA");
+    for key_len in KSA_TIMING_KEY_LENGTHS {
+        let key: Vec<u8> = (0..key_len)
+            .map(|n| (n as u8).wrapping_mul(167) ^ 0x5a)
+            .collect();
+        let average = Rc4::benchmark_ksa(&key, iterations);
+        println!("{:>8} | {:>12}", key_len, average.as_nanos());
+    }
+}
@@ -0,0 +1,64 @@
+//! Эхо-сервер поверх TCP с шифрованием RC4 в обе стороны.
+//!
+//! Сервер оборачивает сокет в `Rc4Reader` и `Rc4Writer`: расшифровывает то,
+//! что прислал клиент, и шифрует ответ своим ключом. У каждого направления
+//! свой ключ, иначе обе стороны шифровали бы одной гаммой. Только
+//! `std::net`, сервер и клиент — в одном процессе:
+//!
+//! ```text
+//! cargo run --example tcp_echo -- "message to echo"
+//! ```
+//!
+//! RC4 не защищает целостность и давно не считается стойким; пример
+//! показывает работу адаптеров, а не протокол для реального применения.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+
+use rust_rc4::{Rc4, Rc4Reader, Rc4Writer};
+
+const CLIENT_KEY: &[u8] = b"client to server key";
+const SERVER_KEY: &[u8] = b"server to client key";
+
+// Одно соединение: читает до конца потока клиента и отвечает тем же текстом
+fn serve(listener: TcpListener) -> io::Result<()> {
+    let (stream, peer) = listener.accept()?;
+    eprintln!("server: connection from {}", peer);
+
+    let mut reader = Rc4Reader::new(stream.try_clone()?, Rc4::new(CLIENT_KEY));
+    let mut writer = Rc4Writer::new(stream, Rc4::new(SERVER_KEY));
+    let echoed = io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    let (stream, _) = writer.into_inner();
+    stream.shutdown(Shutdown::Write)?;
+    eprintln!("server: echoed {} bytes", echoed);
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let message = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Hello over an RC4-encrypted socket".to_string());
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    let server = thread::spawn(move || serve(listener));
+
+    let stream = TcpStream::connect(address)?;
+    let mut writer = Rc4Writer::new(stream.try_clone()?, Rc4::new(CLIENT_KEY));
+    writer.write_all(message.as_bytes())?;
+    writer.flush()?;
+    let (stream, _) = writer.into_inner();
+    // Конец записи: сервер увидит EOF и закроет свою сторону
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    Rc4Reader::new(stream, Rc4::new(SERVER_KEY)).read_to_string(&mut reply)?;
+    server.join().expect("server thread panicked")?;
+
+    println!("sent:  {}", message);
+    println!("reply: {}", reply);
+    assert_eq!(reply, message);
+    Ok(())
+}
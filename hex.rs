@@ -0,0 +1,147 @@
+//! Шестнадцатеричное кодирование для ключей и служебных файлов.
+
+use std::fmt;
+
+/// Ошибка разбора шестнадцатеричной строки.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexError {
+    /// Нечетное число цифр.
+    OddLength(usize),
+    /// Недопустимый символ в указанной позиции.
+    InvalidDigit { position: usize, found: char },
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::OddLength(len) => write!(f, "odd number of hex digits ({})", len),
+            HexError::InvalidDigit { position, found } => {
+                write!(f, "invalid hex digit {:?} at position {}", found, position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+/// Кодирует байты строчными шестнадцатеричными цифрами.
+pub fn encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Разбирает шестнадцатеричную строку (цифры любого регистра, без пробелов).
+pub fn decode(text: &str) -> Result<Vec<u8>, HexError> {
+    let digits = text.as_bytes();
+    if !digits.len().is_multiple_of(2) {
+        return Err(HexError::OddLength(digits.len()));
+    }
+
+    let digit = |position: usize| {
+        let c = digits[position];
+        (c as char)
+            .to_digit(16)
+            .map(|d| d as u8)
+            .ok_or(HexError::InvalidDigit {
+                position,
+                // Позиция может попасть внутрь многобайтового символа
+                found: text
+                    .get(position..)
+                    .and_then(|rest| rest.chars().next())
+                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+            })
+    };
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|n| Ok(digit(n)? << 4 | digit(n + 1)?))
+        .collect()
+}
+
+/// Как `decode`, но пропускает пробельные символы (пробелы, переводы строк)
+/// между цифрами. Позиция в `InvalidDigit` — байтовое смещение в исходной
+/// строке, длина в `OddLength` — число цифр без пробелов.
+pub fn decode_ignoring_whitespace(text: &str) -> Result<Vec<u8>, HexError> {
+    let mut out = Vec::with_capacity(text.len() / 2);
+    let mut high = None;
+    let mut digits = 0;
+    for (position, found) in text.char_indices() {
+        if found.is_whitespace() {
+            continue;
+        }
+        let value = found
+            .to_digit(16)
+            .ok_or(HexError::InvalidDigit { position, found })? as u8;
+        digits += 1;
+        match high.take() {
+            None => high = Some(value),
+            Some(high) => out.push(high << 4 | value),
+        }
+    }
+    if high.is_some() {
+        return Err(HexError::OddLength(digits));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Кодирование и разбор взаимно обратны
+    #[test]
+    fn test_roundtrip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        assert_eq!(decode("DEADbeef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(encode(&[]), "");
+    }
+
+    /// Ошибки разбора указывают на причину
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("abc"), Err(HexError::OddLength(3)));
+        assert_eq!(
+            decode("0g"),
+            Err(HexError::InvalidDigit {
+                position: 1,
+                found: 'g'
+            })
+        );
+        assert!(decode("éa").is_err());
+    }
+
+    /// Пробелы между цифрами пропускаются, позиция ошибки — смещение в строке
+    #[test]
+    fn test_decode_ignoring_whitespace() {
+        assert_eq!(
+            decode_ignoring_whitespace(" DE ad\n\tbe EF\r\n").unwrap(),
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        assert_eq!(decode_ignoring_whitespace("d e").unwrap(), [0xde]);
+        assert_eq!(decode_ignoring_whitespace("  ").unwrap(), [0u8; 0]);
+        assert_eq!(
+            decode_ignoring_whitespace("ab c"),
+            Err(HexError::OddLength(3))
+        );
+        assert_eq!(
+            decode_ignoring_whitespace("ab é0"),
+            Err(HexError::InvalidDigit {
+                position: 3,
+                found: 'é'
+            })
+        );
+        assert_eq!(
+            decode_ignoring_whitespace("0x12"),
+            Err(HexError::InvalidDigit {
+                position: 1,
+                found: 'x'
+            })
+        );
+    }
+}
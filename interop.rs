@@ -0,0 +1,188 @@
+//! Тесты совместимости с внешними реализациями RC4.
+//!
+//! Эталонные файлы в `tests/interop/` созданы скриптом
+//! `tests/interop/generate.py` (команды OpenSSL и PyCryptodome описаны в нем)
+//! и проверяются без внешних программ. Генератор каждого файла записан в
+//! `manifest.txt`: файлы `python-*` пока получены запасным учебным RC4
+//! скрипта (`python-textbook`), а не PyCryptodome; после запуска скрипта с
+//! установленным PyCryptodome там будет `pycryptodome`. Остальные тесты с OpenSSL вызывают
+//! `openssl enc` как подпроцесс и пропускаются
+//! (с сообщением в stderr), если `openssl` не найден или собран без RC4.
+//! В OpenSSL 3 RC4 находится в legacy-провайдере; тест пробует оба варианта.
+//! Чтобы тесты действительно выполнялись, достаточно установить OpenSSL
+//! с legacy-провайдером и запустить `cargo test interop`.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::sha256::sha256;
+use crate::{hex, Rc4};
+
+const FIXTURES: &str = "tests/interop";
+
+/// Строка `manifest.txt`: имя, генератор, ключ, длина сообщения, ожидание.
+struct Fixture {
+    name: String,
+    generator: String,
+    key: Vec<u8>,
+    length: usize,
+    expected: String,
+}
+
+fn load_manifest() -> Vec<Fixture> {
+    let text = fs::read_to_string(Path::new(FIXTURES).join("manifest.txt"))
+        .expect("tests/interop is read relative to the crate root");
+    text.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(fields.len(), 5, "malformed manifest line: {}", line);
+            Fixture {
+                name: fields[0].to_string(),
+                generator: fields[1].to_string(),
+                key: hex::decode(fields[2]).unwrap(),
+                length: fields[3].parse().unwrap(),
+                expected: fields[4].to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Сообщение эталона: байт i равен i % 251 (как в generate.py).
+fn fixture_message(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i % 251) as u8).collect()
+}
+
+/// Эталонные шифротексты OpenSSL и Python совпадают с нашими, а
+/// расшифровка восстанавливает сообщение
+#[test]
+fn test_golden_fixtures() {
+    let fixtures = load_manifest();
+    assert!(fixtures.iter().any(|f| f.key.len() == 256));
+    assert!(fixtures.iter().any(|f| f.length == 1));
+    assert!(fixtures.iter().any(|f| f.length > 4096));
+
+    for fixture in fixtures {
+        let message = fixture_message(fixture.length);
+        let ciphertext = Rc4::new(&fixture.key).apply(&message);
+
+        match fixture.expected.strip_prefix("sha256:") {
+            Some(digest) => assert_eq!(
+                hex::encode(&sha256(&ciphertext)),
+                digest,
+                "{} ({})",
+                fixture.name,
+                fixture.generator
+            ),
+            None => {
+                let expected = fs::read(Path::new(FIXTURES).join(&fixture.expected)).unwrap();
+                assert!(
+                    ciphertext == expected,
+                    "{}: mismatch with {} output",
+                    fixture.name,
+                    fixture.generator
+                );
+                assert_eq!(Rc4::new(&fixture.key).apply(&expected), message);
+            }
+        }
+    }
+}
+
+/// Пересоздает эталоны OpenSSL (аналог generate.py для этой части):
+/// `cargo test regenerate_openssl_fixtures -- --ignored`
+#[test]
+#[ignore]
+fn regenerate_openssl_fixtures() {
+    let manifest_path = Path::new(FIXTURES).join("manifest.txt");
+    let text = fs::read_to_string(&manifest_path).unwrap();
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if line.starts_with('#') || fields.get(1) != Some(&"openssl") {
+            lines.push(line.to_string());
+            continue;
+        }
+        let key = hex::decode(fields[2]).unwrap();
+        let cipher = if key.len() == 5 { "-rc4-40" } else { "-rc4" };
+        let message = fixture_message(fields[3].parse().unwrap());
+        let Some(ciphertext) = openssl_rc4(cipher, &key, &message) else {
+            eprintln!("openssl with RC4 is not available; fixtures left unchanged");
+            return;
+        };
+
+        let expected = if fields[4].starts_with("sha256:") {
+            format!("sha256:{}", hex::encode(&sha256(&ciphertext)))
+        } else {
+            fs::write(Path::new(FIXTURES).join(fields[4]), &ciphertext).unwrap();
+            fields[4].to_string()
+        };
+        lines.push(format!(
+            "{} {} {} {} {}",
+            fields[0], fields[1], fields[2], fields[3], expected
+        ));
+    }
+    fs::write(manifest_path, lines.join("\n") + "\n").unwrap();
+}
+
+/// Шифрует `data` через `openssl enc -<cipher>`; `None`, если OpenSSL недоступен.
+fn openssl_rc4(cipher: &str, key: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let base = ["enc", cipher, "-nosalt", "-K"];
+    let variants: [&[&str]; 2] = [&[], &["-provider", "legacy", "-provider", "default"]];
+
+    for extra in variants {
+        let child = Command::new("openssl")
+            .args(base)
+            .arg(hex::encode(key))
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            return None;
+        };
+
+        // Пишем из отдельного потока, чтобы не упереться в буфер канала
+        let mut stdin = child.stdin.take().unwrap();
+        let input = data.to_vec();
+        let writer = thread::spawn(move || stdin.write_all(&input));
+
+        let output = child.wait_with_output().ok()?;
+        let _ = writer.join();
+        if output.status.success() {
+            return Some(output.stdout);
+        }
+    }
+    None
+}
+
+/// Ciphertext `process` совпадает с OpenSSL для 40- и 128-битных ключей
+#[test]
+fn test_openssl_interop() {
+    let data = Rc4::new(b"interop data").keystream_to_vec(100_000);
+    let cases: [(&str, &[u8]); 3] = [
+        ("-rc4-40", b"\x01\x02\x03\x04\x05"),
+        ("-rc4", b"0123456789abcdef"),
+        ("-rc4", &[0xff; 16]),
+    ];
+
+    for (cipher, key) in cases {
+        let Some(expected) = openssl_rc4(cipher, key, &data) else {
+            eprintln!("skipping OpenSSL interop test: openssl with RC4 is not available");
+            return;
+        };
+
+        let mut actual = data.clone();
+        Rc4::new(key).process(&mut actual);
+        assert!(
+            actual == expected,
+            "mismatch with openssl enc {} for key {}",
+            cipher,
+            hex::encode(key)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Минимальная запись JSON (RFC 8259) для отчетов бенчмарков, без
+//! зависимостей. `Display` для `Value` выводит компактный JSON; порядок
+//! ключей объекта сохраняется, NaN и бесконечности записываются как `null`.
+//! Читают JSON (тестовые векторы, `BenchReport::from_json`) через
+//! `serde_json`.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            // Display для f64 выводит кратчайшую запись без экспоненты,
+            // которая читается обратно в то же число
+            Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Value::Number(_) => f.write_str("null"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (n, item) in items.iter().enumerate() {
+                    if n > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(fields) => {
+                f.write_str("{")?;
+                for (n, (key, value)) in fields.iter().enumerate() {
+                    if n > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Сериализация читается обратно в то же значение
+    #[test]
+    fn test_display_roundtrip() {
+        let value = Value::Object(vec![
+            ("n".to_string(), Value::Number(0.1 + 0.2)),
+            ("big".to_string(), Value::Number(1e300)),
+            ("tiny".to_string(), Value::Number(-5e-324)),
+            (
+                "s".to_string(),
+                Value::String("q\"\\\n\u{1}é😀".to_string()),
+            ),
+            (
+                "a".to_string(),
+                Value::Array(vec![Value::Null, Value::Bool(true), Value::Array(vec![])]),
+            ),
+        ]);
+        let parsed: serde_json::Value = serde_json::from_str(&value.to_string()).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({
+                "n": 0.1 + 0.2,
+                "big": 1e300,
+                "tiny": -5e-324,
+                "s": "q\"\\\n\u{1}é😀",
+                "a": [null, true, []],
+            })
+        );
+        assert_eq!(Value::Number(f64::NAN).to_string(), "null");
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+    }
+}
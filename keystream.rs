@@ -0,0 +1,261 @@
+//! Гамма RC4 как итератор и операторный синтаксис `data ^= keystream`,
+//! а также буферизованная гамма для нерегулярного потребления.
+
+use std::collections::VecDeque;
+use std::ops::BitXorAssign;
+
+use crate::{Rc4, Rc4Error};
+
+/// Размер порции, которой `Rc4Deque` пополняет буфер.
+const DEQUE_REFILL: usize = 4096;
+/// Порог, ниже которого буфер `Rc4Deque` пополняется.
+const DEQUE_LOW_WATER: usize = 256;
+
+/// Итератор по байтам гаммы, заимствующий шифр; см. `Rc4::keystream`
+/// и `Rc4::keystream_n`. Каждый выданный байт продвигает состояние шифра.
+pub struct Rc4KeyStream<'a> {
+    cipher: &'a mut Rc4,
+    remaining: Option<usize>,
+}
+
+impl Rc4KeyStream<'_> {
+    /// Сколько байт еще можно получить; `None` для неограниченной гаммы.
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining
+    }
+}
+
+impl Iterator for Rc4KeyStream<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        match &mut self.remaining {
+            Some(0) => return None,
+            Some(n) => *n -= 1,
+            None => {}
+        }
+        Some(self.cipher.next_byte())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining {
+            Some(n) => (n, Some(n)),
+            None => (usize::MAX, None),
+        }
+    }
+}
+
+/// Изменяемый буфер как левая часть `^=` с гаммой в правой части.
+///
+/// Rust не допускает временное значение слева от `^=`, поэтому обертку
+/// нужно сначала связать с переменной:
+///
+/// ```ignore
+/// let mut bytes = Bytes(&mut data);
+/// bytes ^= cipher.keystream_n(bytes.0.len());
+/// ```
+pub struct Bytes<'a>(pub &'a mut [u8]);
+
+impl BitXorAssign<Rc4KeyStream<'_>> for Bytes<'_> {
+    /// Накладывает ровно `self.0.len()` байт гаммы, как `Rc4::process`.
+    /// Паникует, если ограниченная гамма короче буфера; в этом случае
+    /// ни буфер, ни состояние шифра не изменяются.
+    fn bitxor_assign(&mut self, keystream: Rc4KeyStream<'_>) {
+        if let Some(remaining) = keystream.remaining {
+            assert!(
+                remaining >= self.0.len(),
+                "keystream has {} bytes left but the buffer is {} bytes",
+                remaining,
+                self.0.len()
+            );
+        }
+        keystream.cipher.process(self.0);
+    }
+}
+
+impl Rc4 {
+    /// Неограниченная гамма этого шифра.
+    pub fn keystream(&mut self) -> Rc4KeyStream<'_> {
+        Rc4KeyStream {
+            cipher: self,
+            remaining: None,
+        }
+    }
+
+    /// Гамма длиной ровно `len` байт.
+    pub fn keystream_n(&mut self, len: usize) -> Rc4KeyStream<'_> {
+        Rc4KeyStream {
+            cipher: self,
+            remaining: Some(len),
+        }
+    }
+
+    /// Лениво шифрует `input`: выдает `input[k] ^ гамма[k]`, не выделяя
+    /// выходной буфер. Состояние продвигается только на потребленные байты:
+    /// после `.take(3)` — ровно на три.
+    pub fn encrypt_zip<'a>(&'a mut self, input: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+        // zip берет байт гаммы только после байта входа, поэтому конец
+        // входа не расходует лишний байт гаммы
+        input
+            .iter()
+            .zip(self.keystream())
+            .map(|(&byte, k)| byte ^ k)
+    }
+}
+
+/// Шифр с заранее сгенерированной гаммой в `VecDeque`: для потребителей,
+/// которые берут то по байту, то по нескольку десятков байт. Гамма
+/// генерируется порциями по 4 КБ, когда в буфере остается меньше 256 байт.
+///
+/// Последовательность байт гаммы та же, что у `Rc4` с тем же ключом, но
+/// внутренний шифр опережает выданную гамму на размер буфера.
+pub struct Rc4Deque {
+    cipher: Rc4,
+    buffer: VecDeque<u8>,
+}
+
+impl Rc4Deque {
+    pub fn new(key: &[u8]) -> Result<Self, Rc4Error> {
+        Rc4::try_new(key).map(Rc4Deque::from_cipher)
+    }
+
+    /// Буферизует гамму существующего шифра с его текущей позиции.
+    pub fn from_cipher(cipher: Rc4) -> Self {
+        let mut deque = Rc4Deque {
+            cipher,
+            buffer: VecDeque::with_capacity(DEQUE_REFILL + DEQUE_LOW_WATER),
+        };
+        deque.refill();
+        deque
+    }
+
+    /// Число уже сгенерированных, но еще не выданных байт гаммы.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        if self.buffer.len() < DEQUE_LOW_WATER {
+            self.refill();
+        }
+        self.buffer.pop_front().expect("buffer was just refilled")
+    }
+
+    /// Накладывает гамму на `data` (шифрование и расшифровка симметричны).
+    pub fn apply(&mut self, data: &mut [u8]) {
+        let buffered = data.len().min(self.buffer.len());
+        let (head, rest) = data.split_at_mut(buffered);
+        for (byte, k) in head.iter_mut().zip(self.buffer.drain(..buffered)) {
+            *byte ^= k;
+        }
+
+        // Буфер исчерпан: шифр стоит ровно на следующем байте гаммы, и
+        // длинный остаток быстрее обработать напрямую
+        if rest.len() >= DEQUE_REFILL {
+            self.cipher.process(rest);
+        } else {
+            for byte in rest.iter_mut() {
+                *byte ^= self.next_byte();
+            }
+        }
+
+        if self.buffer.len() < DEQUE_LOW_WATER {
+            self.refill();
+        }
+    }
+
+    fn refill(&mut self) {
+        let mut chunk = [0u8; DEQUE_REFILL];
+        self.cipher.fill_keystream(&mut chunk);
+        self.buffer.extend(chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Полное потребление совпадает с apply, частичное продвигает
+    /// состояние ровно на число взятых байт
+    #[test]
+    fn test_encrypt_zip() {
+        let input = b"Plaintext, but a little longer";
+        let mut cipher = Rc4::new(b"Key");
+        let encrypted: Vec<u8> = cipher.encrypt_zip(input).collect();
+        assert_eq!(encrypted, Rc4::new(b"Key").apply(input));
+        assert_eq!(cipher.byte_position(), input.len() as u64);
+
+        let mut cipher = Rc4::new(b"Key");
+        let head: Vec<u8> = cipher.encrypt_zip(input).take(3).collect();
+        assert_eq!(head, encrypted[..3]);
+        assert_eq!(cipher.byte_position(), 3);
+        assert_eq!(cipher.apply(&input[3..]), encrypted[3..]);
+    }
+
+    /// `Bytes ^= keystream_n` эквивалентен process
+    #[test]
+    fn test_bitxor_assign_matches_process() {
+        let mut rc4 = Rc4::new(b"Key");
+        let mut reference = Rc4::new(b"Key");
+
+        for len in [0, 1, 17, 1000] {
+            let mut data: Vec<u8> = (0..len).map(|n| n as u8).collect();
+            let mut expected = data.clone();
+            reference.process(&mut expected);
+
+            let mut bytes = Bytes(&mut data);
+            bytes ^= rc4.keystream_n(len);
+            assert_eq!(data, expected);
+        }
+        assert_eq!(
+            rc4.keystream().take(8).collect::<Vec<_>>(),
+            reference.keystream_to_vec(8)
+        );
+    }
+
+    /// Итератор keystream_n выдает ровно len байт гаммы
+    #[test]
+    fn test_keystream_n_iterator() {
+        let mut rc4 = Rc4::new(b"Key");
+        let bytes: Vec<u8> = rc4.keystream_n(100).collect();
+        assert_eq!(bytes, Rc4::new(b"Key").keystream_to_vec(100));
+    }
+
+    /// Rc4Deque выдает ту же гамму, что и Rc4, при любом чередовании
+    /// next_byte и apply с порциями произвольной длины
+    #[test]
+    fn test_deque_matches_rc4() {
+        let mut deque = Rc4Deque::new(b"Key").unwrap();
+        let mut reference = Rc4::new(b"Key");
+        let mut sizes = Rc4::new(b"chunk sizes");
+
+        for round in 0..500 {
+            let len = match sizes.next_byte() % 4 {
+                0 => 1,
+                1 => sizes.next_byte() as usize,
+                2 => sizes.next_byte() as usize * 37,
+                _ => 5000 + sizes.next_byte() as usize,
+            };
+            let mut expected = vec![round as u8; len];
+            reference.process(&mut expected);
+
+            let mut actual = vec![round as u8; len];
+            if len == 1 && round % 2 == 0 {
+                actual[0] ^= deque.next_byte();
+            } else {
+                deque.apply(&mut actual);
+            }
+            assert_eq!(actual, expected, "round {} len {}", round, len);
+        }
+    }
+
+    /// Короткая гамма не применяется частично
+    #[test]
+    #[should_panic(expected = "keystream has 3 bytes left")]
+    fn test_short_keystream_panics() {
+        let mut rc4 = Rc4::new(b"Key");
+        let mut data = [0u8; 4];
+        let mut bytes = Bytes(&mut data);
+        bytes ^= rc4.keystream_n(3);
+    }
+}
@@ -0,0 +1,2948 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::mem::MaybeUninit;
+use std::ops::Range;
+
+mod analysis;
+pub mod armor;
+#[cfg(feature = "async-stream")]
+mod async_stream;
+mod authenticated;
+mod base64;
+pub mod bench;
+#[cfg(feature = "bytemuck")]
+mod bytemuck_support;
+#[cfg(feature = "bytes")]
+mod bytes_support;
+mod cascade;
+pub mod cli;
+#[cfg(feature = "codec")]
+mod codec;
+#[cfg(all(test, feature = "compat-tests"))]
+mod compat;
+mod crc32;
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+pub mod hex;
+#[cfg(test)]
+mod interop;
+mod json;
+mod keystream;
+mod limited;
+mod md5;
+#[cfg(feature = "memlock")]
+mod memlock;
+#[cfg(feature = "std")]
+mod nonce_guard;
+pub mod office_legacy;
+pub mod pdf;
+#[cfg(feature = "python")]
+mod python;
+mod ratchet;
+mod resume;
+mod sha256;
+#[cfg(feature = "simd")]
+mod simd;
+mod state_wire;
+mod stream;
+#[cfg(feature = "test-utils")]
+pub mod testvec;
+
+pub use analysis::KeyBiasReport;
+#[cfg(feature = "debug-trace")]
+pub use analysis::PrgaStep;
+#[cfg(feature = "stats")]
+pub use analysis::{keystream_byte_distribution, keystream_byte_distribution_at};
+#[cfg(feature = "async-stream")]
+pub use async_stream::{Rc4Stream, Rc4StreamExt};
+pub use authenticated::{Rc4HmacReader, HMAC_TAG_LEN};
+pub use bench::{benchmark, measure_throughput_mb_s};
+pub use cascade::Rc4Cascade;
+#[cfg(feature = "codec")]
+pub use codec::Rc4Codec;
+pub use keystream::{Bytes, Rc4Deque, Rc4KeyStream};
+pub use limited::Rc4Limited;
+#[cfg(feature = "memlock")]
+pub use memlock::LockedRc4;
+#[cfg(feature = "std")]
+pub use nonce_guard::NonceGuard;
+pub use ratchet::Rc4Ratchet;
+pub use state_wire::{StateParseError, STATE_WIRE_LEN, STATE_WIRE_VERSION};
+pub use stream::{
+    KeystreamReader, Rc4ChunkEncryptor, Rc4Cursor, Rc4Duplex, Rc4Reader, Rc4Seekable, Rc4Writer,
+};
+
+/// Ошибки, возвращаемые API шифра.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rc4Error {
+    /// Пустой ключ.
+    EmptyKey,
+    /// Ключ длиннее 256 байт (в поле — фактическая длина).
+    KeyTooLong(usize),
+    /// Длина данных не совпадает с длиной переданной гаммы.
+    LengthMismatch { data: usize, keystream: usize },
+    /// Ключ отклонен эвристической проверкой `key_quality`.
+    WeakKey(KeyQuality),
+    /// Диапазон `start..end` выходит за пределы буфера длины `len`.
+    RangeOutOfBounds {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    /// Входные данные короче обязательного префикса (например, IV).
+    InputTooShort { len: usize, min: usize },
+    /// Пара (ключ, nonce) уже использовалась; см. `NonceGuard`.
+    NonceReused,
+    /// Пустой nonce там, где он обязателен.
+    EmptyNonce,
+    /// Nonce длиннее, чем допускает операция.
+    NonceTooLong { len: usize, max: usize },
+    /// Переменная окружения с ключом не задана (в поле — ее имя).
+    EnvVarMissing(String),
+    /// Значение переменной окружения не является строкой UTF-8.
+    EnvVarInvalid(String),
+    /// Hex-запись ключа (см. `FromStr for Rc4`) или шифротекста
+    /// (см. `apply_from_hex`) не разобрана.
+    InvalidHex(hex::HexError),
+    /// Длина данных `len` не равна требуемому размеру `expected`
+    /// (например, размеру структуры в `decrypt_struct`).
+    SizeMismatch { len: usize, expected: usize },
+    /// Обработка превысила бы лимит `Rc4Limited`: уже израсходовано
+    /// `processed` байт из `limit`.
+    UsageLimitExceeded { processed: u64, limit: u64 },
+}
+
+impl fmt::Display for Rc4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rc4Error::EmptyKey => write!(f, "key must not be empty"),
+            Rc4Error::KeyTooLong(len) => {
+                write!(f, "key length {} exceeds the maximum of 256 bytes", len)
+            }
+            Rc4Error::LengthMismatch { data, keystream } => write!(
+                f,
+                "data length {} does not match keystream length {}",
+                data, keystream
+            ),
+            Rc4Error::WeakKey(quality) => write!(f, "weak key rejected: {}", quality),
+            Rc4Error::RangeOutOfBounds { start, end, len } => write!(
+                f,
+                "range {}..{} is out of bounds for a buffer of length {}",
+                start, end, len
+            ),
+            Rc4Error::InputTooShort { len, min } => write!(
+                f,
+                "input of {} bytes is shorter than the required {} bytes",
+                len, min
+            ),
+            Rc4Error::NonceReused => write!(f, "this key and nonce pair was already used"),
+            Rc4Error::EmptyNonce => write!(f, "nonce must not be empty"),
+            Rc4Error::NonceTooLong { len, max } => {
+                write!(f, "nonce is {} bytes, at most {} allowed", len, max)
+            }
+            Rc4Error::EnvVarMissing(name) => {
+                write!(f, "environment variable {} is not set", name)
+            }
+            Rc4Error::EnvVarInvalid(name) => {
+                write!(f, "environment variable {} is not valid UTF-8", name)
+            }
+            Rc4Error::InvalidHex(e) => write!(f, "invalid hex: {}", e),
+            Rc4Error::SizeMismatch { len, expected } => write!(
+                f,
+                "input of {} bytes does not match the expected size of {} bytes",
+                len, expected
+            ),
+            Rc4Error::UsageLimitExceeded { processed, limit } => write!(
+                f,
+                "keystream usage limit exceeded: {} of {} bytes already used",
+                processed, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Rc4Error {}
+
+/// Результат эвристической проверки ключа (см. `key_quality`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyQuality {
+    /// Явных признаков ошибки не найдено.
+    Acceptable,
+    /// Все байты ключа одинаковы (например, нулевой буфер).
+    AllBytesEqual,
+}
+
+impl fmt::Display for KeyQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyQuality::Acceptable => write!(f, "no obvious weakness"),
+            KeyQuality::AllBytesEqual => write!(f, "all key bytes are identical"),
+        }
+    }
+}
+
+fn validate_key_len(len: usize) -> Result<(), Rc4Error> {
+    match len {
+        0 => Err(Rc4Error::EmptyKey),
+        1..=256 => Ok(()),
+        _ => Err(Rc4Error::KeyTooLong(len)),
+    }
+}
+
+/// Пошаговая настройка шифра для параметров, которые не помещаются
+/// в простые конструкторы.
+pub struct Rc4Builder<'a> {
+    key: &'a [u8],
+    whitening: &'a [u8],
+}
+
+impl<'a> Rc4Builder<'a> {
+    pub fn new(key: &'a [u8]) -> Self {
+        Rc4Builder {
+            key,
+            whitening: &[],
+        }
+    }
+
+    /// Перед KSA ключ циклически XOR-ится с `whitening_key`
+    /// (`key[n] ^ whitening_key[n % whitening_key.len()]`).
+    ///
+    /// Отбеленный ключ нигде не сохраняется: KSA читает его байты на лету,
+    /// и после построения остается только S-box. С `rekeying` шифр хранит
+    /// исходный, неотбеленный ключ, и `rekey_with_nonce` смешивает nonce
+    /// с ним. Пустой `whitening_key` отключает отбеливание.
+    pub fn with_key_whitening(mut self, whitening_key: &'a [u8]) -> Self {
+        self.whitening = whitening_key;
+        self
+    }
+
+    pub fn build(self) -> Result<Rc4, Rc4Error> {
+        validate_key_len(self.key.len())?;
+        let (key, whitening) = (self.key, self.whitening);
+        if whitening.is_empty() {
+            return Ok(Rc4::ksa(key, key.len(), |n| key[n]));
+        }
+        Ok(Rc4::ksa(key, key.len(), |n| {
+            key[n] ^ whitening[n % whitening.len()]
+        }))
+    }
+}
+
+/// Эвристическая проверка ключа на явные признаки ошибки.
+///
+/// Ключ из одинаковых байт (все нули, все 0x41 и т.п.) дает особенно слабое
+/// расписание и обычно означает неинициализированный буфер или неверно
+/// разобранную конфигурацию. Это защита от ошибок, а не оценка стойкости:
+/// `Acceptable` не означает, что ключ хороший.
+pub fn key_quality(key: &[u8]) -> KeyQuality {
+    match key.split_first() {
+        Some((first, rest)) if rest.iter().all(|b| b == first) => KeyQuality::AllBytesEqual,
+        _ => KeyQuality::Acceptable,
+    }
+}
+
+/// Порог `key_entropy_estimate`, ниже которого ключ считается подозрительным;
+/// с feature `log` о таком ключе пишется `log::warn!`.
+pub const KEY_ENTROPY_WARN_BITS: f64 = 64.0;
+
+/// Оценка энтропии ключа в битах по Шеннону: энтропия распределения байт
+/// ключа, умноженная на его длину. Ключ из одинаковых байт дает 0.
+///
+/// Оценка видит только частоты байт, а не их происхождение, поэтому
+/// ограничена сверху `len * log2(min(len, 256))`: ключ из 16 различных байт
+/// дает максимум 64 бита, а `8 * len` достижимо только от 256 байт. Пароль
+/// вроде `b"abcdefgh..."` получит высокую оценку — это проверка на явные
+/// ошибки (повторы, короткий ключ), а не мера стойкости. Функция ничего
+/// не печатает: сравнить оценку с `KEY_ENTROPY_WARN_BITS` и решить, что
+/// делать, — дело вызывающего; с feature `log` при оценке ниже порога
+/// пишется `log::warn!`.
+pub fn key_entropy_estimate(key: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in key {
+        counts[byte as usize] += 1;
+    }
+    let len = key.len() as f64;
+    let bits_per_byte: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    let bits = bits_per_byte * len;
+
+    #[cfg(feature = "log")]
+    if bits < KEY_ENTROPY_WARN_BITS {
+        log::warn!(
+            "key entropy estimate is {:.1} bits, below {} bits",
+            bits,
+            KEY_ENTROPY_WARN_BITS
+        );
+    }
+    bits
+}
+
+/// Начальное значение аккумулятора для `encrypt_with_rolling_mac`.
+pub const ROLLING_MAC_INIT: u64 = 0xcbf2_9ce4_8422_2325;
+const ROLLING_MAC_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Обновляет аккумулятор `encrypt_with_rolling_mac` байтами `data`
+/// (FNV-1a, 64 бита). Подача частями дает тот же результат, что и целиком.
+///
+/// Это контрольная сумма, а не криптографический MAC: в ней нет ключа, и
+/// любой, кто может изменить шифротекст и угадать открытый текст, может
+/// пересчитать значение. Защищает только от случайных повреждений.
+pub fn rolling_mac_update(state: &mut u64, data: &[u8]) {
+    for &byte in data {
+        *state = (*state ^ byte as u64).wrapping_mul(ROLLING_MAC_PRIME);
+    }
+}
+
+/// Длина IV в `encrypt_with_prepended_iv`.
+pub const PREPENDED_IV_LEN: usize = 16;
+
+/// Вариант ядра PRGA для `Rc4::process_bytes_with` (feature `unsafe-opt`).
+#[cfg(feature = "unsafe-opt")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PrgaVariant {
+    /// Индексация с проверками и `swap`.
+    Checked,
+    /// Прямая запись и `get_unchecked` (путь `process` с этой feature).
+    Unchecked,
+}
+
+/// Реализация потокового шифра RC4 на Rust.
+/// Оптимизированная версия с использованием арифметики u8 и in-place обработки.
+///
+/// `clone()` создает независимую копию состояния: оригинал и копия выдают
+/// одну и ту же гамму с точки ветвления, и обработка в одном не влияет на
+/// другой. Это намеренное повторное использование гаммы — XOR двух
+/// шифротекстов, полученных от них, равен XOR открытых текстов, — поэтому
+/// клонирование годится только для анализа, фаззинга и воспроизведения
+/// протоколов. Копия содержит тот же S-box (и ключ с `rekeying`) и
+/// затирается отдельно от оригинала.
+#[derive(Clone)]
+pub struct Rc4 {
+    s: [u8; 256], // Массив состояния (S-box)
+    i: u8,        // Счетчик i (u8 обеспечивает автоматический mod 256)
+    j: u8,        // Счетчик j (u8 обеспечивает автоматический mod 256)
+    /// Байт гаммы, выданных или пропущенных с момента KSA.
+    bytes_processed: u64,
+    /// Ключ, переданный конструктору (без whitening, nonce и счетчика),
+    /// для `rekey_with_nonce`.
+    #[cfg(feature = "rekeying")]
+    key: Vec<u8>,
+}
+
+/// Результат `Rc4::step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rc4Step {
+    /// Счетчики после шага.
+    pub i: u8,
+    pub j: u8,
+    /// Индекс байта гаммы `t = S[i] + S[j]`.
+    pub output_index: u8,
+    /// Выданный байт гаммы `S[t]`.
+    pub byte: u8,
+}
+
+/// Снимок состояния шифра (S-box и счетчики) для контрольных точек.
+///
+/// S-box эквивалентен ключу: снимок следует хранить и уничтожать так же
+/// аккуратно, как сам ключ. С feature `zeroize` снимок реализует `Zeroize`.
+///
+/// `==` сравнивает все 258 байт за постоянное время; с feature `subtle`
+/// доступна явная форма `ct_eq`. Сам `Rc4` намеренно не реализует `PartialEq`,
+/// для него есть `same_state`.
+#[derive(Clone)]
+pub struct Rc4State {
+    s: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4State {
+    pub fn sbox(&self) -> &[u8; 256] {
+        &self.s
+    }
+
+    pub fn i(&self) -> u8 {
+        self.i
+    }
+
+    pub fn j(&self) -> u8 {
+        self.j
+    }
+
+    /// Сравнение за постоянное время с результатом `subtle::Choice`.
+    #[cfg(feature = "subtle")]
+    pub fn ct_eq(&self, other: &Rc4State) -> subtle::Choice {
+        use subtle::ConstantTimeEq;
+
+        self.s[..].ct_eq(&other.s[..]) & self.i.ct_eq(&other.i) & self.j.ct_eq(&other.j)
+    }
+}
+
+impl PartialEq for Rc4State {
+    fn eq(&self, other: &Rc4State) -> bool {
+        // Накапливаем различия по всем байтам без раннего выхода
+        let mut diff = (self.i ^ other.i) | (self.j ^ other.j);
+        for (a, b) in self.s.iter().zip(&other.s) {
+            diff |= a ^ b;
+        }
+        std::hint::black_box(diff) == 0
+    }
+}
+
+impl Eq for Rc4State {}
+
+/// Разбирает ключ из hex-строки и выполняет KSA: `"4b6579".parse::<Rc4>()`.
+///
+/// Формат — четное число шестнадцатеричных цифр любого регистра, от 2 до 512
+/// (1–256 байт), без префикса `0x`, пробелов и разделителей. Ошибка разбора
+/// возвращается как `Rc4Error::InvalidHex`, недопустимая длина — как
+/// `EmptyKey`/`KeyTooLong`.
+impl std::str::FromStr for Rc4 {
+    type Err = Rc4Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let key = hex::decode(text).map_err(Rc4Error::InvalidHex)?;
+        Rc4::try_new(key)
+    }
+}
+
+/// Перемещает состояние шифра в снимок без копирования через промежуточный
+/// буфер. Ключ, сохраненный для `rekey_with_nonce`, при этом отбрасывается.
+impl From<Rc4> for Rc4State {
+    fn from(rc4: Rc4) -> Self {
+        Rc4State {
+            s: rc4.s,
+            i: rc4.i,
+            j: rc4.j,
+        }
+    }
+}
+
+/// То же, что `Rc4::from_state`.
+impl From<Rc4State> for Rc4 {
+    fn from(state: Rc4State) -> Self {
+        Rc4::from_state(state)
+    }
+}
+
+#[cfg(feature = "subtle")]
+impl subtle::ConstantTimeEq for Rc4State {
+    fn ct_eq(&self, other: &Rc4State) -> subtle::Choice {
+        Rc4State::ct_eq(self, other)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Rc4State {
+    fn zeroize(&mut self) {
+        self.s.zeroize();
+        self.i.zeroize();
+        self.j.zeroize();
+    }
+}
+
+/// С feature `zeroize` (ее включает и `secrecy`) состояние шифра и
+/// сохраненный для `rekey_with_nonce` ключ затираются при удалении.
+#[cfg(feature = "zeroize")]
+impl Drop for Rc4 {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+
+        self.s.zeroize();
+        self.i.zeroize();
+        self.j.zeroize();
+        #[cfg(feature = "rekeying")]
+        self.key.zeroize();
+    }
+}
+
+/// Ключ, который затирается при удалении. Хранилище — `Vec<u8>` по
+/// умолчанию или массив вроде `[u8; 16]`, если ключ не должен попадать в
+/// кучу. Реализует `AsRef<[u8]>`, поэтому принимается всеми конструкторами
+/// `Rc4` (по значению или по ссылке) без копирования в незатираемый буфер.
+/// `Debug` не раскрывает содержимое.
+#[cfg(feature = "zeroize")]
+pub struct SecretKey<K: zeroize::Zeroize = Vec<u8>>(K);
+
+#[cfg(feature = "zeroize")]
+impl<K: zeroize::Zeroize> SecretKey<K> {
+    pub fn new(key: K) -> Self {
+        SecretKey(key)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: zeroize::Zeroize> From<K> for SecretKey<K> {
+    fn from(key: K) -> Self {
+        SecretKey(key)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: zeroize::Zeroize + AsRef<[u8]>> AsRef<[u8]> for SecretKey<K> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// Для чтения ключа сразу в затираемый буфер.
+#[cfg(feature = "zeroize")]
+impl<K: zeroize::Zeroize + AsMut<[u8]>> AsMut<[u8]> for SecretKey<K> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.0.as_mut()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: zeroize::Zeroize> fmt::Debug for SecretKey<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<K: zeroize::Zeroize> Drop for SecretKey<K> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Rc4 {
+    /// Создает новый экземпляр RC4 и выполняет KSA (Key-Scheduling Algorithm).
+    /// Паникует, если длина ключа вне диапазона 1..=256; см. `try_new`.
+    ///
+    /// Ключ принимается как любой `AsRef<[u8]>`: `&[u8]`, `&str`, `Vec<u8>`,
+    /// `[u8; N]`; то же верно для остальных конструкторов.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        match Self::try_new(key) {
+            Ok(rc4) => rc4,
+            Err(_) => panic!("Key length must be between 1 and 256 bytes"),
+        }
+    }
+
+    /// Как `new`, но возвращает ошибку вместо паники при недопустимой длине ключа.
+    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self, Rc4Error> {
+        let key = key.as_ref();
+        validate_key_len(key.len())?;
+        Ok(Self::ksa(key, key.len(), |n| key[n]))
+    }
+
+    /// Как `try_new`, но для ключа в `secrecy::SecretSlice` (feature
+    /// `secrecy`). KSA читает ключ через `expose_secret` побайтно, не копируя
+    /// его во временные буферы; единственная копия — ключ, сохраняемый с
+    /// `rekeying`, — затирается вместе с шифром.
+    ///
+    /// Ключ хранится в секретном контейнере с момента чтения:
+    ///
+    /// ```ignore
+    /// let key: SecretSlice<u8> = fs::read("key.bin")?.into();
+    /// let mut cipher = Rc4::new_secret(&key)?;
+    /// drop(key); // затирается здесь, шифр — при своем удалении
+    /// cipher.process(&mut data);
+    /// ```
+    #[cfg(feature = "secrecy")]
+    pub fn new_secret(key: &secrecy::SecretSlice<u8>) -> Result<Self, Rc4Error> {
+        use secrecy::ExposeSecret;
+
+        Self::try_new(key.expose_secret())
+    }
+
+    /// Создает шифр из общего секрета Диффи — Хеллмана (или ECDH): секрет
+    /// нельзя использовать как ключ напрямую, поэтому ключом служат первые
+    /// 16 байт HKDF-SHA256 (RFC 5869) без соли с контекстом `info` (`None`
+    /// равно пустому). Разные `info` дают независимые ключи из одного
+    /// секрета, например для двух направлений обмена. Пустой секрет
+    /// отклоняется как `EmptyKey`. Feature `kdf`.
+    #[cfg(feature = "kdf")]
+    pub fn new_from_shared_secret(dh_secret: &[u8], info: Option<&[u8]>) -> Result<Self, Rc4Error> {
+        if dh_secret.is_empty() {
+            return Err(Rc4Error::EmptyKey);
+        }
+        let mut key = [0u8; 16];
+        sha256::hkdf_sha256(&[], dh_secret, info.unwrap_or_default(), &mut key);
+        Self::try_new(key)
+    }
+
+    /// Создает шифр с ключом сеанса `nonce || key`, как в WEP: открытый
+    /// одноразовый IV стоит перед секретным ключом. Повтор пары (key, nonce)
+    /// повторяет гамму, а такие связанные ключи уязвимы к атаке FMS —
+    /// конструктор нужен для совместимости, а не для новых протоколов.
+    /// Секретный ключ не может быть пустым, суммарная длина — не больше 256.
+    pub fn new_with_nonce(key: impl AsRef<[u8]>, nonce: &[u8]) -> Result<Self, Rc4Error> {
+        let key = key.as_ref();
+        validate_key_len(key.len())?;
+        let len = nonce.len() + key.len();
+        validate_key_len(len)?;
+        Ok(Self::ksa(key, len, |n| match n.checked_sub(nonce.len()) {
+            Some(k) => key[k],
+            None => nonce[n],
+        }))
+    }
+
+    /// Создает шифр с ключом сообщения `base_key || counter` (счетчик — 8 байт
+    /// little-endian), как в системах с номером сообщения в ключе. Базовый
+    /// ключ не может быть пустым, суммарная длина — не больше 256 (базовый
+    /// ключ до 248 байт).
+    ///
+    /// Схема хрупкая: повтор счетчика с тем же ключом повторяет гамму, а
+    /// ключи соседних сообщений различаются несколькими байтами — это
+    /// связанные ключи, к которым применимы атаки класса FMS. Для новых
+    /// протоколов лучше выводить ключ сообщения хешем
+    /// (`new_from_shared_secret`) и отбрасывать начало гаммы.
+    pub fn new_with_counter(base_key: impl AsRef<[u8]>, counter: u64) -> Result<Self, Rc4Error> {
+        let base_key = base_key.as_ref();
+        validate_key_len(base_key.len())?;
+        let counter = counter.to_le_bytes();
+        let len = base_key.len() + counter.len();
+        validate_key_len(len)?;
+        Ok(Self::ksa(base_key, len, |n| {
+            match n.checked_sub(base_key.len()) {
+                Some(c) => counter[c],
+                None => base_key[n],
+            }
+        }))
+    }
+
+    /// Шифрует `data` на ключе `iv || key` со случайным 16-байтовым IV из
+    /// `rng` и возвращает `iv || ciphertext`; см. `decrypt_with_prepended_iv`.
+    ///
+    /// Схема не обеспечивает целостности: измененный байт шифротекста
+    /// молча меняет соответствующий байт открытого текста. Ключ не длиннее
+    /// 240 байт, чтобы вместе с IV уложиться в 256.
+    #[cfg(feature = "rand_core")]
+    pub fn encrypt_with_prepended_iv(
+        key: &[u8],
+        rng: &mut impl rand_core::RngCore,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Rc4Error> {
+        let mut iv = [0u8; PREPENDED_IV_LEN];
+        rng.fill_bytes(&mut iv);
+        Self::encrypt_with_iv(key, &iv, data)
+    }
+
+    #[cfg(any(test, feature = "rand_core"))]
+    fn encrypt_with_iv(
+        key: &[u8],
+        iv: &[u8; PREPENDED_IV_LEN],
+        data: &[u8],
+    ) -> Result<Vec<u8>, Rc4Error> {
+        let mut cipher = Self::new_with_nonce(key, iv)?;
+        let mut out = Vec::with_capacity(PREPENDED_IV_LEN + data.len());
+        out.extend_from_slice(iv);
+        out.extend_from_slice(data);
+        cipher.process(&mut out[PREPENDED_IV_LEN..]);
+        Ok(out)
+    }
+
+    /// Расшифровывает результат `encrypt_with_prepended_iv`: первые 16 байт —
+    /// IV, остальное — шифротекст.
+    pub fn decrypt_with_prepended_iv(key: &[u8], data: &[u8]) -> Result<Vec<u8>, Rc4Error> {
+        if data.len() < PREPENDED_IV_LEN {
+            return Err(Rc4Error::InputTooShort {
+                len: data.len(),
+                min: PREPENDED_IV_LEN,
+            });
+        }
+        let (iv, ciphertext) = data.split_at(PREPENDED_IV_LEN);
+        Ok(Self::new_with_nonce(key, iv)?.apply(ciphertext))
+    }
+
+    /// Шифрует блок `data` на свежем шифре с ключом `nonce || counter`
+    /// (счетчик в little-endian, всего 16 байт), как блочный шифр в режиме
+    /// CTR: каждый блок расшифровывается независимо, без `skip`.
+    ///
+    /// Кроме nonce, ключ ничего не содержит, поэтому nonce здесь и есть
+    /// секрет. Пара (nonce, counter) не должна повторяться для разных данных,
+    /// а ключи соседних блоков отличаются несколькими байтами — это связанные
+    /// ключи, уязвимые к атаке FMS.
+    pub fn process_with_nonce_counter(nonce: &[u8; 8], counter: u64, data: &mut [u8]) {
+        let mut key = [0u8; 16];
+        key[..8].copy_from_slice(nonce);
+        key[8..].copy_from_slice(&counter.to_le_bytes());
+        Self::new(key).process(data);
+    }
+
+    /// Создает шифр и отбрасывает первые `drop` байт гаммы (RC4-drop[n]).
+    pub fn new_drop(key: impl AsRef<[u8]>, drop: usize) -> Result<Self, Rc4Error> {
+        let mut rc4 = Self::try_new(key)?;
+        rc4.skip(drop);
+        Ok(rc4)
+    }
+
+    /// Создает шифр, уже пропустивший `header_len` байт гаммы: замена пары
+    /// `new` + `skip`, в которой `skip` можно потерять при рефакторинге.
+    /// Шифр без пропуска наружу не попадает. То же, что `new_drop`.
+    pub fn new_with_header_skip(
+        key: impl AsRef<[u8]>,
+        header_len: usize,
+    ) -> Result<Self, Rc4Error> {
+        Self::new_drop(key, header_len)
+    }
+
+    /// Создает шифр с обязательным «прогревом»: первые `warmup_bytes` байт
+    /// гаммы отбрасываются, так как они сильнее всего коррелируют с ключом.
+    ///
+    /// Рекомендуемый минимум — 768 байт (I. Mironov, "(Not So) Random
+    /// Shuffles of RC4", CRYPTO 2002); RFC 4345 для SSH использует 1536.
+    /// Значения меньше 256 почти не дают защиты; метод их не отвергает и
+    /// ничего не печатает, но с feature `log` пишет о них `log::warn!`.
+    /// Паникует при недопустимой длине ключа.
+    pub fn new_with_warmup(key: impl AsRef<[u8]>, warmup_bytes: usize) -> Self {
+        #[cfg(feature = "log")]
+        if warmup_bytes < 256 {
+            log::warn!(
+                "RC4 warmup of {} bytes is below 256; at least 768 is recommended",
+                warmup_bytes
+            );
+        }
+        match Self::new_drop(key, warmup_bytes) {
+            Ok(rc4) => rc4,
+            Err(_) => panic!("Key length must be between 1 and 256 bytes"),
+        }
+    }
+
+    /// Создает шифр с ключом, расширенным повторением `short_key` до
+    /// `target_len` байт (не больше 256), с однократным KSA.
+    ///
+    /// Расширение не добавляет стойкости: энтропия остается той же, что у
+    /// `short_key`, а KSA и так всегда делает 256 шагов, повторяя ключ. Если
+    /// `target_len` кратна длине ключа или не меньше 256, результат совпадает
+    /// с `Rc4::new(short_key)`; иначе получается другое, но не более стойкое
+    /// расписание. Ключи короче 16 байт (128 бит) не рекомендуются ни в
+    /// каком виде.
+    ///
+    /// Паникует при недопустимой длине `short_key` и при `target_len`
+    /// меньше длины ключа.
+    pub fn new_expand(short_key: &[u8], target_len: usize) -> Self {
+        if let Err(e) = validate_key_len(short_key.len()) {
+            panic!("{}", e);
+        }
+        assert!(
+            target_len >= short_key.len(),
+            "target length {} is shorter than the key",
+            target_len
+        );
+        let len = target_len.min(256);
+        Self::ksa(short_key, len, |n| short_key[n % short_key.len()])
+    }
+
+    /// Начинает настройку шифра через `Rc4Builder`.
+    pub fn builder(key: &[u8]) -> Rc4Builder<'_> {
+        Rc4Builder::new(key)
+    }
+
+    /// KSA над ключом длины `key_len`, байты которого выдает `key_byte`.
+    /// Ключ читается побайтно, поэтому производные ключи (например, после
+    /// whitening) не нужно материализовать в памяти. С `rekeying` шифр
+    /// сохраняет `caller_key` — ключ в том виде, в каком его передал
+    /// вызывающий, а не производный.
+    fn ksa(caller_key: &[u8], key_len: usize, key_byte: impl Fn(usize) -> u8) -> Self {
+        let mut s = [0u8; 256];
+        Self::schedule(&mut s, key_len, key_byte);
+        #[cfg(not(feature = "rekeying"))]
+        let _ = caller_key;
+
+        Rc4 {
+            s,
+            i: 0,
+            j: 0,
+            bytes_processed: 0,
+            #[cfg(feature = "rekeying")]
+            key: caller_key.to_vec(),
+        }
+    }
+
+    /// KSA на месте: заполняет `s` заново и перемешивает его ключом.
+    fn schedule(s: &mut [u8; 256], key_len: usize, key_byte: impl Fn(usize) -> u8) {
+        // Шаг 1: Заполнить массив S линейно
+        for i in 0..=255 {
+            s[i as usize] = i;
+        }
+
+        // Шаг 2: Перемешать массив S используя ключ
+        let mut j: u8 = 0;
+        // Используем usize для итерации, чтобы избежать бесконечного цикла при i=255 -> 0
+        for i in 0..256 { 
+            let key_byte = key_byte(i % key_len);
+            
+            // j = (j + S[i] + Key[i % key_length]) % 256;
+            // wrapping_add используется для явного указания на переполнение
+            j = j.wrapping_add(s[i]).wrapping_add(key_byte);
+            
+            s.swap(i, j as usize);
+        }
+    }
+
+    /// Как `try_new`, но дополнительно отклоняет ключи, не прошедшие
+    /// эвристику `key_quality` (например, ключ из одних нулей).
+    pub fn new_checked(key: impl AsRef<[u8]>) -> Result<Self, Rc4Error> {
+        let key = key.as_ref();
+        let rc4 = Self::try_new(key)?;
+        match key_quality(key) {
+            KeyQuality::Acceptable => Ok(rc4),
+            quality => Err(Rc4Error::WeakKey(quality)),
+        }
+    }
+
+    /// Создает шифр с ключом из переменной окружения `var_name`.
+    ///
+    /// Значение, являющееся корректной hex-строкой (четное число hex-цифр,
+    /// пробельные символы по краям игнорируются), декодируется; иначе ключом
+    /// служат байты строки как есть. Значит, текстовый ключ вроде `cafe`
+    /// будет прочитан как два байта — такие ключи лучше задавать в hex.
+    ///
+    /// Переменные окружения видны другим процессам того же пользователя
+    /// (`/proc/<pid>/environ`), наследуются дочерними процессами и нередко
+    /// попадают в логи и дампы; для долгоживущих секретов файл с ограниченными
+    /// правами или менеджер секретов надежнее.
+    #[cfg(feature = "env")]
+    pub fn new_from_environment(var_name: &str) -> Result<Self, Rc4Error> {
+        use std::env::{self, VarError};
+
+        let value = env::var(var_name).map_err(|e| match e {
+            VarError::NotPresent => Rc4Error::EnvVarMissing(var_name.to_string()),
+            VarError::NotUnicode(_) => Rc4Error::EnvVarInvalid(var_name.to_string()),
+        })?;
+        match hex::decode(value.trim()) {
+            Ok(key) => Self::try_new(key),
+            Err(_) => Self::try_new(value),
+        }
+    }
+
+    /// Создает по шифру на каждый ключ. Ошибки возвращаются для каждого ключа
+    /// отдельно, поэтому один недопустимый ключ не мешает остальным.
+    pub fn new_multi(keys: &[&[u8]]) -> Vec<Result<Self, Rc4Error>> {
+        keys.iter().map(Self::try_new).collect()
+    }
+
+    /// Создает шифр со случайным 16-байтовым ключом из переданного ГПСЧ.
+    /// Ключ возвращается вместе с шифром, чтобы его можно было сохранить
+    /// и позже восстановить идентичный шифр через `try_new`.
+    #[cfg(feature = "rand_core")]
+    pub fn new_random(rng: &mut impl rand_core::RngCore) -> (Self, [u8; 16]) {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        (Self::new(key), key)
+    }
+
+    /// Детерминированно перемешивает `slice` (Фишер — Йетс) по гамме шифра:
+    /// один и тот же ключ дает одну и ту же перестановку. Удобно для
+    /// воспроизводимых тестовых данных.
+    ///
+    /// Перестановка не является криптографически равномерной: индекс
+    /// берется как 64-битное число по модулю длины оставшейся части, что
+    /// дает смещение порядка `len / 2^64`, а сама гамма RC4 имеет известные
+    /// смещения. Для секретных перестановок это не годится.
+    #[cfg(feature = "rand_core")]
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let mut word = [0u8; 8];
+            self.fill_keystream(&mut word);
+            let j = (u64::from_le_bytes(word) % (i as u64 + 1)) as usize;
+            slice.swap(i, j);
+        }
+    }
+
+    /// Основной метод шифрования/дешифрования (PRGA).
+    /// Работает "на месте" (in-place) с переданным буфером, избегая аллокаций.
+    /// Это наиболее производительный способ использования.
+    ///
+    /// Принимает все, что дает `&mut [u8]` (`Vec<u8>`, массив, `Box<[u8]>`,
+    /// `BytesMut`), по изменяемой ссылке, чтобы временный буфер нельзя было
+    /// по ошибке зашифровать и выбросить. Вся работа — в негенерической
+    /// `process_slice`, так что обобщение не размножает код PRGA.
+    #[inline]
+    pub fn process<T: AsMut<[u8]> + ?Sized>(&mut self, data: &mut T) {
+        self.process_slice(data.as_mut());
+    }
+
+    // Побайтный XOR быстрее пословного через буфер гаммы на любых размерах:
+    // время уходит на цепочку зависимостей PRGA, а не на XOR
+    fn process_slice(&mut self, data: &mut [u8]) {
+        self.prga(data, |byte, k| *byte ^= k);
+    }
+
+    /// Общее ядро PRGA: для каждого элемента `slots` вычисляет очередной байт
+    /// гаммы `k` и вызывает `emit(slot, k)`. Индексы и S-box загружаются в
+    /// локальные переменные один раз на вызов, а не на каждый байт; ядро
+    /// встраивается в вызывающий метод, поэтому `emit` не стоит вызова.
+    ///
+    /// С feature `unsafe-opt` используется `prga_unchecked`; гамма от этого
+    /// не меняется.
+    #[inline(always)]
+    fn prga<T>(&mut self, slots: &mut [T], emit: impl FnMut(&mut T, u8)) {
+        #[cfg(not(feature = "unsafe-opt"))]
+        self.prga_checked(slots, emit);
+        #[cfg(feature = "unsafe-opt")]
+        self.prga_unchecked(slots, emit);
+    }
+
+    /// Побайтный XOR с гаммой выбранным вариантом ядра PRGA — для сравнения
+    /// вариантов в тестах и бенчмарках.
+    #[cfg(feature = "unsafe-opt")]
+    pub(crate) fn process_bytes_with(&mut self, data: &mut [u8], variant: PrgaVariant) {
+        match variant {
+            PrgaVariant::Checked => self.prga_checked(data, |byte, k| *byte ^= k),
+            PrgaVariant::Unchecked => self.prga_unchecked(data, |byte, k| *byte ^= k),
+        }
+    }
+
+    /// Безопасное ядро PRGA; без feature `unsafe-opt` — единственное.
+    #[inline(always)]
+    fn prga_checked<T>(&mut self, slots: &mut [T], mut emit: impl FnMut(&mut T, u8)) {
+        let mut i = self.i;
+        let mut j = self.j;
+        let s = &mut self.s;
+
+        for slot in slots.iter_mut() {
+            // 1. i = (i + 1) % 256
+            i = i.wrapping_add(1);
+
+            // 2. j = (j + S[i]) % 256
+            let si = s[i as usize];
+            j = j.wrapping_add(si);
+
+            // 3. swap(S[i], S[j])
+            let sj = s[j as usize];
+            s.swap(i as usize, j as usize);
+
+            // 4. Байт гаммы K = S[(S[i] + S[j]) % 256]
+            emit(slot, s[si.wrapping_add(sj) as usize]);
+        }
+
+        // Сохраняем состояние обратно
+        self.i = i;
+        self.j = j;
+        self.bytes_processed = self.bytes_processed.wrapping_add(slots.len() as u64);
+    }
+
+    /// Ядро PRGA без `swap` и проверок границ: S[i] и S[j] читаются один раз
+    /// и записываются обратно напрямую через `get_unchecked`.
+    ///
+    /// Почему это корректно: `s` — массив `[u8; 256]`, его длина задана
+    /// типом и не может измениться, а каждый индекс (`i`, `j` и
+    /// `si.wrapping_add(sj)`) имеет тип `u8`, то есть лежит в `0..=255`.
+    /// Арифметика индексов — `wrapping_add` над `u8`, поэтому приведение
+    /// к `usize` не может дать 256 и больше. Ссылки на элементы не
+    /// удерживаются между обращениями: значения копируются, так что при
+    /// `i == j` запись `sj` и затем `si` дает тот же результат, что `swap`.
+    /// Совпадение с безопасным ядром на случайных ключах и данных
+    /// проверяет `test_prga_variants_fuzz`.
+    ///
+    /// Компилируется только с feature `unsafe-opt`.
+    #[cfg(feature = "unsafe-opt")]
+    #[inline(always)]
+    fn prga_unchecked<T>(&mut self, slots: &mut [T], mut emit: impl FnMut(&mut T, u8)) {
+        let mut i = self.i;
+        let mut j = self.j;
+        let s = &mut self.s;
+
+        for slot in slots.iter_mut() {
+            i = i.wrapping_add(1);
+            // SAFETY: i, j и si + sj имеют тип u8 (< 256), а s: [u8; 256];
+            // значения копируются, живых ссылок в S-box нет
+            let k = unsafe {
+                let si = *s.get_unchecked(i as usize);
+                j = j.wrapping_add(si);
+                let sj = *s.get_unchecked(j as usize);
+                *s.get_unchecked_mut(i as usize) = sj;
+                *s.get_unchecked_mut(j as usize) = si;
+                *s.get_unchecked(si.wrapping_add(sj) as usize)
+            };
+            emit(slot, k);
+        }
+
+        self.i = i;
+        self.j = j;
+        self.bytes_processed = self.bytes_processed.wrapping_add(slots.len() as u64);
+    }
+
+    /// Обертка для удобства, если нужен новый Vec (как в предыдущей версии).
+    /// Принимает любые байты: `&[u8]`, `&Vec<u8>`, массив, `String`, `&str`.
+    pub fn apply(&mut self, data: impl AsRef<[u8]>) -> Vec<u8> {
+        self.apply_slice(data.as_ref())
+    }
+
+    /// Шифрует `plaintext` и возвращает шифротекст строчными hex-цифрами.
+    pub fn apply_hex(&mut self, plaintext: &[u8]) -> String {
+        hex::encode(&self.apply(plaintext))
+    }
+
+    /// Разбирает hex-шифротекст (цифры любого регистра, пробелы и переводы
+    /// строк между цифрами допускаются) и расшифровывает его. При ошибке
+    /// разбора возвращает `InvalidHex` с байтовым смещением недопустимого
+    /// символа; состояние шифра при этом не меняется.
+    pub fn apply_from_hex(&mut self, ciphertext_hex: &str) -> Result<Vec<u8>, Rc4Error> {
+        let mut data =
+            hex::decode_ignoring_whitespace(ciphertext_hex).map_err(Rc4Error::InvalidHex)?;
+        self.process(&mut data);
+        Ok(data)
+    }
+
+    fn apply_slice(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut output = data.to_vec(); // Аллокация здесь
+        self.process_slice(&mut output);
+        output
+    }
+
+    /// Шифрует `data` и возвращает `(шифротекст, гамма)` одинаковой длины:
+    /// `гамма ^ шифротекст == data`. Для исследования атак с известным
+    /// открытым текстом; раскрытая гамма позволяет расшифровать все, что
+    /// зашифровано на этой позиции потока.
+    pub fn apply_with_keystream(&mut self, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let keystream = self.keystream_to_vec(data.len());
+        let ciphertext = data.iter().zip(&keystream).map(|(d, k)| d ^ k).collect();
+        (ciphertext, keystream)
+    }
+
+    /// Шифрует `data`, за тот же проход добавляя открытый текст в аккумулятор
+    /// `mac_state` (см. `rolling_mac_update`, начальное значение —
+    /// `ROLLING_MAC_INIT`). Аккумулятор передается между вызовами, поэтому
+    /// поток можно шифровать частями.
+    pub fn encrypt_with_rolling_mac(&mut self, data: &[u8], mac_state: &mut u64) -> Vec<u8> {
+        let mut state = *mac_state;
+        let output = data
+            .iter()
+            .map(|&byte| {
+                rolling_mac_update(&mut state, &[byte]);
+                byte ^ self.next_byte()
+            })
+            .collect();
+        *mac_state = state;
+        output
+    }
+
+    /// Шифрует на месте только `data[range]`, продвигая состояние на длину
+    /// диапазона; остальной буфер (например, открытые заголовки) не меняется.
+    /// При выходе диапазона за границы буфер и состояние не изменяются.
+    pub fn process_range(&mut self, data: &mut [u8], range: Range<usize>) -> Result<(), Rc4Error> {
+        let len = data.len();
+        match data.get_mut(range.clone()) {
+            Some(slice) => {
+                self.process(slice);
+                Ok(())
+            }
+            None => Err(Rc4Error::RangeOutOfBounds {
+                start: range.start,
+                end: range.end,
+                len,
+            }),
+        }
+    }
+
+    /// Шифрует новую часть окна в протоколах со скользящими окнами, где
+    /// первые `window_size` байт `data` повторяют конец предыдущего окна и
+    /// уже зашифрованы. Шифруется только `data[window_size..]` — гаммой,
+    /// продолжающей поток с места, где закончилось предыдущее окно;
+    /// состояние продвигается на `data.len() - window_size` байт. Первое окно
+    /// передается с `window_size = 0`. Если `window_size > data.len()`,
+    /// возвращается `RangeOutOfBounds`, а буфер и состояние не меняются.
+    pub fn process_rolling_window(
+        &mut self,
+        data: &mut [u8],
+        window_size: usize,
+    ) -> Result<(), Rc4Error> {
+        self.process_range(data, window_size..data.len())
+    }
+
+    /// Шифрует на месте только байты, для абсолютной позиции которых в гамме
+    /// (см. `byte_position`) `predicate` возвращает `true`; остальные байты
+    /// остаются открытыми. Гамма продвигается на каждый байт `data`
+    /// независимо от `predicate`, поэтому позиции у сторон совпадают, а
+    /// расшифровка — тот же вызов с тем же `predicate`. Позиции — `u64`,
+    /// как у `byte_position`, поэтому не обрезаются на 32-битных целях.
+    pub fn process_indexed(&mut self, data: &mut [u8], mut predicate: impl FnMut(u64) -> bool) {
+        let mut position = self.bytes_processed;
+        self.prga(data, |byte, k| {
+            if predicate(position) {
+                *byte ^= k;
+            }
+            position = position.wrapping_add(1);
+        });
+    }
+
+    /// Меняет ключ на месте: KSA выполняется заново в том же S-box, счетчики
+    /// и `bytes_processed` обнуляются. После `rekey(k)` шифр ведет себя как
+    /// `Rc4::new(k)`. При недопустимой длине ключа состояние не меняется.
+    ///
+    /// Без `rekeying` память не выделяется. С `rekeying` копия ключа
+    /// переписывается в тот же буфер, а если новый ключ длиннее его емкости,
+    /// буфер выделяется заново; с `zeroize` старый ключ затирается (вместе с
+    /// хвостом буфера) до того, как буфер переиспользуется или освобождается.
+    pub fn rekey(&mut self, key: &[u8]) -> Result<(), Rc4Error> {
+        validate_key_len(key.len())?;
+        Self::schedule(&mut self.s, key.len(), |n| key[n]);
+        self.i = 0;
+        self.j = 0;
+        self.bytes_processed = 0;
+        #[cfg(feature = "rekeying")]
+        {
+            #[cfg(feature = "zeroize")]
+            zeroize::Zeroize::zeroize(&mut self.key);
+            #[cfg(not(feature = "zeroize"))]
+            self.key.clear();
+            self.key.extend_from_slice(key);
+        }
+        Ok(())
+    }
+
+    /// Начинает новый сегмент потока: заново выполняет KSA с ключом
+    /// `key[i] ^ nonce[i % nonce.len()]`, где `key` — ключ, переданный
+    /// конструктору (без whitening, nonce и счетчика).
+    /// Один и тот же nonce дает одну и ту же гамму, поэтому nonce нельзя
+    /// повторять; ключи, отличающиеся только nonce, связаны, и для защиты от
+    /// атак на связанные ключи стоит отбрасывать начало гаммы (`skip`).
+    /// Nonce длиннее ключа отвергается: байты за длиной ключа не повлияли бы
+    /// на результат, и разные nonce давали бы одинаковую гамму.
+    ///
+    /// Требует feature `rekeying`: шифр хранит копию ключа, что увеличивает
+    /// его размер и время жизни ключа в памяти.
+    #[cfg(feature = "rekeying")]
+    pub fn rekey_with_nonce(&mut self, nonce: &[u8]) -> Result<(), Rc4Error> {
+        if nonce.is_empty() {
+            return Err(Rc4Error::EmptyNonce);
+        }
+        validate_key_len(self.key.len())?;
+        if nonce.len() > self.key.len() {
+            return Err(Rc4Error::NonceTooLong {
+                len: nonce.len(),
+                max: self.key.len(),
+            });
+        }
+
+        let key = &self.key;
+        Self::schedule(&mut self.s, key.len(), |n| key[n] ^ nonce[n % nonce.len()]);
+        self.i = 0;
+        self.j = 0;
+        self.bytes_processed = 0;
+        Ok(())
+    }
+
+    /// Шифрует текст построчно: каждая строка — на отдельном шифре с ключом
+    /// `номер_строки || key` (номер с нуля, 8 байт big-endian, как IV в
+    /// `new_with_nonce`), и записывается в `writer` в hex с `\n`. Разделитель
+    /// строк не шифруется, поэтому любую строку можно расшифровать отдельно
+    /// через `decrypt_line`. Возвращает число строк.
+    ///
+    /// Собственный номер у каждой строки обязателен: строки на одном и том же
+    /// ключе шифровались бы одной гаммой, и XOR двух шифротекстов раскрывал
+    /// бы XOR открытых текстов. Ключи строк при этом связаны (как в WEP), а
+    /// повторное шифрование другого текста тем же ключом повторяет гаммы.
+    /// Требует feature `rekeying` (ключ берется из шифра); ключ не длиннее
+    /// 248 байт.
+    #[cfg(feature = "rekeying")]
+    pub fn encrypt_lines(
+        &self,
+        mut reader: impl io::BufRead,
+        mut writer: impl Write,
+    ) -> io::Result<u64> {
+        let mut line = Vec::new();
+        let mut count = 0u64;
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            self.line_cipher(count)?.process(&mut line);
+            writeln!(writer, "{}", hex::encode(&line))?;
+            line.clear();
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Обращает `encrypt_lines`: расшифровывает каждую hex-строку `reader`
+    /// и пишет открытый текст с `\n`. Возвращает число строк.
+    #[cfg(feature = "rekeying")]
+    pub fn decrypt_lines(
+        &self,
+        reader: impl io::BufRead,
+        mut writer: impl Write,
+    ) -> io::Result<u64> {
+        let mut count = 0u64;
+        for line in reader.lines() {
+            writer.write_all(&self.decrypt_line(count, &line?)?)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Расшифровывает одну строку вывода `encrypt_lines` по ее номеру (с нуля).
+    /// Строка не в hex — ошибка `InvalidData`.
+    #[cfg(feature = "rekeying")]
+    pub fn decrypt_line(&self, line_number: u64, hex_line: &str) -> io::Result<Vec<u8>> {
+        let mut line = hex::decode(hex_line.trim_end_matches('\r')).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: invalid hex ciphertext: {}", line_number, e),
+            )
+        })?;
+        self.line_cipher(line_number)?.process(&mut line);
+        Ok(line)
+    }
+
+    #[cfg(feature = "rekeying")]
+    fn line_cipher(&self, line_number: u64) -> io::Result<Rc4> {
+        Self::new_with_nonce(&self.key, &line_number.to_be_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Пропускает `start_byte_offset` байт гаммы от текущей позиции и
+    /// обрабатывает `data`: для сообщений, начинающихся в середине гаммы
+    /// (например, после отдельно зашифрованного заголовка переменной длины).
+    /// Состояние продвигается на `start_byte_offset + data.len()`.
+    pub fn process_non_aligned(&mut self, data: &mut [u8], start_byte_offset: usize) {
+        self.skip(start_byte_offset);
+        self.process(data);
+    }
+
+    /// Обрабатывает буферы scatter-gather по порядку как один непрерывный
+    /// поток и возвращает общее число обработанных байт. Результат тот же,
+    /// что у `process` над конкатенацией буферов.
+    pub fn process_vectored(&mut self, bufs: &mut [&mut [u8]]) -> usize {
+        self.process_chunks(bufs.iter_mut().map(|buf| &mut **buf))
+    }
+
+    /// Обрабатывает сегменты из итератора по порядку как один поток, без
+    /// склейки в общий буфер, и возвращает общее число обработанных байт.
+    pub fn process_chunks<'a>(&mut self, chunks: impl IntoIterator<Item = &'a mut [u8]>) -> usize {
+        chunks.into_iter().fold(0, |total, chunk| {
+            self.process_slice(chunk);
+            total + chunk.len()
+        })
+    }
+
+    /// Шифрует `data` и пишет результат в `out` блоками по 8 КБ через буфер
+    /// на стеке, не выделяя память под весь шифротекст. Возвращает число
+    /// записанных байт (всегда `data.len()` при успехе).
+    /// При ошибке записи состояние шифра уже продвинуто на неудавшийся блок,
+    /// поэтому после ошибки поток следует считать рассинхронизированным.
+    pub fn apply_to_writer<W: Write>(&mut self, data: &[u8], out: &mut W) -> io::Result<usize> {
+        let mut scratch = [0u8; 8192];
+        for chunk in data.chunks(scratch.len()) {
+            let buf = &mut scratch[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            self.process(buf);
+            out.write_all(buf)?;
+        }
+        Ok(data.len())
+    }
+
+    /// Сравнивает два буфера (например, шифротексты или MAC) за время,
+    /// зависящее только от их длин, а не от позиции первого различия.
+    /// Буферы разной длины не равны, но сравнение все равно проходит
+    /// по всей длине большего из них.
+    pub fn constant_time_equals(a: &[u8], b: &[u8]) -> bool {
+        let mut diff = (a.len() != b.len()) as u8;
+        for n in 0..a.len().max(b.len()) {
+            let x = a.get(n).copied().unwrap_or(0);
+            let y = b.get(n).copied().unwrap_or(0);
+            diff |= x ^ y;
+        }
+        // black_box не дает компилятору превратить цикл в ранний выход
+        std::hint::black_box(diff) == 0
+    }
+
+    /// Совпадают ли S-box и счетчики: из одинакового состояния оба шифра
+    /// выдадут одинаковую гамму. Сохраненный ключ (`rekeying`) и
+    /// `bytes_processed` не сравниваются.
+    /// S-box эквивалентен ключу, поэтому сравнение выполняется за постоянное
+    /// время.
+    pub fn same_state(&self, other: &Rc4) -> bool {
+        let counters = (self.i, self.j) == (other.i, other.j);
+        Self::constant_time_equals(&self.s, &other.s) & counters
+    }
+
+    /// Сколько байт гаммы выдано или пропущено (`process`, `skip` и другие
+    /// методы, продвигающие состояние) с момента KSA или `rekey_with_nonce`.
+    /// Шифр из `from_state` считает с нуля: снимок не хранит счетчик.
+    pub fn bytes_processed(&self) -> u64 {
+        self.bytes_processed
+    }
+
+    /// Текущая позиция в гамме — номер следующего байта гаммы, считая от
+    /// KSA; то же значение, что `bytes_processed()`. Удобна для журналов
+    /// и проверки синхронности отправителя и получателя. После 2^64 байт
+    /// счетчик переходит через ноль: период гаммы RC4 несравнимо больше.
+    pub fn byte_position(&self) -> u64 {
+        self.bytes_processed
+    }
+
+    /// Оценка оставшегося объема для мониторинга сеанса: `u64::MAX -
+    /// bytes_processed()`. Период гаммы RC4 несравнимо больше, так что
+    /// ограничение чисто учетное — это емкость самого счетчика.
+    pub fn capacity(&self) -> u64 {
+        u64::MAX - self.bytes_processed
+    }
+
+    /// Копия текущего S-box для инструментов отладки и визуализации.
+    ///
+    /// S-box эквивалентен ключу: по нему и счетчикам восстанавливается вся
+    /// дальнейшая гамма, а сразу после KSA — и сам ключ. Копию следует
+    /// затирать после использования и не передавать за пределы доверенного
+    /// процесса. Для контрольных точек используйте `export_state`.
+    pub fn sbox_snapshot(&self) -> [u8; 256] {
+        self.s
+    }
+
+    /// Сохраняет текущее состояние шифра; см. `from_state`.
+    pub fn export_state(&self) -> Rc4State {
+        Rc4State {
+            s: self.s,
+            i: self.i,
+            j: self.j,
+        }
+    }
+
+    /// Восстанавливает шифр из снимка: дальнейшая гамма продолжается
+    /// ровно с той позиции, на которой был сделан снимок. Снимок не
+    /// содержит ключа, поэтому `rekey_with_nonce` для такого шифра
+    /// возвращает `EmptyKey`.
+    pub fn from_state(state: Rc4State) -> Self {
+        Rc4 {
+            s: state.s,
+            i: state.i,
+            j: state.j,
+            bytes_processed: 0,
+            #[cfg(feature = "rekeying")]
+            key: Vec::new(),
+        }
+    }
+
+    /// Разбирает шифр на S-box и счетчики `(s, i, j)`.
+    ///
+    /// Возвращаемый массив содержит чувствительные данные (S-box эквивалентен
+    /// ключу). Вызывающая сторона отвечает за его затирание после
+    /// использования, например через `zeroize::Zeroize`.
+    pub fn into_raw_parts(self) -> ([u8; 256], u8, u8) {
+        (self.s, self.i, self.j)
+    }
+
+    /// Пропускает `n` байт гаммы, продвигая состояние так же, как `process`
+    /// на буфере длины `n`, но без обращения к данным.
+    pub fn skip(&mut self, n: usize) {
+        let mut i = self.i;
+        let mut j = self.j;
+        let s = &mut self.s;
+
+        for _ in 0..n {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(s[i as usize]);
+            s.swap(i as usize, j as usize);
+        }
+
+        self.i = i;
+        self.j = j;
+        self.bytes_processed = self.bytes_processed.wrapping_add(n as u64);
+    }
+
+    /// Продвигает шифр на `n` байт: при `Some(data)` обрабатывает `data[..n]`
+    /// как `process`, при `None` пропускает гамму как `skip`. Удобно для
+    /// парсеров, которые то разбирают поле, то пропускают его.
+    ///
+    /// Если `data` короче `n`, возвращается ошибка, а состояние не меняется.
+    pub fn process_or_skip(&mut self, data: Option<&mut [u8]>, n: usize) -> Result<(), Rc4Error> {
+        match data {
+            Some(data) => self.process_range(data, 0..n),
+            None => {
+                self.skip(n);
+                Ok(())
+            }
+        }
+    }
+
+    /// Возвращает следующий байт гаммы, продвигая состояние на один шаг.
+    #[inline]
+    pub fn next_byte(&mut self) -> u8 {
+        self.step().byte
+    }
+
+    /// Один шаг PRGA с подробностями для учебных визуализаций: состояние
+    /// меняется так же, как в `next_byte`. Обмененные элементы — `S[i]`
+    /// и `S[j]` из результата.
+    #[inline]
+    pub fn step(&mut self) -> Rc4Step {
+        self.bytes_processed = self.bytes_processed.wrapping_add(1);
+        self.i = self.i.wrapping_add(1);
+        let si = self.s[self.i as usize];
+        self.j = self.j.wrapping_add(si);
+        let sj = self.s[self.j as usize];
+        self.s.swap(self.i as usize, self.j as usize);
+        let output_index = si.wrapping_add(sj);
+        Rc4Step {
+            i: self.i,
+            j: self.j,
+            output_index,
+            byte: self.s[output_index as usize],
+        }
+    }
+
+    /// Заполняет `out` гаммой (перезаписывая содержимое), продвигая состояние.
+    pub fn fill_keystream(&mut self, out: &mut [u8]) {
+        // XOR с нулями дает саму гамму
+        out.fill(0);
+        self.process(out);
+    }
+
+    /// Как `fill_keystream`, но для неинициализированного буфера: избавляет
+    /// от обнуления перед генерацией. Возвращает тот же буфер как `&mut [u8]`.
+    pub fn fill_keystream_uninit<'a>(&mut self, out: &'a mut [MaybeUninit<u8>]) -> &'a mut [u8] {
+        self.prga(out, |slot, k| {
+            slot.write(k);
+        });
+
+        // SAFETY: цикл выше записал значение в каждый элемент `out`, а
+        // MaybeUninit<u8> имеет то же представление, что и u8.
+        unsafe { &mut *(out as *mut [MaybeUninit<u8>] as *mut [u8]) }
+    }
+
+    /// Генерирует ровно `N` байт гаммы в массив на стеке, без выделения
+    /// памяти. Эквивалентно `fill_keystream` над буфером длины `N`.
+    pub fn next_array<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0u8; N];
+        self.process(&mut out);
+        out
+    }
+
+    /// Генерирует `len` байт гаммы в новый буфер, продвигая состояние шифра.
+    /// Полезно, когда одну и ту же позицию потока нужно применить ко многим
+    /// буферам: гамма вычисляется один раз, а затем накладывается через `xor_in_place`.
+    pub fn keystream_to_vec(&mut self, len: usize) -> Vec<u8> {
+        // XOR с нулями дает саму гамму
+        let mut keystream = vec![0u8; len];
+        self.process(&mut keystream);
+        keystream
+    }
+}
+
+/// Шифрует `plaintext` новым шифром с ключом `key` и возвращает hex-строку.
+/// Паникует при недопустимой длине ключа, как `Rc4::new`.
+pub fn rc4_hex(key: impl AsRef<[u8]>, plaintext: &[u8]) -> String {
+    Rc4::new(key).apply_hex(plaintext)
+}
+
+/// Расшифровывает hex-шифротекст новым шифром с ключом `key`.
+pub fn rc4_unhex(key: impl AsRef<[u8]>, ciphertext_hex: &str) -> Result<Vec<u8>, Rc4Error> {
+    Rc4::try_new(key)?.apply_from_hex(ciphertext_hex)
+}
+
+/// Гамма RC4 как источник случайных чисел для API `rand`: детерминированный
+/// поток для тестов и симуляций, а не криптографический ГПСЧ.
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for Rc4 {
+    fn next_u32(&mut self) -> u32 {
+        let mut word = [0u8; 4];
+        self.fill_keystream(&mut word);
+        u32::from_le_bytes(word)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut word = [0u8; 8];
+        self.fill_keystream(&mut word);
+        u64::from_le_bytes(word)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.fill_keystream(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_keystream(dest);
+        Ok(())
+    }
+}
+
+/// Накладывает заранее вычисленную гамму на данные (`data ^= keystream`).
+/// Основная часть обрабатывается словами по 8 байт, хвост — побайтно; с
+/// feature `simd` — сначала векторами SSE2/AVX2 или NEON (см. `simd`).
+/// Длины буферов должны совпадать.
+pub fn xor_in_place(data: &mut [u8], keystream: &[u8]) -> Result<(), Rc4Error> {
+    if data.len() != keystream.len() {
+        return Err(Rc4Error::LengthMismatch {
+            data: data.len(),
+            keystream: keystream.len(),
+        });
+    }
+
+    // Короткие буферы векторам не выгодны: проверка AVX2 и вызов дороже
+    #[cfg(feature = "simd")]
+    if data.len() >= simd::MIN_LEN {
+        let done = simd::xor_prefix(data, keystream);
+        xor_words(&mut data[done..], &keystream[done..]);
+        return Ok(());
+    }
+
+    xor_words(data, keystream);
+    Ok(())
+}
+
+fn xor_words(data: &mut [u8], keystream: &[u8]) {
+    // from_ne_bytes/to_ne_bytes не требуют выравнивания, поэтому
+    // произвольные (невыровненные) срезы обрабатываются корректно.
+    let mut data_words = data.chunks_exact_mut(8);
+    let mut key_words = keystream.chunks_exact(8);
+    for (d, k) in (&mut data_words).zip(&mut key_words) {
+        let x = u64::from_ne_bytes((*d).try_into().unwrap())
+            ^ u64::from_ne_bytes(k.try_into().unwrap());
+        d.copy_from_slice(&x.to_ne_bytes());
+    }
+
+    for (d, k) in data_words
+        .into_remainder()
+        .iter_mut()
+        .zip(key_words.remainder())
+    {
+        *d ^= k;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test Vector 1
+    #[test]
+    fn test_vector_1() {
+        let key = b"Key"; 
+        let plaintext = b"Plaintext";
+        let expected_ciphertext: [u8; 9] = [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3];
+
+        let mut rc4 = Rc4::new(key);
+        let result = rc4.apply(plaintext);
+        assert_eq!(result, expected_ciphertext);
+    }
+
+    /// Test Vector 2
+    #[test]
+    fn test_vector_2() {
+        let key = b"Wiki"; 
+        let plaintext = b"pedia";
+        let expected_ciphertext: [u8; 5] = [0x10, 0x21, 0xBF, 0x04, 0x20];
+
+        let mut rc4 = Rc4::new(key);
+        let result = rc4.apply(plaintext);
+        assert_eq!(result, expected_ciphertext);
+    }
+
+    /// Проверка симметричности
+    #[test]
+    fn test_symmetry() {
+        let key = b"SecretKey";
+        let plaintext = b"Hello, World!";
+        
+        let mut rc4_enc = Rc4::new(key);
+        let ciphertext = rc4_enc.apply(plaintext);
+        
+        let mut rc4_dec = Rc4::new(key);
+        // Дешифруем in-place для разнообразия
+        let mut decrypted = ciphertext.clone();
+        rc4_dec.process(&mut decrypted);
+
+        assert_eq!(plaintext.to_vec(), decrypted);
+    }
+
+    /// skip(n) эквивалентен process над n байтами
+    #[test]
+    fn test_skip_matches_process() {
+        let mut skipped = Rc4::new(b"Key");
+        skipped.skip(300);
+
+        let mut processed = Rc4::new(b"Key");
+        processed.process(&mut [0u8; 300]);
+
+        assert_eq!(skipped.keystream_to_vec(64), processed.keystream_to_vec(64));
+    }
+
+    /// process/apply принимают массивы, Vec, Box<[u8]> и String так же, как
+    /// срезы; process_chunks равен process над конкатенацией сегментов
+    #[test]
+    fn test_generic_process_inputs() {
+        let plain = Rc4::new(b"data").keystream_to_vec(300);
+        let expected = Rc4::new(b"Key").apply(&plain[..]);
+
+        let mut array: [u8; 300] = plain.clone().try_into().unwrap();
+        Rc4::new(b"Key").process(&mut array);
+        assert!(array[..] == expected[..]);
+
+        let mut vec = plain.clone();
+        Rc4::new(b"Key").process(&mut vec);
+        assert!(vec == expected);
+
+        let mut boxed = plain.clone().into_boxed_slice();
+        Rc4::new(b"Key").process(&mut boxed);
+        assert!(boxed[..] == expected[..]);
+
+        assert!(Rc4::new(b"Key").apply(&plain) == expected);
+        assert!(Rc4::new(b"Key").apply(array) == Rc4::new(b"Key").apply(&array[..]));
+        assert_eq!(
+            Rc4::new(b"Key").apply(String::from("text")),
+            Rc4::new(b"Key").apply(&b"text"[..])
+        );
+
+        let mut segmented = plain.clone();
+        let mut cipher = Rc4::new(b"Key");
+        let (a, rest) = segmented.split_at_mut(7);
+        let (b, c) = rest.split_at_mut(0);
+        assert_eq!(cipher.process_chunks([a, b, c]), 300);
+        assert!(segmented == expected);
+        assert_eq!(cipher.process_chunks(std::iter::empty::<&mut [u8]>()), 0);
+        assert_eq!(cipher.bytes_processed(), 300);
+    }
+
+    /// rekey посреди потока дает ту же гамму, что и новый шифр с этим ключом
+    #[test]
+    fn test_rekey_matches_new() {
+        let mut cipher = Rc4::new(b"first key");
+        cipher.skip(12345);
+        for key in [&b"second"[..], b"k", &[0x5a; 256]] {
+            cipher.rekey(key).unwrap();
+            assert_eq!(cipher.bytes_processed(), 0);
+            assert!(cipher.same_state(&Rc4::new(key)));
+            assert_eq!(
+                cipher.keystream_to_vec(64),
+                Rc4::new(key).keystream_to_vec(64)
+            );
+        }
+
+        let before = cipher.export_state();
+        assert_eq!(cipher.rekey(b""), Err(Rc4Error::EmptyKey));
+        assert_eq!(cipher.rekey(&[1; 257]), Err(Rc4Error::KeyTooLong(257)));
+        assert!(cipher.export_state() == before);
+    }
+
+    /// rekey на более короткий ключ затирает старый ключ, включая хвост
+    /// буфера за длиной нового
+    #[cfg(all(feature = "rekeying", feature = "zeroize"))]
+    #[test]
+    fn test_rekey_wipes_old_key() {
+        let mut cipher = Rc4::new(b"a long original key");
+        let capacity = cipher.key.capacity();
+        cipher.rekey(b"short").unwrap();
+        assert_eq!(cipher.key, b"short");
+        assert_eq!(cipher.key.capacity(), capacity);
+        // SAFETY: все байты до емкости записаны (ключом, затем нулями)
+        let tail = unsafe { std::slice::from_raw_parts(cipher.key.as_ptr().add(5), capacity - 5) };
+        assert!(tail.iter().all(|&b| b == 0));
+    }
+
+    /// Повторный rekey с тем же nonce дает ту же гамму, с другим — другую
+    #[cfg(feature = "rekeying")]
+    #[test]
+    fn test_rekey_with_nonce() {
+        let key = b"long-running session key";
+        let mut a = Rc4::new(key);
+        let mut b = Rc4::new(key);
+        a.skip(1000);
+
+        a.rekey_with_nonce(b"segment-1").unwrap();
+        b.rekey_with_nonce(b"segment-1").unwrap();
+        let stream = a.keystream_to_vec(64);
+        assert_eq!(stream, b.keystream_to_vec(64));
+
+        // Ключ смешивается с исходным, а не с предыдущим смешанным
+        a.rekey_with_nonce(b"segment-1").unwrap();
+        assert_eq!(a.keystream_to_vec(64), stream);
+
+        b.rekey_with_nonce(b"segment-2").unwrap();
+        assert_ne!(b.keystream_to_vec(64), stream);
+        assert_ne!(Rc4::new(key).keystream_to_vec(64), stream);
+
+        assert_eq!(a.rekey_with_nonce(b""), Err(Rc4Error::EmptyNonce));
+        assert_eq!(
+            Rc4::new(b"Key").rekey_with_nonce(b"segment-1"),
+            Err(Rc4Error::NonceTooLong { len: 9, max: 3 })
+        );
+        let mut restored = Rc4::from_state(a.export_state());
+        assert_eq!(restored.rekey_with_nonce(b"n"), Err(Rc4Error::EmptyKey));
+    }
+
+    /// Сохраняется ключ вызывающего, а не отбеленный или склеенный с nonce
+    /// или счетчиком
+    #[cfg(feature = "rekeying")]
+    #[test]
+    fn test_rekeying_stores_caller_key() {
+        let whitened = Rc4::builder(b"BaseKey")
+            .with_key_whitening(b"pad-a")
+            .build()
+            .unwrap();
+        assert_eq!(whitened.key, b"BaseKey");
+        assert_eq!(
+            Rc4::new_with_nonce(b"secret", b"iv").unwrap().key,
+            b"secret"
+        );
+        assert_eq!(
+            Rc4::new_with_counter(b"base key", 7).unwrap().key,
+            b"base key"
+        );
+
+        let mut whitened = whitened;
+        let mut plain = Rc4::new(b"BaseKey");
+        whitened.rekey_with_nonce(b"n").unwrap();
+        plain.rekey_with_nonce(b"n").unwrap();
+        assert!(whitened.same_state(&plain));
+    }
+
+    /// Каждая строка журнала расшифровывается отдельно, одинаковые строки
+    /// дают разные шифротексты
+    #[cfg(feature = "rekeying")]
+    #[test]
+    fn test_encrypt_lines() {
+        let log = "GET /index.html 200\nGET /index.html 200\n\nPOST /login 403";
+        let cipher = Rc4::new(b"log key");
+        let mut encrypted = Vec::new();
+        assert_eq!(
+            cipher
+                .encrypt_lines(log.as_bytes(), &mut encrypted)
+                .unwrap(),
+            4
+        );
+
+        let encrypted = String::from_utf8(encrypted).unwrap();
+        let lines: Vec<&str> = encrypted.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_ne!(lines[0], lines[1]);
+        assert_eq!(lines[2], "");
+        // В обратном порядке: строка не зависит от предыдущих
+        let plain: Vec<&str> = log.lines().collect();
+        for (n, (hex_line, plain)) in lines.iter().zip(plain).enumerate().rev() {
+            let decrypted = Rc4::new(b"log key")
+                .decrypt_line(n as u64, hex_line)
+                .unwrap();
+            assert_eq!(decrypted, plain.as_bytes());
+        }
+
+        let mut decrypted = Vec::new();
+        assert_eq!(
+            cipher
+                .decrypt_lines(encrypted.as_bytes(), &mut decrypted)
+                .unwrap(),
+            4
+        );
+        assert_eq!(decrypted, format!("{}\n", log).into_bytes());
+
+        let err = cipher.decrypt_lines(&b"zz\n"[..], io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let restored = Rc4::from_state(cipher.export_state());
+        assert!(restored.encrypt_lines(&b"x"[..], io::sink()).is_err());
+    }
+
+    /// Ключ из окружения: hex декодируется, остальное берется как есть
+    #[cfg(feature = "env")]
+    #[test]
+    fn test_new_from_environment() {
+        use std::env;
+
+        let expected = Rc4::new(b"\x01\xab\xff").keystream_to_vec(32);
+        env::set_var("RC4_TEST_ENV_HEX", " 01abFF\n");
+        let mut cipher = Rc4::new_from_environment("RC4_TEST_ENV_HEX").unwrap();
+        assert_eq!(cipher.keystream_to_vec(32), expected);
+
+        env::set_var("RC4_TEST_ENV_RAW", "not hex: Key");
+        let mut cipher = Rc4::new_from_environment("RC4_TEST_ENV_RAW").unwrap();
+        assert_eq!(
+            cipher.keystream_to_vec(32),
+            Rc4::new(b"not hex: Key").keystream_to_vec(32)
+        );
+
+        assert_eq!(
+            Rc4::new_from_environment("RC4_TEST_ENV_UNSET").err(),
+            Some(Rc4Error::EnvVarMissing("RC4_TEST_ENV_UNSET".to_string()))
+        );
+        env::set_var("RC4_TEST_ENV_EMPTY", "");
+        assert_eq!(
+            Rc4::new_from_environment("RC4_TEST_ENV_EMPTY").err(),
+            Some(Rc4Error::EmptyKey)
+        );
+
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            env::set_var("RC4_TEST_ENV_BINARY", OsStr::from_bytes(b"\xff\xfe"));
+            assert_eq!(
+                Rc4::new_from_environment("RC4_TEST_ENV_BINARY").err(),
+                Some(Rc4Error::EnvVarInvalid("RC4_TEST_ENV_BINARY".to_string()))
+            );
+        }
+    }
+
+    /// Гамма XOR шифротекст дает открытый текст
+    #[test]
+    fn test_apply_with_keystream() {
+        let plaintext = b"Plaintext";
+        let (ciphertext, keystream) = Rc4::new(b"Key").apply_with_keystream(plaintext);
+        assert_eq!(ciphertext, Rc4::new(b"Key").apply(plaintext));
+        assert_eq!(
+            keystream,
+            Rc4::new(b"Key").keystream_to_vec(plaintext.len())
+        );
+        let recovered: Vec<u8> = keystream
+            .iter()
+            .zip(&ciphertext)
+            .map(|(k, c)| k ^ c)
+            .collect();
+        assert_eq!(recovered, plaintext);
+    }
+
+    /// Расширение ключа до его же длины или кратной ей совпадает с new
+    #[test]
+    fn test_new_expand() {
+        let key = b"abcd";
+        let reference = Rc4::new(key);
+        for target_len in [4, 8, 64, 256, 1000] {
+            assert!(Rc4::new_expand(key, target_len).same_state(&reference));
+        }
+        // Длина не кратна ключу: другое расписание
+        assert!(!Rc4::new_expand(key, 6).same_state(&reference));
+        assert!(Rc4::new_expand(key, 6).same_state(&Rc4::new(b"abcdab")));
+    }
+
+    /// Снимок S-box после KSA — перестановка 0..=255
+    #[test]
+    fn test_sbox_snapshot_is_permutation() {
+        let rc4 = Rc4::new(b"Key");
+        let mut snapshot = rc4.sbox_snapshot();
+        assert_eq!(&snapshot, rc4.export_state().sbox());
+        snapshot.sort_unstable();
+        assert!(snapshot.iter().enumerate().all(|(n, &b)| n == b as usize));
+    }
+
+    /// Свежий шифр и шифр, возвращенный к начальному снимку, в одном состоянии
+    #[test]
+    fn test_same_state() {
+        let fresh = Rc4::new(b"Key");
+        let initial = fresh.export_state();
+
+        let mut reset = Rc4::new(b"Key");
+        reset.skip(500);
+        assert!(!fresh.same_state(&reset));
+        reset = Rc4::from_state(initial);
+        assert!(fresh.same_state(&reset));
+
+        let mut advanced = fresh.clone();
+        assert!(advanced.same_state(&fresh));
+        advanced.next_byte();
+        assert!(!advanced.same_state(&fresh));
+        assert!(!fresh.same_state(&Rc4::new(b"Other")));
+    }
+
+    /// Клон выдает ту же гамму с точки ветвления и не зависит от оригинала
+    #[test]
+    fn test_clone_forks_keystream() {
+        let mut original = Rc4::new(b"Key");
+        original.skip(100);
+        let mut fork = original.clone();
+
+        let mut a = b"continuation A".to_vec();
+        let mut b = b"other branch B".to_vec();
+        original.process(&mut a);
+        fork.process(&mut b);
+        let keystream =
+            |ct: &[u8], pt: &[u8]| -> Vec<u8> { ct.iter().zip(pt).map(|(c, p)| c ^ p).collect() };
+        assert_eq!(
+            keystream(&a, b"continuation A"),
+            keystream(&b, b"other branch B")
+        );
+
+        // Продвижение копии не сдвигает оригинал
+        fork.skip(1000);
+        let mut reference = Rc4::new(b"Key");
+        reference.skip(100 + a.len());
+        assert_eq!(
+            original.keystream_to_vec(64),
+            reference.keystream_to_vec(64)
+        );
+    }
+
+    /// Аккумулятор при шифровании частями совпадает с отдельным подсчетом
+    #[test]
+    fn test_encrypt_with_rolling_mac() {
+        let plaintext: Vec<u8> = (0..1000u32).map(|n| (n * 7) as u8).collect();
+        let mut cipher = Rc4::new(b"Key");
+        let mut mac = ROLLING_MAC_INIT;
+        let mut ciphertext = cipher.encrypt_with_rolling_mac(&plaintext[..333], &mut mac);
+        ciphertext.extend(cipher.encrypt_with_rolling_mac(&plaintext[333..], &mut mac));
+
+        let mut expected = ROLLING_MAC_INIT;
+        rolling_mac_update(&mut expected, &plaintext);
+        assert_eq!(mac, expected);
+        assert_eq!(ciphertext, Rc4::new(b"Key").apply(&plaintext));
+
+        let mut empty = ROLLING_MAC_INIT;
+        rolling_mac_update(&mut empty, b"");
+        assert_eq!(empty, ROLLING_MAC_INIT);
+        // Контрольное значение FNV-1a 64 для "a"
+        let mut single = ROLLING_MAC_INIT;
+        rolling_mac_update(&mut single, b"a");
+        assert_eq!(single, 0xaf63_dc4c_8601_ec8c);
+    }
+
+    /// process совпадает с XOR гаммы при любом выравнивании начала буфера
+    #[test]
+    fn test_process_any_alignment() {
+        let source = Rc4::new(b"data").keystream_to_vec(5000);
+        let keystream = Rc4::new(b"Key").keystream_to_vec(5000);
+        for len in [0, 1, 7, 8, 9, 63, 64, 65, 127, 128, 129, 1000, 4099] {
+            for offset in 0..8 {
+                let expected: Vec<u8> = source[offset..offset + len]
+                    .iter()
+                    .zip(&keystream)
+                    .map(|(d, k)| d ^ k)
+                    .collect();
+
+                // Сдвиг внутри буфера меняет выравнивание начала среза
+                let mut buffer = source.clone();
+                let mut cipher = Rc4::new(b"Key");
+                cipher.process(&mut buffer[offset..offset + len]);
+                assert_eq!(
+                    &buffer[offset..offset + len],
+                    &expected[..],
+                    "len {} offset {}",
+                    len,
+                    offset
+                );
+
+                // Состояние после обработки тоже совпадает
+                let mut reference = Rc4::new(b"Key");
+                reference.skip(len);
+                assert_eq!(cipher.keystream_to_vec(16), reference.keystream_to_vec(16));
+            }
+        }
+    }
+
+    /// new_with_nonce эквивалентен try_new с ключом nonce || key
+    #[test]
+    fn test_new_with_nonce() {
+        let mut with_nonce = Rc4::new_with_nonce(b"secret", b"\x01\x02\x03").unwrap();
+        let mut concatenated = Rc4::new(b"\x01\x02\x03secret");
+        assert_eq!(
+            with_nonce.keystream_to_vec(32),
+            concatenated.keystream_to_vec(32)
+        );
+
+        assert_eq!(
+            Rc4::new_with_nonce(b"", b"iv").err(),
+            Some(Rc4Error::EmptyKey)
+        );
+        assert_eq!(
+            Rc4::new_with_nonce([1; 250], &[2; 7]).err(),
+            Some(Rc4Error::KeyTooLong(257))
+        );
+    }
+
+    /// IV перед шифротекстом: расшифровка восстанавливает 100 сообщений
+    #[test]
+    fn test_prepended_iv_roundtrip() {
+        let mut source = Rc4::new(b"messages");
+        for n in 0..100 {
+            let mut iv = [0u8; PREPENDED_IV_LEN];
+            source.fill_keystream(&mut iv);
+            let message = source.keystream_to_vec(n * 7);
+
+            let sealed = Rc4::encrypt_with_iv(b"Key", &iv, &message).unwrap();
+            assert_eq!(sealed.len(), PREPENDED_IV_LEN + message.len());
+            assert_eq!(&sealed[..PREPENDED_IV_LEN], &iv);
+            assert_eq!(
+                Rc4::decrypt_with_prepended_iv(b"Key", &sealed).unwrap(),
+                message
+            );
+        }
+
+        assert_eq!(
+            Rc4::decrypt_with_prepended_iv(b"Key", &[0; 15]).err(),
+            Some(Rc4Error::InputTooShort { len: 15, min: 16 })
+        );
+        assert_eq!(
+            Rc4::encrypt_with_iv(&[1; 241], &[0; 16], b"x").err(),
+            Some(Rc4Error::KeyTooLong(257))
+        );
+    }
+
+    /// Ядро без проверок границ дает ту же гамму и то же состояние, что и
+    /// безопасное
+    #[cfg(feature = "unsafe-opt")]
+    #[test]
+    fn test_prga_variants_match() {
+        let data = Rc4::new(b"data").keystream_to_vec(5000);
+        for key in [&b"Key"[..], b"Secret", &[0xff; 256]] {
+            for len in [0, 1, 2, 255, 256, 257, 1000, 5000] {
+                let mut checked = Rc4::new(key);
+                let mut unchecked = Rc4::new(key);
+                let mut expected = data[..len].to_vec();
+                let mut actual = expected.clone();
+                checked.process_bytes_with(&mut expected, PrgaVariant::Checked);
+                unchecked.process_bytes_with(&mut actual, PrgaVariant::Unchecked);
+                assert!(actual == expected, "key {:02x?}, len {}", key, len);
+                assert!(checked.same_state(&unchecked));
+
+                let mut default_path = data[..len].to_vec();
+                Rc4::new(key).process(&mut default_path);
+                assert!(default_path == expected);
+            }
+        }
+    }
+
+    /// Дифференциальный прогон ядер PRGA: случайные ключи всех длин,
+    /// случайные данные и разбиение на порции. Около 500 МБ гаммы на ядро,
+    /// поэтому только по запросу:
+    /// `cargo test --release --features unsafe-opt -- --ignored fuzz`
+    #[cfg(feature = "unsafe-opt")]
+    #[test]
+    #[ignore]
+    fn test_prga_variants_fuzz() {
+        // Источник случайности — сама гамма RC4: воспроизводимо и без зависимостей
+        let mut rng = Rc4::new(b"prga fuzz");
+        fn random(rng: &mut Rc4, bound: usize) -> usize {
+            u32::from_le_bytes(rng.next_array()) as usize % bound
+        }
+
+        for round in 0..20_000 {
+            let key_len = random(&mut rng, 256) + 1;
+            let key = rng.keystream_to_vec(key_len);
+            let len = random(&mut rng, 50_000);
+            let data = rng.keystream_to_vec(len);
+
+            let mut checked = Rc4::new(&key);
+            let mut unchecked = Rc4::new(&key);
+            let mut expected = data.clone();
+            let mut actual = data;
+            let mut offset = 0;
+            while offset < len {
+                let n = (random(&mut rng, 4096) + 1).min(len - offset);
+                let range = offset..offset + n;
+                checked.process_bytes_with(&mut expected[range.clone()], PrgaVariant::Checked);
+                unchecked.process_bytes_with(&mut actual[range], PrgaVariant::Unchecked);
+                offset += n;
+            }
+            assert!(actual == expected, "round {}, key {:02x?}", round, key);
+            assert!(checked.same_state(&unchecked), "round {}", round);
+        }
+    }
+
+    /// Блок зависит только от (nonce, counter) и совпадает с Rc4::new
+    #[test]
+    fn test_process_with_nonce_counter() {
+        let nonce = *b"8-bytes!";
+        let block = |counter| {
+            let mut data = [0u8; 64];
+            Rc4::process_with_nonce_counter(&nonce, counter, &mut data);
+            data
+        };
+
+        assert_eq!(block(5), block(5));
+        assert_ne!(block(5), block(6));
+        assert_ne!(block(0), block(u64::MAX));
+        let key = [&nonce[..], &7u64.to_le_bytes()].concat();
+        assert_eq!(block(7).to_vec(), Rc4::new(key).keystream_to_vec(64));
+
+        let mut data = b"counter mode".to_vec();
+        Rc4::process_with_nonce_counter(&nonce, 1, &mut data);
+        assert_ne!(data, b"counter mode");
+        Rc4::process_with_nonce_counter(&nonce, 1, &mut data);
+        assert_eq!(data, b"counter mode");
+    }
+
+    /// Измененный байт шифротекста или IV дает неверный открытый текст
+    #[test]
+    fn test_prepended_iv_tampering_changes_plaintext() {
+        let message = b"attack at dawn, bring snacks".to_vec();
+        let sealed = Rc4::encrypt_with_iv(b"Key", &[7; PREPENDED_IV_LEN], &message).unwrap();
+        for position in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[position] ^= 0x01;
+            let decrypted = Rc4::decrypt_with_prepended_iv(b"Key", &tampered).unwrap();
+            assert_ne!(decrypted, message, "byte {}", position);
+        }
+    }
+
+    /// process, skip, next_byte и потоковые адаптеры увеличивают счетчик
+    /// на число байт гаммы
+    #[test]
+    fn test_bytes_processed() {
+        let mut cipher = Rc4::new(b"Key");
+        assert_eq!((cipher.bytes_processed(), cipher.capacity()), (0, u64::MAX));
+
+        cipher.process(&mut [0u8; 1000]);
+        assert_eq!(cipher.bytes_processed(), 1000);
+        cipher.process(&mut [0u8; 5]);
+        cipher.skip(300);
+        assert_eq!(cipher.bytes_processed(), 1305);
+        cipher.next_byte();
+        cipher.fill_keystream(&mut [0u8; 10]);
+        cipher.process(&mut [0u8; 21]);
+        assert_eq!(cipher.bytes_processed(), 1337);
+        assert_eq!(cipher.capacity(), u64::MAX - 1337);
+
+        // Копия состояния и снимок: гамма та же, счетчик снимка — с нуля
+        assert_eq!(cipher.clone().bytes_processed(), 1337);
+        assert_eq!(Rc4::from_state(cipher.export_state()).bytes_processed(), 0);
+        cipher.process_vectored(&mut [&mut [0u8; 3], &mut [0u8; 4]]);
+        assert_eq!(cipher.bytes_processed(), 1344);
+    }
+
+    /// Последовательные окна с перекрытием: перекрытие берется из
+    /// предыдущего окна без изменений, каждое окно — срез сплошного
+    /// шифротекста
+    #[test]
+    fn test_process_rolling_window_overlap() {
+        let plaintext: Vec<u8> = (0..1000u32).map(|n| (n % 251) as u8).collect();
+        let ciphertext = Rc4::new(b"Key").apply(&plaintext);
+        let (len, overlap) = (100, 30);
+
+        let mut cipher = Rc4::new(b"Key");
+        let mut window = plaintext[..len].to_vec();
+        cipher.process_rolling_window(&mut window, 0).unwrap();
+        assert_eq!(window, ciphertext[..len]);
+
+        let mut start = 0;
+        while start + len - overlap + len <= plaintext.len() {
+            start += len - overlap;
+            let mut next = window[len - overlap..].to_vec();
+            next.extend_from_slice(&plaintext[start + overlap..start + len]);
+            cipher.process_rolling_window(&mut next, overlap).unwrap();
+            assert_eq!(next, ciphertext[start..start + len], "window at {}", start);
+            window = next;
+        }
+        assert_eq!(cipher.byte_position(), (start + len) as u64);
+
+        let before = cipher.clone();
+        assert_eq!(
+            cipher.process_rolling_window(&mut window, len + 1),
+            Err(Rc4Error::RangeOutOfBounds {
+                start: len + 1,
+                end: len,
+                len
+            })
+        );
+        assert!(cipher.same_state(&before));
+    }
+
+    /// Через байт: четные позиции шифруются, нечетные остаются открытыми,
+    /// гамма продвигается на каждый байт; расшифровка тем же вызовом
+    #[test]
+    fn test_process_indexed_every_other_byte() {
+        let message: Vec<u8> = (0..101u8).collect();
+        let keystream = Rc4::new(b"Key").keystream_to_vec(message.len() + 7);
+
+        let mut sender = Rc4::new(b"Key");
+        let mut data = message.clone();
+        sender.process_indexed(&mut data[..40], |pos| pos % 2 == 0);
+        sender.process_indexed(&mut data[40..], |pos| pos % 2 == 0);
+        for (pos, (&c, &p)) in data.iter().zip(&message).enumerate() {
+            let expected = if pos % 2 == 0 { p ^ keystream[pos] } else { p };
+            assert_eq!(c, expected, "position {}", pos);
+        }
+        assert_eq!(sender.byte_position(), message.len() as u64);
+        assert_eq!(sender.keystream_to_vec(7), keystream[message.len()..]);
+
+        let mut receiver = Rc4::new(b"Key");
+        receiver.process_indexed(&mut data, |pos| pos % 2 == 0);
+        assert_eq!(data, message);
+
+        // Позиции за 2^32 доходят до predicate без обрезки
+        let mut far = Rc4::new(b"Key");
+        far.bytes_processed = (1 << 32) - 2;
+        let mut seen = Vec::new();
+        far.process_indexed(&mut [0; 4], |pos| {
+            seen.push(pos);
+            false
+        });
+        assert_eq!(seen, [(1 << 32) - 2, (1 << 32) - 1, 1 << 32, (1 << 32) + 1]);
+    }
+
+    /// Позиция в гамме после process, skip и apply; переход через 2^64
+    #[test]
+    fn test_byte_position() {
+        let mut sender = Rc4::new(b"Key");
+        let mut receiver = Rc4::new(b"Key");
+        assert_eq!(sender.byte_position(), 0);
+
+        let ciphertext = sender.apply(b"Plaintext");
+        sender.skip(100);
+        sender.process(&mut [0u8; 16]);
+        assert_eq!(sender.byte_position(), 125);
+
+        receiver.process(&mut ciphertext.clone());
+        assert_eq!(receiver.byte_position(), 9);
+        receiver.skip(116);
+        assert_eq!(receiver.byte_position(), sender.byte_position());
+        assert!(receiver.same_state(&sender));
+
+        sender.bytes_processed = u64::MAX - 1;
+        sender.apply([0u8; 3]);
+        assert_eq!(sender.byte_position(), 1);
+        sender.skip(0);
+        assert_eq!(sender.byte_position(), 1);
+    }
+
+    /// Три буфера scatter-gather шифруются как их конкатенация
+    #[test]
+    fn test_process_vectored_matches_concatenation() {
+        let data = Rc4::new(b"data").keystream_to_vec(1000);
+        let mut expected = data.clone();
+        Rc4::new(b"Key").process(&mut expected);
+
+        let mut buffer = data.clone();
+        let (first, rest) = buffer.split_at_mut(3);
+        let (second, third) = rest.split_at_mut(600);
+        let mut cipher = Rc4::new(b"Key");
+        assert_eq!(
+            cipher.process_vectored(&mut [first, &mut [], second, third]),
+            1000
+        );
+        assert!(buffer == expected);
+
+        assert_eq!(cipher.process_vectored(&mut []), 0);
+        let mut reference = Rc4::new(b"Key");
+        reference.skip(1000);
+        assert!(cipher.same_state(&reference));
+    }
+
+    /// Получатель синхронизируется с телом сообщения, пропустив заголовок
+    #[test]
+    fn test_process_non_aligned_matches_consecutive_process() {
+        let mut header = *b"variable-length header";
+        let mut body = b"message body after the header".to_vec();
+        let plain_body = body.clone();
+
+        let mut sender = Rc4::new(b"Key");
+        sender.process(&mut header);
+        sender.process(&mut body);
+
+        let mut receiver = Rc4::new(b"Key");
+        receiver.process_non_aligned(&mut body, header.len());
+        assert_eq!(body, plain_body);
+        assert_eq!(receiver.keystream_to_vec(8), sender.keystream_to_vec(8));
+    }
+
+    /// step выдает те же байты, что и next_byte, и согласован с S-box
+    #[test]
+    fn test_step_matches_next_byte() {
+        let mut stepped = Rc4::new(b"Key");
+        let mut reference = Rc4::new(b"Key");
+        for n in 0..600 {
+            let step = stepped.step();
+            assert_eq!(step.byte, reference.next_byte());
+            assert_eq!(step.i, (n + 1) as u8);
+            assert_eq!((step.i, step.j), (stepped.i, stepped.j));
+
+            let s = stepped.export_state().sbox().to_owned();
+            let t = s[step.i as usize].wrapping_add(s[step.j as usize]);
+            assert_eq!(step.output_index, t);
+            assert_eq!(step.byte, s[t as usize]);
+        }
+    }
+
+    /// next_byte выдает ту же гамму, что и keystream_to_vec
+    #[test]
+    fn test_next_byte_matches_keystream() {
+        let mut bytewise = Rc4::new(b"Key");
+        let bytes: Vec<u8> = (0..300).map(|_| bytewise.next_byte()).collect();
+        assert_eq!(bytes, Rc4::new(b"Key").keystream_to_vec(300));
+    }
+
+    /// apply_to_writer пишет тот же шифротекст, что и apply
+    #[test]
+    fn test_apply_to_writer_matches_apply() {
+        let data: Vec<u8> = (0..20_000u32).map(|n| (n % 253) as u8).collect();
+
+        let mut sink = io::Cursor::new(Vec::new());
+        let mut rc4 = Rc4::new(b"Key");
+        assert_eq!(rc4.apply_to_writer(&data, &mut sink).unwrap(), data.len());
+        assert_eq!(rc4.apply_to_writer(b"tail", &mut sink).unwrap(), 4);
+        assert_eq!(rc4.apply_to_writer(b"", &mut sink).unwrap(), 0);
+
+        let mut reference = Rc4::new(b"Key");
+        let mut expected = reference.apply(&data);
+        expected.extend(reference.apply(b"tail"));
+        assert_eq!(sink.into_inner(), expected);
+    }
+
+    /// Сравнение за постоянное время
+    #[test]
+    fn test_constant_time_equals() {
+        assert!(Rc4::constant_time_equals(b"", b""));
+        assert!(Rc4::constant_time_equals(b"Plaintext", b"Plaintext"));
+        assert!(!Rc4::constant_time_equals(b"Plaintext", b"Plaintexu"));
+        assert!(!Rc4::constant_time_equals(b"Plaintext", b"Qlaintext"));
+        assert!(!Rc4::constant_time_equals(b"Plaintext", b"Plain"));
+        // Совпадающий префикс с нулевым хвостом не равен исходному
+        assert!(!Rc4::constant_time_equals(b"ab", b"ab\0"));
+    }
+
+    /// Восстановленный из снимка шифр продолжает гамму с той же позиции
+    #[test]
+    fn test_export_state_roundtrip() {
+        let mut rc4 = Rc4::new(b"Key");
+        rc4.skip(100);
+        let state = rc4.export_state();
+        let expected = rc4.keystream_to_vec(32);
+
+        let mut restored = Rc4::from_state(state.clone());
+        assert_eq!(restored.keystream_to_vec(32), expected);
+
+        let (s, i, j) = Rc4::from_state(state.clone()).into_raw_parts();
+        assert_eq!(&s, state.sbox());
+        assert_eq!((i, j), (state.i(), state.j()));
+        assert_eq!(i, 100);
+    }
+
+    /// Ключ из hex-строки; нечетная длина и не-hex символы — ошибки
+    #[test]
+    fn test_from_str_hex_key() {
+        let mut parsed: Rc4 = "4b6579".parse().unwrap();
+        assert_eq!(
+            parsed.keystream_to_vec(32),
+            Rc4::new(b"Key").keystream_to_vec(32)
+        );
+        let mut upper: Rc4 = "4B6579".parse().unwrap();
+        assert_eq!(
+            upper.keystream_to_vec(32),
+            Rc4::new(b"Key").keystream_to_vec(32)
+        );
+
+        let err = "4b657".parse::<Rc4>().err().unwrap();
+        assert_eq!(err, Rc4Error::InvalidHex(hex::HexError::OddLength(5)));
+        assert_eq!(err.to_string(), "invalid hex: odd number of hex digits (5)");
+
+        let err = "4b65zz".parse::<Rc4>().err().unwrap();
+        assert_eq!(
+            err,
+            Rc4Error::InvalidHex(hex::HexError::InvalidDigit {
+                position: 4,
+                found: 'z'
+            })
+        );
+        assert_eq!("".parse::<Rc4>().err(), Some(Rc4Error::EmptyKey));
+        assert_eq!(
+            "00".repeat(257).parse::<Rc4>().err(),
+            Some(Rc4Error::KeyTooLong(257))
+        );
+    }
+
+    /// process_or_skip эквивалентен skip при None и process при Some
+    #[test]
+    fn test_process_or_skip() {
+        let mut skipped = Rc4::new(b"Key");
+        let mut reference = Rc4::new(b"Key");
+        skipped.process_or_skip(None, 77).unwrap();
+        reference.skip(77);
+        assert!(skipped.same_state(&reference));
+
+        let mut buf = *b"0123456789abcdef";
+        let mut expected = buf;
+        skipped.process_or_skip(Some(&mut buf), 10).unwrap();
+        reference.process(&mut expected[..10]);
+        assert_eq!(buf, expected);
+        assert!(skipped.same_state(&reference));
+
+        let err = skipped.process_or_skip(Some(&mut buf[..4]), 5).unwrap_err();
+        assert_eq!(
+            err,
+            Rc4Error::RangeOutOfBounds {
+                start: 0,
+                end: 5,
+                len: 4
+            }
+        );
+        assert!(skipped.same_state(&reference));
+    }
+
+    /// Преобразование в снимок и обратно не сдвигает гамму
+    #[test]
+    fn test_state_from_conversions() {
+        let mut reference = Rc4::new(b"Key");
+        let mut rc4 = Rc4::new(b"Key");
+        reference.skip(300);
+        rc4.skip(300);
+
+        let state = Rc4State::from(rc4);
+        assert!(state == reference.export_state());
+        let mut restored: Rc4 = state.into();
+        assert_eq!(
+            restored.keystream_to_vec(64),
+            reference.keystream_to_vec(64)
+        );
+    }
+
+    /// Снимки равны только при совпадении всех 258 байт
+    #[test]
+    fn test_state_equality() {
+        let state = Rc4::new(b"Key").export_state();
+        assert!(state == Rc4::new(b"Key").export_state());
+
+        let mut first = state.clone();
+        first.s[0] ^= 1;
+        let mut last = state.clone();
+        last.s[255] ^= 0x80;
+        let mut i = state.clone();
+        i.i = 1;
+        let mut j = state.clone();
+        j.j = 1;
+        for other in [&first, &last, &i, &j] {
+            assert!(state != *other);
+            #[cfg(feature = "subtle")]
+            assert_eq!(state.ct_eq(other).unwrap_u8(), 0);
+        }
+        #[cfg(feature = "subtle")]
+        assert_eq!(state.ct_eq(&state.clone()).unwrap_u8(), 1);
+    }
+
+    // Rc4 не должен реализовывать PartialEq: при наличии реализации вызов
+    // ниже становится неоднозначным и не компилируется
+    const _: fn() = || {
+        trait AmbiguousIfPartialEq<A> {
+            fn some_item() {}
+        }
+        impl<T: ?Sized> AmbiguousIfPartialEq<()> for T {}
+        struct Invalid;
+        impl<T: ?Sized + PartialEq> AmbiguousIfPartialEq<Invalid> for T {}
+
+        let _ = <Rc4 as AmbiguousIfPartialEq<_>>::some_item;
+    };
+
+    /// Разные ключи отбеливания дают разные S-box и шифротекст
+    #[test]
+    fn test_builder_key_whitening() {
+        let plain = Rc4::builder(b"BaseKey").build().unwrap();
+        let white_a = Rc4::builder(b"BaseKey")
+            .with_key_whitening(b"pad-a")
+            .build()
+            .unwrap();
+        let white_b = Rc4::builder(b"BaseKey")
+            .with_key_whitening(b"pad-b")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            plain.export_state().sbox(),
+            Rc4::new(b"BaseKey").export_state().sbox()
+        );
+        assert_ne!(white_a.export_state().sbox(), white_b.export_state().sbox());
+        assert_ne!(white_a.export_state().sbox(), plain.export_state().sbox());
+
+        let (mut white_a, mut white_b) = (white_a, white_b);
+        assert_ne!(white_a.apply(b"Plaintext"), white_b.apply(b"Plaintext"));
+
+        // Отбеливание эквивалентно KSA над вручную подготовленным ключом
+        let whitened: Vec<u8> = b"BaseKey"
+            .iter()
+            .zip(b"pad-a".iter().cycle())
+            .map(|(k, w)| k ^ w)
+            .collect();
+        let mut reference = Rc4::new(&whitened);
+        let mut white_a = Rc4::builder(b"BaseKey")
+            .with_key_whitening(b"pad-a")
+            .build()
+            .unwrap();
+        assert_eq!(white_a.apply(b"Plaintext"), reference.apply(b"Plaintext"));
+    }
+
+    /// Заполнение неинициализированного буфера совпадает с fill_keystream
+    #[test]
+    fn test_fill_keystream_uninit_matches() {
+        let mut rc4_a = Rc4::new(b"Key");
+        let mut rc4_b = Rc4::new(b"Key");
+
+        for len in [0, 1, 9, 300] {
+            let mut expected = vec![0xAAu8; len];
+            rc4_a.fill_keystream(&mut expected);
+
+            let mut uninit = vec![MaybeUninit::<u8>::uninit(); len];
+            assert_eq!(rc4_b.fill_keystream_uninit(&mut uninit), &expected[..]);
+        }
+
+        // Первые 9 байт гаммы для "Key" дают известный вектор
+        let mut buf = [MaybeUninit::uninit(); 9];
+        let keystream = Rc4::new(b"Key").fill_keystream_uninit(&mut buf);
+        let ciphertext: Vec<u8> = b"Plaintext"
+            .iter()
+            .zip(keystream.iter())
+            .map(|(p, k)| p ^ k)
+            .collect();
+        assert_eq!(
+            ciphertext,
+            [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+        );
+    }
+
+    /// Векторы из Википедии через hex-обертки в обе стороны
+    #[test]
+    fn test_hex_helpers() {
+        let vectors: [(&[u8], &[u8], &str); 3] = [
+            (b"Key", b"Plaintext", "bbf316e8d940af0ad3"),
+            (b"Wiki", b"pedia", "1021bf0420"),
+            (b"Secret", b"Attack at dawn", "45a01f645fc35b383552544b9bf5"),
+        ];
+        for (key, plaintext, ciphertext) in vectors {
+            assert_eq!(rc4_hex(key, plaintext), ciphertext);
+            assert_eq!(Rc4::new(key).apply_hex(plaintext), ciphertext);
+            assert_eq!(rc4_unhex(key, ciphertext).unwrap(), plaintext);
+            let spaced: String = ciphertext
+                .to_uppercase()
+                .as_bytes()
+                .chunks(2)
+                .map(|pair| std::str::from_utf8(pair).unwrap())
+                .collect::<Vec<_>>()
+                .join(" ");
+            assert_eq!(Rc4::new(key).apply_from_hex(&spaced).unwrap(), plaintext);
+        }
+
+        let mut cipher = Rc4::new(b"Key");
+        assert_eq!(
+            cipher.apply_from_hex("bb f3 1g"),
+            Err(Rc4Error::InvalidHex(hex::HexError::InvalidDigit {
+                position: 7,
+                found: 'g'
+            }))
+        );
+        assert_eq!(
+            cipher.apply_from_hex("bbf"),
+            Err(Rc4Error::InvalidHex(hex::HexError::OddLength(3)))
+        );
+        assert_eq!(cipher.bytes_processed(), 0);
+        assert_eq!(rc4_unhex(b"", "bb"), Err(Rc4Error::EmptyKey));
+    }
+
+    /// next_array совпадает с fill_keystream той же длины
+    #[test]
+    fn test_next_array() {
+        let keystream = Rc4::new(b"Key").next_array::<9>();
+        let ciphertext: Vec<u8> = b"Plaintext"
+            .iter()
+            .zip(&keystream)
+            .map(|(p, k)| p ^ k)
+            .collect();
+        assert_eq!(
+            ciphertext,
+            [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+        );
+
+        let mut cipher = Rc4::new(b"Key");
+        let mut reference = Rc4::new(b"Key");
+        let mut expected = [0u8; 16];
+        for _ in 0..3 {
+            reference.fill_keystream(&mut expected);
+            assert_eq!(cipher.next_array::<16>(), expected);
+        }
+        assert_eq!(cipher.next_array::<0>(), [0u8; 0]);
+        assert!(cipher.same_state(&reference));
+    }
+
+    /// new_with_header_skip дает то же состояние, что new + skip
+    #[test]
+    fn test_new_with_header_skip() {
+        for header_len in [0, 1, 255, 256, 3072] {
+            let mut two_step = Rc4::new(b"Key");
+            two_step.skip(header_len);
+            let combined = Rc4::new_with_header_skip(b"Key", header_len).unwrap();
+            assert!(combined.same_state(&two_step), "header_len {}", header_len);
+            assert!(combined.export_state() == two_step.export_state());
+            assert_eq!(combined.bytes_processed(), header_len as u64);
+        }
+        assert_eq!(
+            Rc4::new_with_header_skip(b"", 16).err(),
+            Some(Rc4Error::EmptyKey)
+        );
+    }
+
+    /// Прогрев эквивалентен new + skip
+    #[test]
+    fn test_new_with_warmup() {
+        let mut reference = Rc4::new(b"Key");
+        reference.skip(768);
+        let expected = reference.keystream_to_vec(32);
+
+        assert_eq!(
+            Rc4::new_with_warmup(b"Key", 768).keystream_to_vec(32),
+            expected
+        );
+        assert_eq!(
+            Rc4::new_drop(b"Key", 768).unwrap().keystream_to_vec(32),
+            expected
+        );
+        assert_eq!(Rc4::new_drop(b"", 768).err(), Some(Rc4Error::EmptyKey));
+    }
+
+    /// process_range шифрует только середину буфера
+    #[test]
+    fn test_process_range() {
+        let original: Vec<u8> = (0..100u8).collect();
+        let mut data = original.clone();
+
+        let mut rc4 = Rc4::new(b"Key");
+        rc4.process_range(&mut data, 10..90).unwrap();
+
+        assert_eq!(data[..10], original[..10]);
+        assert_eq!(data[90..], original[90..]);
+        assert_eq!(data[10..90], Rc4::new(b"Key").apply(&original[10..90])[..]);
+
+        // Состояние продвинуто ровно на длину диапазона
+        let mut reference = Rc4::new(b"Key");
+        reference.skip(80);
+        assert_eq!(rc4.keystream_to_vec(8), reference.keystream_to_vec(8));
+
+        assert_eq!(
+            rc4.process_range(&mut data, 50..101),
+            Err(Rc4Error::RangeOutOfBounds {
+                start: 50,
+                end: 101,
+                len: 100
+            })
+        );
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 60..50;
+        assert!(rc4.process_range(&mut data, reversed).is_err());
+    }
+
+    /// Недопустимая длина ключа возвращается как ошибка
+    #[test]
+    fn test_try_new_key_length() {
+        assert_eq!(Rc4::try_new(b"").err(), Some(Rc4Error::EmptyKey));
+        assert_eq!(
+            Rc4::try_new([0u8; 257]).err(),
+            Some(Rc4Error::KeyTooLong(257))
+        );
+        assert!(Rc4::try_new([0u8; 256]).is_ok());
+    }
+
+    /// Ключ из String, Vec<u8> и массива с одинаковыми байтами дает одну гамму
+    #[test]
+    fn test_key_as_ref() {
+        let expected = Rc4::new(b"Key".as_slice()).keystream_to_vec(32);
+        assert_eq!(Rc4::new(String::from("Key")).keystream_to_vec(32), expected);
+        assert_eq!(Rc4::new("Key").keystream_to_vec(32), expected);
+        let vec_key: Vec<u8> = vec![b'K', b'e', b'y'];
+        assert_eq!(Rc4::new(vec_key).keystream_to_vec(32), expected);
+        assert_eq!(Rc4::new([b'K', b'e', b'y']).keystream_to_vec(32), expected);
+        assert_eq!(Rc4::try_new(String::new()).err(), Some(Rc4Error::EmptyKey));
+    }
+
+    /// Энтропия: 0 для повторов, log2(16) * 16 для 16 различных байт
+    #[test]
+    fn test_key_entropy_estimate() {
+        assert_eq!(key_entropy_estimate(&[0x41; 32]), 0.0);
+        assert_eq!(key_entropy_estimate(&[]), 0.0);
+
+        let distinct: Vec<u8> = (0..16).collect();
+        assert!((key_entropy_estimate(&distinct) - 64.0).abs() < 1e-9);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((key_entropy_estimate(&all) - 8.0 * 256.0).abs() < 1e-9);
+
+        let key = key_entropy_estimate(b"Key");
+        assert!(key > 0.0 && key < 8.0 * 3.0);
+        assert!((key - 3.0 * 3f64.log2()).abs() < 1e-9);
+        assert!(key_entropy_estimate(b"aab") < key);
+    }
+
+    /// Ключ из одинаковых байт отклоняется, обычный ключ проходит
+    #[test]
+    fn test_key_quality() {
+        assert_eq!(key_quality(&[0u8; 16]), KeyQuality::AllBytesEqual);
+        assert_eq!(key_quality(&[0x41; 5]), KeyQuality::AllBytesEqual);
+        assert_eq!(key_quality(b"SecretKey"), KeyQuality::Acceptable);
+
+        assert_eq!(
+            Rc4::new_checked([0u8; 16]).err(),
+            Some(Rc4Error::WeakKey(KeyQuality::AllBytesEqual))
+        );
+        assert_eq!(Rc4::new_checked(b"").err(), Some(Rc4Error::EmptyKey));
+        assert!(Rc4::new_checked(b"SecretKey").is_ok());
+    }
+
+    /// new_multi создает шифр на каждый ключ и сообщает ошибки по отдельности
+    #[test]
+    fn test_new_multi() {
+        let too_long = [0u8; 300];
+        let keys: [&[u8]; 4] = [b"Key", b"", b"Wiki", &too_long];
+        let ciphers = Rc4::new_multi(&keys);
+
+        assert_eq!(ciphers.len(), 4);
+        assert_eq!(ciphers[1].as_ref().err(), Some(&Rc4Error::EmptyKey));
+        assert_eq!(ciphers[3].as_ref().err(), Some(&Rc4Error::KeyTooLong(300)));
+
+        let mut results = ciphers.into_iter();
+        let mut key = results.next().unwrap().unwrap();
+        assert_eq!(
+            key.apply(b"Plaintext"),
+            Rc4::new(b"Key").apply(b"Plaintext")
+        );
+        let mut wiki = results.nth(1).unwrap().unwrap();
+        assert_eq!(wiki.apply(b"pedia"), [0x10, 0x21, 0xBF, 0x04, 0x20]);
+    }
+
+    /// Гамма, примененная через xor_in_place, совпадает с process
+    #[test]
+    fn test_keystream_xor_matches_process() {
+        let data: Vec<u8> = (0..1000u32).map(|n| (n * 7) as u8).collect();
+
+        let mut expected = data.clone();
+        Rc4::new(b"Key").process(&mut expected);
+
+        let keystream = Rc4::new(b"Key").keystream_to_vec(data.len());
+        // Одна гамма применяется многократно к копиям данных
+        for _ in 0..3 {
+            let mut actual = data.clone();
+            xor_in_place(&mut actual, &keystream).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    /// Невыровненные буферы и хвосты длиной 1..16
+    #[test]
+    fn test_xor_in_place_misaligned_tails() {
+        let backing: Vec<u8> = (0..64u8).collect();
+        let keystream_full = Rc4::new(b"Key").keystream_to_vec(64);
+
+        for offset in 0..8 {
+            for len in 1..=16 {
+                let mut data = backing.clone();
+                let slice = &mut data[offset..offset + len];
+                let keystream = &keystream_full[3..3 + len];
+                xor_in_place(slice, keystream).unwrap();
+
+                for n in 0..len {
+                    assert_eq!(data[offset + n], backing[offset + n] ^ keystream[n]);
+                }
+                // Байты за пределами среза не затронуты
+                assert_eq!(data[..offset], backing[..offset]);
+                assert_eq!(data[offset + len..], backing[offset + len..]);
+            }
+        }
+    }
+
+    /// Несовпадение длин — ошибка, данные не изменяются
+    #[test]
+    fn test_xor_in_place_length_mismatch() {
+        let mut data = [1u8, 2, 3];
+        let err = xor_in_place(&mut data, &[0u8; 2]).unwrap_err();
+        assert_eq!(
+            err,
+            Rc4Error::LengthMismatch {
+                data: 3,
+                keystream: 2
+            }
+        );
+        assert_eq!(data, [1, 2, 3]);
+    }
+
+    /// Простейший детерминированный ГПСЧ для тестов new_random
+    #[cfg(feature = "rand_core")]
+    struct CounterRng(u64);
+
+    #[cfg(feature = "rand_core")]
+    impl rand_core::RngCore for CounterRng {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    /// Один ключ дает одну перестановку, разные ключи — разные
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn test_shuffle_deterministic() {
+        let original: Vec<u32> = (0..100).collect();
+        let shuffled = |key: &[u8]| {
+            let mut items = original.clone();
+            Rc4::new(key).shuffle(&mut items);
+            items
+        };
+
+        let a = shuffled(b"seed");
+        assert_eq!(a, shuffled(b"seed"));
+        assert_ne!(a, shuffled(b"other seed"));
+        assert_ne!(a, original);
+
+        let mut sorted = a.clone();
+        sorted.sort();
+        assert_eq!(sorted, original);
+
+        let mut empty: [u8; 0] = [];
+        Rc4::new(b"seed").shuffle(&mut empty);
+    }
+
+    /// Случайный IV из ГПСЧ: разные IV для одного сообщения, расшифровка обратна
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn test_encrypt_with_prepended_iv_rng() {
+        let mut rng = CounterRng(7);
+        let a = Rc4::encrypt_with_prepended_iv(b"Key", &mut rng, b"hello").unwrap();
+        let b = Rc4::encrypt_with_prepended_iv(b"Key", &mut rng, b"hello").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(
+            Rc4::decrypt_with_prepended_iv(b"Key", &a).unwrap(),
+            b"hello"
+        );
+        assert_eq!(
+            Rc4::decrypt_with_prepended_iv(b"Key", &b).unwrap(),
+            b"hello"
+        );
+    }
+
+    /// Разные состояния ГПСЧ дают разные шифры, а ключ восстанавливает шифр
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn test_new_random() {
+        let (mut rc4_a, key_a) = Rc4::new_random(&mut CounterRng(1));
+        let (mut rc4_b, key_b) = Rc4::new_random(&mut CounterRng(2));
+        assert_ne!(key_a, key_b);
+
+        let stream_a = rc4_a.keystream_to_vec(32);
+        assert_ne!(stream_a, rc4_b.keystream_to_vec(32));
+
+        let mut restored = Rc4::try_new(key_a).unwrap();
+        assert_eq!(restored.keystream_to_vec(32), stream_a);
+    }
+
+    /// Zeroize затирает весь снимок состояния
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_state_zeroize() {
+        use std::mem::ManuallyDrop;
+        use zeroize::Zeroize;
+
+        let mut state = ManuallyDrop::new(Rc4::new(b"Key").export_state());
+        state.zeroize();
+
+        // black_box не дает компилятору считать затирание мертвой записью
+        let state = std::hint::black_box(&*state);
+        assert!(state.sbox().iter().all(|&b| b == 0));
+        assert_eq!((state.i(), state.j()), (0, 0));
+    }
+
+    /// new_with_counter равен new на склеенном ключе base_key || counter
+    #[test]
+    fn test_new_with_counter() {
+        for counter in [0, 1, 0x0102_0304_0506_0708, u64::MAX] {
+            let mut key = b"base key".to_vec();
+            key.extend_from_slice(&counter.to_le_bytes());
+            let cipher = Rc4::new_with_counter(b"base key", counter).unwrap();
+            assert!(cipher.same_state(&Rc4::new(&key)), "counter {}", counter);
+        }
+        assert!(!Rc4::new_with_counter(b"k", 1)
+            .unwrap()
+            .same_state(&Rc4::new_with_counter(b"k", 2).unwrap()));
+
+        assert!(Rc4::new_with_counter([1; 248], 7).is_ok());
+        assert_eq!(
+            Rc4::new_with_counter([1; 249], 7).err(),
+            Some(Rc4Error::KeyTooLong(257))
+        );
+        assert_eq!(
+            Rc4::new_with_counter(b"", 7).err(),
+            Some(Rc4Error::EmptyKey)
+        );
+        // Принимает то же, что new: String, Vec, массивы
+        assert!(Rc4::new_with_counter(String::from("k"), 7)
+            .unwrap()
+            .same_state(&Rc4::new_with_counter(vec![b'k'], 7).unwrap()));
+    }
+
+    /// Один и тот же общий секрет и info дают одно состояние, разные — разные
+    #[cfg(feature = "kdf")]
+    #[test]
+    fn test_new_from_shared_secret() {
+        let secret = sha256::sha256(b"shared DH secret");
+        let a = Rc4::new_from_shared_secret(&secret, None).unwrap();
+        let b = Rc4::new_from_shared_secret(&secret, Some(b"")).unwrap();
+        assert!(a.same_state(&b));
+
+        let mut key = [0u8; 16];
+        sha256::hkdf_sha256(&[], &secret, &[], &mut key);
+        assert!(a.same_state(&Rc4::new(key)));
+        assert!(!a.same_state(&Rc4::new(&secret[..16])));
+
+        let other = Rc4::new_from_shared_secret(&sha256::sha256(b"other"), None).unwrap();
+        assert!(!a.same_state(&other));
+        let client = Rc4::new_from_shared_secret(&secret, Some(b"client to server")).unwrap();
+        let server = Rc4::new_from_shared_secret(&secret, Some(b"server to client")).unwrap();
+        assert!(!client.same_state(&server) && !client.same_state(&a));
+
+        assert_eq!(
+            Rc4::new_from_shared_secret(b"", None).err(),
+            Some(Rc4Error::EmptyKey)
+        );
+    }
+
+    /// Удаление шифра затирает S-box и индексы
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_cipher_zeroized_on_drop() {
+        use std::mem::ManuallyDrop;
+
+        let mut cipher = ManuallyDrop::new(Rc4::new(b"Key"));
+        cipher.skip(10);
+        // SAFETY: после drop_in_place читаются только встроенные поля u8,
+        // которые не владеют ресурсами
+        unsafe { std::ptr::drop_in_place(&mut *cipher as *mut Rc4) };
+        let cipher = std::hint::black_box(&*cipher);
+        assert!(cipher.s.iter().all(|&b| b == 0));
+        assert_eq!((cipher.i, cipher.j), (0, 0));
+    }
+
+    /// new_secret дает тот же шифр, что и try_new с открытым ключом
+    #[cfg(feature = "secrecy")]
+    #[test]
+    fn test_new_secret() {
+        let key = secrecy::SecretSlice::from(b"Key".to_vec());
+        let mut cipher = Rc4::new_secret(&key).unwrap();
+        assert!(cipher.same_state(&Rc4::new(b"Key")));
+        assert_eq!(
+            cipher.apply(b"Plaintext"),
+            [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+        );
+        let empty = secrecy::SecretSlice::from(Vec::new());
+        assert_eq!(Rc4::new_secret(&empty).err(), Some(Rc4Error::EmptyKey));
+    }
+
+    /// SecretKey принимается конструкторами и затирает ключ при удалении
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_secret_key_zeroized_on_drop() {
+        use std::mem::ManuallyDrop;
+
+        let key = SecretKey::from(b"Key".to_vec());
+        assert_eq!(
+            Rc4::new(&key).keystream_to_vec(16),
+            Rc4::new(b"Key").keystream_to_vec(16)
+        );
+        assert_eq!(format!("{:?}", key), "SecretKey(..)");
+        drop(Rc4::new(key));
+
+        // Массив лежит внутри обертки, поэтому после drop_in_place его байты
+        // остаются доступны для проверки (память Vec была бы освобождена)
+        let mut key = ManuallyDrop::new(SecretKey::new(*b"0123456789abcdef"));
+        assert_eq!(key.as_ref(), b"0123456789abcdef");
+        // SAFETY: key больше не используется как SecretKey; ниже читаются
+        // только байты массива u8, который не владеет ресурсами
+        unsafe { std::ptr::drop_in_place(&mut *key as *mut SecretKey<[u8; 16]>) };
+        let bytes = std::hint::black_box(&key.0);
+        assert_eq!(*bytes, [0u8; 16]);
+    }
+}
@@ -0,0 +1,175 @@
+//! `Rc4Limited` — RC4 с ограничением объема гаммы на один ключ.
+
+use crate::{Rc4, Rc4Error};
+
+/// Шифр, который выдает не больше `limit` байт гаммы, считая с момента
+/// `Rc4::with_limit`.
+///
+/// Обработка «все или ничего»: если буфер не помещается в остаток лимита,
+/// `process` возвращает `Rc4Error::UsageLimitExceeded`, не трогая ни данные,
+/// ни состояние шифра, и лимит по-прежнему можно дорасходовать буфером
+/// поменьше (см. `remaining`). Буфер, заканчивающийся ровно на границе,
+/// обрабатывается.
+///
+/// `skip` гамму не выдает и поэтому не ограничивается, но пропущенные байты
+/// расходуют лимит: после пропуска за границу любая непустая обработка
+/// возвращает ошибку. Методы `Rc4` намеренно недоступны через `Deref`, чтобы
+/// лимит нельзя было обойти; `into_inner` снимает ограничение явно.
+#[derive(Clone)]
+pub struct Rc4Limited {
+    cipher: Rc4,
+    limit: u64,
+    used: u64,
+}
+
+impl Rc4Limited {
+    pub fn new(cipher: Rc4, limit: u64) -> Self {
+        Rc4Limited {
+            cipher,
+            limit,
+            used: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Байт гаммы, выданных или пропущенных с момента установки лимита.
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// Сколько байт еще можно обработать; 0 после пропуска за границу.
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+
+    /// Шифрует/расшифровывает `data` на месте целиком или, если буфер
+    /// не помещается в остаток лимита, не обрабатывает ничего.
+    #[inline]
+    pub fn process<T: AsMut<[u8]> + ?Sized>(&mut self, data: &mut T) -> Result<(), Rc4Error> {
+        self.process_slice(data.as_mut())
+    }
+
+    fn process_slice(&mut self, data: &mut [u8]) -> Result<(), Rc4Error> {
+        self.reserve(data.len())?;
+        self.cipher.process(data);
+        Ok(())
+    }
+
+    pub fn apply(&mut self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, Rc4Error> {
+        let mut output = data.as_ref().to_vec();
+        self.process_slice(&mut output)?;
+        Ok(output)
+    }
+
+    pub fn fill_keystream(&mut self, out: &mut [u8]) -> Result<(), Rc4Error> {
+        self.reserve(out.len())?;
+        self.cipher.fill_keystream(out);
+        Ok(())
+    }
+
+    pub fn keystream_to_vec(&mut self, len: usize) -> Result<Vec<u8>, Rc4Error> {
+        self.reserve(len)?;
+        Ok(self.cipher.keystream_to_vec(len))
+    }
+
+    /// Пропускает `n` байт гаммы; пропущенные байты расходуют лимит.
+    pub fn skip(&mut self, n: usize) {
+        self.cipher.skip(n);
+        self.used = self.used.saturating_add(n as u64);
+    }
+
+    /// Снимает ограничение и возвращает шифр в текущем состоянии.
+    pub fn into_inner(self) -> Rc4 {
+        self.cipher
+    }
+
+    /// Учитывает `len` байт, если они помещаются в остаток лимита.
+    fn reserve(&mut self, len: usize) -> Result<(), Rc4Error> {
+        if len == 0 {
+            return Ok(());
+        }
+        match self.used.checked_add(len as u64) {
+            Some(end) if end <= self.limit => {
+                self.used = end;
+                Ok(())
+            }
+            _ => Err(Rc4Error::UsageLimitExceeded {
+                processed: self.used,
+                limit: self.limit,
+            }),
+        }
+    }
+}
+
+impl Rc4 {
+    /// Ограничивает гамму этого шифра `max_bytes` байтами; см. `Rc4Limited`.
+    /// Без вызова объем не ограничен.
+    pub fn with_limit(self, max_bytes: u64) -> Rc4Limited {
+        Rc4Limited::new(self, max_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit_error(processed: u64, limit: u64) -> Result<(), Rc4Error> {
+        Err(Rc4Error::UsageLimitExceeded { processed, limit })
+    }
+
+    /// Буфер, заканчивающийся ровно на границе, обрабатывается, следующий — нет
+    #[test]
+    fn test_limit_on_buffer_boundary() {
+        let expected = Rc4::new(b"Key").keystream_to_vec(100);
+        let mut cipher = Rc4::new(b"Key").with_limit(100);
+
+        let mut data = [0u8; 60];
+        assert_eq!(cipher.process(&mut data), Ok(()));
+        assert_eq!(cipher.keystream_to_vec(40).unwrap(), expected[60..]);
+        assert_eq!(cipher.remaining(), 0);
+
+        assert_eq!(cipher.process(&mut [0u8; 1]), limit_error(100, 100));
+        assert_eq!(cipher.process(&mut []), Ok(()));
+        assert_eq!(cipher.used(), 100);
+    }
+
+    /// Буфер, пересекающий границу, не обрабатывается вовсе: ни данные,
+    /// ни состояние не меняются, остаток лимита можно дорасходовать
+    #[test]
+    fn test_limit_mid_buffer_is_all_or_nothing() {
+        let expected = Rc4::new(b"Key").keystream_to_vec(100);
+        let mut cipher = Rc4::new(b"Key").with_limit(100);
+        assert_eq!(cipher.keystream_to_vec(70).unwrap(), expected[..70]);
+
+        let mut data = [0x5au8; 50];
+        assert_eq!(cipher.process(&mut data), limit_error(70, 100));
+        assert_eq!(data, [0x5au8; 50]);
+        assert!(matches!(
+            cipher.apply([0u8; 31]),
+            Err(Rc4Error::UsageLimitExceeded { processed: 70, .. })
+        ));
+        assert_eq!(cipher.used(), 70);
+
+        assert_eq!(cipher.keystream_to_vec(30).unwrap(), expected[70..]);
+        let mut reference = Rc4::new(b"Key");
+        reference.skip(100);
+        assert!(cipher.into_inner().same_state(&reference));
+    }
+
+    /// Пропуск за границу расходует лимит: дальше обработка запрещена
+    #[test]
+    fn test_limit_after_skip_past_it() {
+        let mut cipher = Rc4::new(b"Key").with_limit(100);
+        cipher.skip(40);
+        assert_eq!(cipher.keystream_to_vec(60).map(|k| k.len()), Ok(60));
+
+        let mut cipher = Rc4::new(b"Key").with_limit(100);
+        cipher.skip(150);
+        assert_eq!(cipher.remaining(), 0);
+        assert_eq!(cipher.process(&mut [0u8; 1]), limit_error(150, 100));
+        assert_eq!(cipher.fill_keystream(&mut [0u8; 1]), limit_error(150, 100));
+    }
+}
@@ -0,0 +1,42 @@
+//! Вывод диагностики утилиты `rc4` в stderr.
+//!
+//! CLI пишет диагностику через фасад `log`, а этот модуль бинарника
+//! устанавливает для него простой журнал. Библиотека сама журнал не
+//! устанавливает; из ее кода через `log::warn!` пишет только `LockedRc4`,
+//! и только с feature `log`.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Пишет каждое сообщение одной строкой в stderr с префиксом уровня.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let prefix = match record.level() {
+            Level::Error => "error: ",
+            Level::Warn => "warning: ",
+            Level::Info => "",
+            Level::Debug => "debug: ",
+            Level::Trace => "trace: ",
+        };
+        eprintln!("{}{}", prefix, record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Устанавливает журнал с порогом `Info`; `cli::run` меняет порог по
+/// `-q`/`-v`.
+pub fn init() {
+    // Журнал устанавливается один раз при старте процесса
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
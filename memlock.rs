@@ -0,0 +1,187 @@
+//! `LockedRc4` — состояние шифра в памяти, закрепленной от выгрузки в swap.
+
+use std::alloc::{self, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{self, Ordering};
+
+use crate::{Rc4, Rc4Error};
+
+/// Предупреждение о недоступном `mlock` пишется один раз на процесс.
+#[cfg(feature = "log")]
+static WARNED: atomic::AtomicBool = atomic::AtomicBool::new(false);
+
+/// `Rc4`, размещенный на отдельной странице памяти, закрепленной через
+/// `mlock` (`VirtualLock` в Windows, крейт `region`), чтобы S-box —
+/// эквивалент ключа — не попал в swap. Остальной API `Rc4` доступен через
+/// `Deref`/`DerefMut`.
+///
+/// Каждый экземпляр занимает целую страницу: `munlock` не считает ссылки, и
+/// общая страница разблокировалась бы при удалении первого из соседей.
+/// Если закрепить страницу не удалось (например, исчерпан `RLIMIT_MEMLOCK`),
+/// шифр работает в обычной памяти: `is_locked` возвращает `false`, а с
+/// feature `log` один раз пишется `log::warn!`. При удалении страница
+/// затирается и только затем разблокируется.
+///
+/// Ключ, сохраняемый с feature `rekeying`, лежит в отдельном буфере вне
+/// закрепленной страницы.
+pub struct LockedRc4 {
+    cipher: NonNull<Rc4>,
+    layout: Layout,
+    guard: Option<region::LockGuard>,
+}
+
+// SAFETY: LockedRc4 единолично владеет страницей с Rc4 (который Send и
+// Sync), а LockGuard хранит только адрес и длину закрепленной области
+unsafe impl Send for LockedRc4 {}
+unsafe impl Sync for LockedRc4 {}
+
+impl LockedRc4 {
+    /// Паникует при недопустимой длине ключа, как `Rc4::new`.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        match Self::try_new(key) {
+            Ok(rc4) => rc4,
+            Err(_) => panic!("Key length must be between 1 and 256 bytes"),
+        }
+    }
+
+    /// Закрепляет страницу до KSA и выполняет KSA на месте (`Rc4::rekey`),
+    /// поэтому состояние от ключа не появляется вне закрепленной страницы.
+    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self, Rc4Error> {
+        let page = region::page::size();
+        let layout =
+            Layout::from_size_align(std::mem::size_of::<Rc4>().next_multiple_of(page), page)
+                .expect("page size is a power of two");
+        // SAFETY: размер layout ненулевой (не меньше страницы)
+        let raw = unsafe { alloc::alloc_zeroed(layout) } as *mut Rc4;
+        let Some(cipher) = NonNull::new(raw) else {
+            alloc::handle_alloc_error(layout);
+        };
+
+        let guard = match region::lock(raw as *const u8, layout.size()) {
+            Ok(guard) => Some(guard),
+            Err(e) => {
+                #[cfg(feature = "log")]
+                if !WARNED.swap(true, Ordering::Relaxed) {
+                    log::warn!(
+                        "cannot lock cipher state in memory ({}); it may be swapped to disk",
+                        e
+                    );
+                }
+                #[cfg(not(feature = "log"))]
+                let _ = e;
+                None
+            }
+        };
+
+        // Заглушка с несекретным ключом, затем настоящий KSA на месте
+        // SAFETY: raw выровнен и указывает на выделенную под Rc4 память
+        unsafe { raw.write(Rc4::new([0u8])) };
+        let mut locked = LockedRc4 {
+            cipher,
+            layout,
+            guard,
+        };
+        locked.rekey(key.as_ref())?;
+        Ok(locked)
+    }
+
+    /// Удалось ли закрепить страницу с состоянием.
+    pub fn is_locked(&self) -> bool {
+        self.guard.is_some()
+    }
+}
+
+impl Deref for LockedRc4 {
+    type Target = Rc4;
+
+    fn deref(&self) -> &Rc4 {
+        // SAFETY: Rc4 инициализирован в try_new и живет до drop
+        unsafe { self.cipher.as_ref() }
+    }
+}
+
+impl DerefMut for LockedRc4 {
+    fn deref_mut(&mut self) -> &mut Rc4 {
+        // SAFETY: как в deref; &mut self гарантирует единственность ссылки
+        unsafe { self.cipher.as_mut() }
+    }
+}
+
+impl Drop for LockedRc4 {
+    fn drop(&mut self) {
+        let base = self.cipher.as_ptr() as *mut u8;
+        // SAFETY: Rc4 удаляется ровно один раз, затем страница затирается
+        // байтами u8 и освобождается с тем же layout, с которым выделена
+        unsafe {
+            ptr::drop_in_place(self.cipher.as_ptr());
+            for offset in 0..self.layout.size() {
+                ptr::write_volatile(base.add(offset), 0);
+            }
+            atomic::compiler_fence(Ordering::SeqCst);
+            // Разблокируем только затертую страницу
+            self.guard = None;
+            alloc::dealloc(base, self.layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Закрепленный шифр дает ту же гамму и то же состояние, что Rc4
+    #[test]
+    fn test_matches_plain_rc4() {
+        let mut locked = LockedRc4::new(b"Key");
+        let mut plain = Rc4::new(b"Key");
+        assert!(locked.same_state(&plain));
+        assert_eq!(
+            locked.apply(b"Plaintext"),
+            [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+        );
+        plain.skip(9);
+        let mut data = vec![0u8; 1000];
+        locked.process(&mut data);
+        assert_eq!(data, plain.keystream_to_vec(1000));
+
+        assert_eq!(LockedRc4::try_new(b"").err(), Some(Rc4Error::EmptyKey));
+    }
+
+    /// При исчерпании лимита закрепления шифры продолжают работать без
+    /// блокировки. Если лимит не исчерпан и за 4096 экземпляров (16 МиБ),
+    /// проверка фолбэка пропускается с сообщением.
+    #[test]
+    fn test_fallback_when_limit_exhausted() {
+        let mut ciphers = Vec::new();
+        while ciphers.len() < 4096 {
+            let cipher = LockedRc4::new(b"Key");
+            let locked = cipher.is_locked();
+            ciphers.push(cipher);
+            if !locked {
+                break;
+            }
+        }
+        let last = ciphers.last_mut().unwrap();
+        if last.is_locked() {
+            eprintln!("skipping fallback check: memory lock limit was not reached");
+        }
+        assert!(last.same_state(&Rc4::new(b"Key")));
+        assert_eq!(
+            last.keystream_to_vec(16),
+            Rc4::new(b"Key").keystream_to_vec(16)
+        );
+    }
+
+    /// Удаление в любом порядке не ломает оставшиеся экземпляры
+    #[test]
+    fn test_drop_in_any_order() {
+        let mut ciphers: Vec<LockedRc4> = (0..8).map(|n| LockedRc4::new([n + 1])).collect();
+        for n in [3, 0, 5] {
+            drop(ciphers.remove(n));
+        }
+        for (cipher, key) in ciphers.iter().zip([2, 3, 5, 6, 7]) {
+            assert!(cipher.same_state(&Rc4::new([key])));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Защита от повторного использования пары (ключ, nonce). Требует feature
+//! `std` (включена по умолчанию): история хранится в `HashSet`.
+
+use std::collections::HashSet;
+
+use crate::sha256::Sha256;
+use crate::{Rc4, Rc4Error};
+
+/// Запоминает уже использованные пары (ключ, nonce) и отказывается
+/// создавать шифр для повторной пары: повтор дает ту же гамму, и XOR двух
+/// шифротекстов раскрывает XOR открытых текстов (классическая ошибка WEP).
+///
+/// Проверка действует только в пределах одного экземпляра в одном
+/// процессе: после перезапуска или в другом процессе история пуста. Это
+/// средство поймать ошибку при разработке, а не замена nonce-счетчику.
+///
+/// Пары сравниваются по ключу сеанса `nonce || key`, который
+/// `Rc4::new_with_nonce` подает в KSA: разные пары с одинаковой склейкой
+/// (например, ("ab", "c") и ("b", "ca")) дают одну гамму и считаются
+/// повтором. Сами ключи не хранятся — только SHA-256 ключа сеанса, поэтому
+/// память растет на 32 байта на каждое использование.
+#[derive(Default)]
+pub struct NonceGuard {
+    seen: HashSet<[u8; 32]>,
+}
+
+impl NonceGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Создает шифр `Rc4::new_with_nonce(key, iv)`, если пара еще не
+    /// использовалась, иначе возвращает `Rc4Error::NonceReused`. Пара
+    /// запоминается только при успешном создании шифра.
+    pub fn with_iv(&mut self, key: &[u8], iv: &[u8]) -> Result<Rc4, Rc4Error> {
+        let fingerprint = fingerprint(key, iv);
+        if self.seen.contains(&fingerprint) {
+            return Err(Rc4Error::NonceReused);
+        }
+        let cipher = Rc4::new_with_nonce(key, iv)?;
+        self.seen.insert(fingerprint);
+        Ok(cipher)
+    }
+
+    /// Число запомненных пар.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+/// SHA-256 ключа сеанса `iv || key` — ровно тех байтов, что идут в KSA.
+fn fingerprint(key: &[u8], iv: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(iv);
+    hasher.update(key);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Повторная пара отклоняется, новые nonce и ключи принимаются
+    #[test]
+    fn test_second_use_of_nonce_fails() {
+        let mut guard = NonceGuard::new();
+        assert!(guard.with_iv(b"Key", b"nonce-1").is_ok());
+        assert_eq!(
+            guard.with_iv(b"Key", b"nonce-1").err(),
+            Some(Rc4Error::NonceReused)
+        );
+
+        assert!(guard.with_iv(b"Key", b"nonce-2").is_ok());
+        assert!(guard.with_iv(b"Other key", b"nonce-1").is_ok());
+        assert!(guard.with_iv(b"Ke", b"ynonce-1").is_ok());
+        assert_eq!(guard.len(), 4);
+
+        // Другая пара с тем же ключом сеанса дает ту же гамму
+        let mut first = guard.with_iv(b"ab", b"c").unwrap();
+        assert_eq!(
+            first.keystream_to_vec(16),
+            Rc4::new_with_nonce(b"b", b"ca")
+                .unwrap()
+                .keystream_to_vec(16)
+        );
+        assert_eq!(
+            guard.with_iv(b"b", b"ca").err(),
+            Some(Rc4Error::NonceReused)
+        );
+        assert_eq!(guard.len(), 5);
+
+        // Неудачное создание шифра не занимает пару
+        assert!(guard.with_iv(b"", b"nonce-3").is_err());
+        assert_eq!(guard.len(), 5);
+    }
+}
@@ -0,0 +1,192 @@
+//! RC4-шифрование двоичных документов Office 97–2003 (.doc, .xls, .ppt),
+//! MS-OFFCRYPTO 2.3.6.
+//!
+//! Ключ выводится из пароля в UTF-16LE и 16-байтовой соли через MD5
+//! (2.3.6.2); поток делится на блоки, и для каждого блока RC4 заново
+//! инициализируется ключом с номером блока. Вариант CryptoAPI RC4 (2.3.5,
+//! SHA-1) здесь не поддерживается. Какие части потока зашифрованы (например,
+//! заголовок FIB в .doc или заголовки записей BIFF в .xls остаются открытыми),
+//! определяет вызывающий код.
+//!
+//! Office 97–2003 принимает пароль на открытие длиной от 1 до
+//! `MAX_PASSWORD_LEN` символов; с другими паролями документ зашифрован быть
+//! не может, поэтому функции модуля их отклоняют.
+
+use crate::md5::{md5, Md5};
+use crate::Rc4;
+
+/// Наибольшая длина пароля в символах UTF-16, которую допускает Office
+/// 97–2003.
+pub const MAX_PASSWORD_LEN: usize = 15;
+
+/// Промежуточный хеш пароля (2.3.6.2): первые 5 байт
+/// `MD5((MD5(пароль)[..5] || соль) × 16)`. От номера блока не зависит,
+/// поэтому вычисляется один раз на поток. `None` для пустого пароля и
+/// пароля длиннее `MAX_PASSWORD_LEN`.
+fn password_hash(password: &str, salt: &[u8; 16]) -> Option<[u8; 5]> {
+    let utf16: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    if utf16.is_empty() || utf16.len() > 2 * MAX_PASSWORD_LEN {
+        return None;
+    }
+    let h0 = md5(&utf16);
+
+    let mut hasher = Md5::new();
+    for _ in 0..16 {
+        hasher.update(&h0[..5]);
+        hasher.update(salt);
+    }
+    Some(hasher.finalize()[..5].try_into().unwrap())
+}
+
+fn key_for_block(hash: &[u8; 5], block: u32) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(hash);
+    hasher.update(&block.to_le_bytes());
+    hasher.finalize()
+}
+
+/// 128-битный ключ RC4 блока `block` для пароля и соли; `None`, если
+/// длина пароля вне 1..=`MAX_PASSWORD_LEN`.
+pub fn derive_key(password: &str, salt: &[u8; 16], block: u32) -> Option<[u8; 16]> {
+    password_hash(password, salt).map(|hash| key_for_block(&hash, block))
+}
+
+/// Проверяет пароль по полям `EncryptedVerifier` и `EncryptedVerifierHash`
+/// заголовка шифрования (2.3.6.3): оба расшифровываются одной гаммой
+/// ключа блока 0, и MD5 верификатора должен совпасть с его хешем. Пароль
+/// недопустимой длины не подходит никогда.
+pub fn verify_password(
+    salt: &[u8; 16],
+    verifier: &[u8; 16],
+    verifier_hash: &[u8; 16],
+    password: &str,
+) -> bool {
+    let Some(key) = derive_key(password, salt, 0) else {
+        return false;
+    };
+    let mut cipher = Rc4::new(key);
+    let verifier = cipher.apply(verifier);
+    let verifier_hash = cipher.apply(verifier_hash);
+    Rc4::constant_time_equals(&md5(&verifier), &verifier_hash)
+}
+
+/// Расшифровывает `data` от начала блока 0, заново инициализируя RC4 каждые
+/// `block_size` байт (512 для Word и PowerPoint, 1024 для Excel). Последний
+/// блок может быть неполным. RC4 симметричен, поэтому функция и шифрует.
+/// `None`, если длина пароля вне 1..=`MAX_PASSWORD_LEN`. Паникует при
+/// нулевом `block_size`.
+pub fn decrypt_stream(
+    password: &str,
+    salt: &[u8; 16],
+    data: &[u8],
+    block_size: usize,
+) -> Option<Vec<u8>> {
+    assert!(block_size > 0, "block size must be positive");
+    let hash = password_hash(password, salt)?;
+    let mut output = data.to_vec();
+    for (block, chunk) in output.chunks_mut(block_size).enumerate() {
+        Rc4::new(key_for_block(&hash, block as u32)).process(chunk);
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+    use std::fs;
+
+    const FIXTURES: &str = "tests/office";
+
+    /// Строка manifest.txt: пароль, соль, верификатор и его хеш.
+    struct Fixture {
+        password: String,
+        salt: [u8; 16],
+        verifier: [u8; 16],
+        verifier_hash: [u8; 16],
+    }
+
+    impl Fixture {
+        fn check(&self, password: &str) -> bool {
+            verify_password(&self.salt, &self.verifier, &self.verifier_hash, password)
+        }
+    }
+
+    fn load_manifest() -> Vec<Fixture> {
+        let text = fs::read_to_string(format!("{}/manifest.txt", FIXTURES))
+            .expect("tests/office is read relative to the crate root");
+        let field =
+            |hex_text: &str| -> [u8; 16] { hex::decode(hex_text).unwrap().try_into().unwrap() };
+        text.lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                assert_eq!(fields.len(), 4, "malformed manifest line: {}", line);
+                Fixture {
+                    password: fields[0].to_string(),
+                    salt: field(fields[1]),
+                    verifier: field(fields[2]),
+                    verifier_hash: field(fields[3]),
+                }
+            })
+            .collect()
+    }
+
+    /// Верные пароли из tests/office (см. generate.py) принимаются, а
+    /// неверные, в том числе отличающиеся регистром, — нет
+    #[test]
+    fn test_verify_password() {
+        let entries = load_manifest();
+        assert_eq!(entries.len(), 3);
+        for fixture in &entries {
+            assert!(fixture.check(&fixture.password), "{}", fixture.password);
+            for wrong in ["Password", "password ", "wrong", "Pässwörd-ω"] {
+                assert!(!fixture.check(wrong));
+            }
+        }
+    }
+
+    /// Поток расшифровывается с переинициализацией на границах блоков
+    #[test]
+    fn test_decrypt_stream_fixtures() {
+        let Fixture { password, salt, .. } = &load_manifest()[0];
+        let message: Vec<u8> = (0..2600).map(|i| (i % 251) as u8).collect();
+        for block_size in [512, 1024] {
+            let encrypted = fs::read(format!("{}/stream-{}.bin", FIXTURES, block_size)).unwrap();
+            let decrypt = |password| decrypt_stream(password, salt, &encrypted, block_size);
+            assert!(decrypt(password).unwrap() == message);
+            assert!(decrypt("wrong").unwrap() != message);
+        }
+
+        // Второй блок начинается с новой гаммы, а не продолжает первую
+        let encrypted = fs::read(format!("{}/stream-512.bin", FIXTURES)).unwrap();
+        let mut continuous = Rc4::new(derive_key(password, salt, 0).unwrap());
+        assert!(continuous.apply(&encrypted[..1024])[512..] != message[512..1024]);
+        let mut second = Rc4::new(derive_key(password, salt, 1).unwrap());
+        assert!(second.apply(&encrypted[512..1024]) == message[512..1024]);
+    }
+
+    /// Пустой пароль и пароль длиннее 15 символов UTF-16 отклоняются;
+    /// 15 символов (стандартный пароль Excel VelvetSweatshop) допустимы
+    #[test]
+    fn test_password_length_limit() {
+        let salt = [0; 16];
+        assert!(derive_key("VelvetSweatshop", &salt, 0).is_some());
+        assert!(derive_key("ПятнадцатьБукв!", &salt, 0).is_some());
+        for bad in ["", "VelvetSweatshop!", "ШестнадцатьБукв!"] {
+            assert_eq!(derive_key(bad, &salt, 0), None, "{}", bad);
+            assert_eq!(decrypt_stream(bad, &salt, b"data", 512), None);
+            assert!(!verify_password(&salt, &[0; 16], &[0; 16], bad));
+        }
+        // Символ вне BMP занимает два кода UTF-16
+        assert!(derive_key(&"😀".repeat(7), &salt, 0).is_some());
+        assert!(derive_key(&"😀".repeat(8), &salt, 0).is_none());
+    }
+
+    /// Нулевой размер блока отклоняется
+    #[test]
+    #[should_panic(expected = "block size must be positive")]
+    fn test_zero_block_size_panics() {
+        decrypt_stream("password", &[0; 16], b"data", 0);
+    }
+}
@@ -0,0 +1,293 @@
+//! Стандартный обработчик безопасности PDF для ревизий 2 и 3 (RC4),
+//! ISO 32000-1, раздел 7.6.3.
+//!
+//! Ключ файла выводится из пароля пользователя по алгоритму 2, ключ объекта —
+//! по алгоритму 1; строки и потоки объекта шифруются RC4 с ключом объекта,
+//! каждая строка — с начала гаммы. Ревизия 4 (криптофильтры) и AES здесь не
+//! поддерживаются.
+
+use std::fmt;
+
+use crate::md5::{md5, Md5};
+use crate::Rc4;
+
+/// Строка дополнения пароля до 32 байт (алгоритм 2, шаг a).
+pub const PADDING: [u8; 32] = [
+    0x28, 0xbf, 0x4e, 0x5e, 0x4e, 0x75, 0x8a, 0x41, 0x64, 0x00, 0x4e, 0x56, 0xff, 0xfa, 0x01, 0x08,
+    0x2e, 0x2e, 0x00, 0xb6, 0xd0, 0x68, 0x3e, 0x80, 0x2f, 0x0c, 0xa9, 0xfe, 0x64, 0x53, 0x69, 0x7a,
+];
+
+/// Ошибка параметров словаря `/Encrypt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdfError {
+    /// Ревизия `/R`, отличная от 2 и 3.
+    UnsupportedRevision(u32),
+    /// Длина ключа в байтах недопустима для ревизии: 5 для R2, 5–16 для R3.
+    InvalidKeyLength { revision: u32, len: usize },
+    /// Запись `/O` не 32 байта (в поле — фактическая длина).
+    InvalidOEntry(usize),
+}
+
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdfError::UnsupportedRevision(r) => {
+                write!(f, "unsupported security handler revision {}", r)
+            }
+            PdfError::InvalidKeyLength { revision, len } => write!(
+                f,
+                "key length of {} bytes is not valid for revision {}",
+                len, revision
+            ),
+            PdfError::InvalidOEntry(len) => {
+                write!(f, "/O entry is {} bytes, expected 32", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PdfError {}
+
+fn check_params(revision: u32, key_len: usize) -> Result<(), PdfError> {
+    let valid = match revision {
+        2 => key_len == 5,
+        3 => (5..=16).contains(&key_len),
+        _ => return Err(PdfError::UnsupportedRevision(revision)),
+    };
+    if !valid {
+        return Err(PdfError::InvalidKeyLength {
+            revision,
+            len: key_len,
+        });
+    }
+    Ok(())
+}
+
+/// Пароль, усеченный или дополненный `PADDING` до 32 байт.
+fn pad_password(password: &[u8]) -> [u8; 32] {
+    let mut padded = PADDING;
+    let len = password.len().min(32);
+    padded[..len].copy_from_slice(&password[..len]);
+    padded[len..].copy_from_slice(&PADDING[..32 - len]);
+    padded
+}
+
+/// Ключ файла из пароля пользователя (алгоритм 2).
+///
+/// `o_entry` — 32 байта `/O`, `p_flags` — значение `/P` (знаковое, как в
+/// файле), `id0` — первый элемент `/ID` трейлера, `key_len` — длина ключа в
+/// байтах: 5 (40 бит) для R2, `/Length / 8` для R3. В ревизии 3 хеш
+/// дополнительно пересчитывается 50 раз над первыми `key_len` байтами.
+///
+/// Неверный пароль не обнаруживается здесь: ключ выводится из любого
+/// пароля, проверить его можно сравнением `compute_u_entry` с `/U`.
+pub fn derive_file_key(
+    password: &[u8],
+    o_entry: &[u8],
+    p_flags: i32,
+    id0: &[u8],
+    revision: u32,
+    key_len: usize,
+) -> Result<Vec<u8>, PdfError> {
+    check_params(revision, key_len)?;
+    if o_entry.len() != 32 {
+        return Err(PdfError::InvalidOEntry(o_entry.len()));
+    }
+
+    let mut hasher = Md5::new();
+    hasher.update(&pad_password(password));
+    hasher.update(o_entry);
+    hasher.update(&(p_flags as u32).to_le_bytes());
+    hasher.update(id0);
+    let mut digest = hasher.finalize();
+    if revision >= 3 {
+        for _ in 0..50 {
+            digest = md5(&digest[..key_len]);
+        }
+    }
+    Ok(digest[..key_len].to_vec())
+}
+
+/// Ключ объекта `obj_num gen_num R` (алгоритм 1): MD5 от ключа файла,
+/// трех младших байт номера и двух байт поколения (little-endian),
+/// усеченный до `min(len + 5, 16)` байт.
+pub fn object_key(file_key: &[u8], obj_num: u32, gen_num: u16) -> Vec<u8> {
+    let mut hasher = Md5::new();
+    hasher.update(file_key);
+    hasher.update(&obj_num.to_le_bytes()[..3]);
+    hasher.update(&gen_num.to_le_bytes());
+    let digest = hasher.finalize();
+    digest[..(file_key.len() + 5).min(16)].to_vec()
+}
+
+/// Расшифровывает строку или поток объекта `obj gen R`. RC4 симметричен,
+/// поэтому та же функция и шифрует. Каждая строка объекта расшифровывается
+/// отдельным вызовом: гамма для нее начинается заново.
+pub fn decrypt_object(file_key: &[u8], obj: u32, gen: u16, data: &[u8]) -> Vec<u8> {
+    Rc4::new(object_key(file_key, obj, gen)).apply(data)
+}
+
+/// Ожидаемое значение `/U` для ключа файла (алгоритмы 4 и 5). Для R2 это
+/// все 32 байта записи; для R3 — 16 байт, с которыми сравнивается начало
+/// `/U` (остальные 16 байт в файле произвольны). Совпадение означает, что
+/// ключ выведен из верного пароля пользователя.
+pub fn compute_u_entry(file_key: &[u8], id0: &[u8], revision: u32) -> Result<Vec<u8>, PdfError> {
+    check_params(revision, file_key.len())?;
+    if revision == 2 {
+        return Ok(Rc4::new(file_key).apply(PADDING));
+    }
+
+    let mut hasher = Md5::new();
+    hasher.update(&PADDING);
+    hasher.update(id0);
+    let mut u = hasher.finalize();
+    Rc4::new(file_key).process(&mut u);
+    let mut round_key = file_key.to_vec();
+    for round in 1..=19u8 {
+        for (k, f) in round_key.iter_mut().zip(file_key) {
+            *k = f ^ round;
+        }
+        Rc4::new(&round_key).process(&mut u);
+    }
+    Ok(u.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+    use std::fs;
+
+    /// Значение после `name` в словаре: hex-строка `<...>` (у массива —
+    /// первая) или число.
+    fn entry(pdf: &[u8], name: &str) -> String {
+        let text = String::from_utf8_lossy(pdf);
+        let start = text
+            .find(&format!("{} ", name))
+            .unwrap_or_else(|| panic!("{} not found", name))
+            + name.len()
+            + 1;
+        let rest = text[start..].trim_start_matches('[');
+        if let Some(hex) = rest.strip_prefix('<') {
+            hex[..hex.find('>').unwrap()].to_string()
+        } else {
+            let end = rest.find([' ', '>', '/']).unwrap();
+            rest[..end].to_string()
+        }
+    }
+
+    /// Тело потока объекта `num 0 obj` длиной `/Length` (qpdf не ставит
+    /// перевод строки перед `endstream`).
+    fn stream(pdf: &[u8], num: u32) -> &[u8] {
+        let find = |from: usize, needle: &[u8]| {
+            from + pdf[from..]
+                .windows(needle.len())
+                .position(|w| w == needle)
+                .unwrap()
+        };
+        let obj = find(0, format!("\n{} 0 obj\n", num).as_bytes());
+        let len: usize = entry(&pdf[obj..], "/Length").parse().unwrap();
+        let start = find(obj, b"stream\n") + b"stream\n".len();
+        &pdf[start..start + len]
+    }
+
+    /// Файлы из tests/pdf, зашифрованные qpdf (см. README.md): ключ из
+    /// пароля "user" совпадает с ключом, который сообщает qpdf, и
+    /// подтверждается записью /U, поток и строки расшифровываются
+    #[test]
+    fn test_fixture_files() {
+        for (name, revision, key_len, expected_key) in [
+            ("r2-40.pdf", 2, 5, "ee4fd3a223"),
+            ("r3-128.pdf", 3, 16, "55831911f0f2b9a08ac3cbcc766166db"),
+        ] {
+            let pdf = fs::read(format!("tests/pdf/{}", name))
+                .expect("tests/pdf is read relative to the crate root");
+            assert_eq!(entry(&pdf, "/R"), revision.to_string());
+            let o = hex::decode(&entry(&pdf, "/O")).unwrap();
+            let u = hex::decode(&entry(&pdf, "/U")).unwrap();
+            let p: i32 = entry(&pdf, "/P").parse().unwrap();
+            let id0 = hex::decode(&entry(&pdf, "/ID")).unwrap();
+
+            let key = derive_file_key(b"user", &o, p, &id0, revision, key_len).unwrap();
+            assert_eq!(hex::encode(&key), expected_key, "{}", name);
+            let expected_u = compute_u_entry(&key, &id0, revision).unwrap();
+            assert_eq!(u[..expected_u.len()], expected_u[..], "{}", name);
+
+            let wrong = derive_file_key(b"owner", &o, p, &id0, revision, key_len).unwrap();
+            let wrong_u = compute_u_entry(&wrong, &id0, revision).unwrap();
+            assert_ne!(u[..wrong_u.len()], wrong_u[..]);
+
+            assert_eq!(
+                decrypt_object(&key, 5, 0, stream(&pdf, 5)),
+                b"BT /F1 12 Tf 20 50 Td (Hello from an RC4 encrypted PDF) Tj ET"
+            );
+            let title = hex::decode(&entry(&pdf, "/Title")).unwrap();
+            assert_eq!(decrypt_object(&key, 2, 0, &title), b"Tiny RC4 fixture");
+            let producer = hex::decode(&entry(&pdf, "/Producer")).unwrap();
+            assert_eq!(
+                decrypt_object(&key, 2, 0, &producer),
+                b"hand-written source for qpdf"
+            );
+        }
+    }
+
+    /// Длина ключа объекта — n + 5 байт, но не больше 16; номер и
+    /// поколение меняют ключ
+    #[test]
+    fn test_object_key() {
+        assert_eq!(object_key(&[1; 5], 4, 0).len(), 10);
+        assert_eq!(object_key(&[1; 11], 4, 0).len(), 16);
+        assert_eq!(object_key(&[1; 16], 4, 0).len(), 16);
+        assert_ne!(object_key(&[1; 5], 4, 0), object_key(&[1; 5], 5, 0));
+        assert_ne!(object_key(&[1; 5], 4, 0), object_key(&[1; 5], 4, 1));
+        // Учитываются только три младших байта номера
+        assert_eq!(
+            object_key(&[1; 5], 4, 0),
+            object_key(&[1; 5], 0x0100_0004, 0)
+        );
+    }
+
+    /// Пароль длиннее 32 байт усекается, короткий дополняется
+    #[test]
+    fn test_pad_password() {
+        assert_eq!(pad_password(b""), PADDING);
+        assert_eq!(&pad_password(b"user")[..4], b"user");
+        assert_eq!(pad_password(b"user")[4..], PADDING[..28]);
+        assert_eq!(pad_password(&[b'x'; 40]), [b'x'; 32]);
+    }
+
+    /// Недопустимые параметры словаря отклоняются
+    #[test]
+    fn test_invalid_parameters() {
+        let o = [0u8; 32];
+        assert_eq!(
+            derive_file_key(b"", &o, -44, b"id", 4, 16),
+            Err(PdfError::UnsupportedRevision(4))
+        );
+        assert_eq!(
+            derive_file_key(b"", &o, -44, b"id", 2, 16),
+            Err(PdfError::InvalidKeyLength {
+                revision: 2,
+                len: 16
+            })
+        );
+        assert_eq!(
+            derive_file_key(b"", &o, -44, b"id", 3, 17),
+            Err(PdfError::InvalidKeyLength {
+                revision: 3,
+                len: 17
+            })
+        );
+        assert_eq!(
+            derive_file_key(b"", &o[..31], -44, b"id", 3, 16),
+            Err(PdfError::InvalidOEntry(31))
+        );
+        assert_eq!(
+            compute_u_entry(&[0; 4], b"id", 3),
+            Err(PdfError::InvalidKeyLength {
+                revision: 3,
+                len: 4
+            })
+        );
+    }
+}
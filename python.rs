@@ -0,0 +1,100 @@
+//! Python-привязки через pyo3 (feature `python`).
+//!
+//! Модуль собирается как расширение Python с помощью maturin: библиотечная
+//! цель имеет `crate-type = ["rlib", "cdylib"]` (Cargo.toml), а
+//! `pyproject.toml` выбирает `build-backend = "maturin"` и включает feature
+//! `python`:
+//!
+//! ```text
+//! pip install maturin
+//! maturin develop --release
+//! python -c "import rc4; print(rc4.Rc4(b'Key').process(b'Plaintext').hex())"
+//! ```
+//!
+//! Обертка тонкая: вся логика шифра остается в `Rc4`.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::Rc4;
+
+/// Класс `rc4.Rc4` на стороне Python.
+#[pyclass(name = "Rc4", module = "rc4")]
+pub struct PyRc4 {
+    inner: Rc4,
+}
+
+// Методы без зависимостей от интерпретатора: их можно тестировать из Rust
+impl PyRc4 {
+    fn try_new(key: &[u8]) -> Result<Self, crate::Rc4Error> {
+        Ok(PyRc4 {
+            inner: Rc4::try_new(key)?,
+        })
+    }
+
+    fn process_vec(&mut self, data: &[u8]) -> Vec<u8> {
+        self.inner.apply(data)
+    }
+
+    fn keystream_vec(&mut self, n: usize) -> Vec<u8> {
+        self.inner.keystream_to_vec(n)
+    }
+}
+
+#[pymethods]
+impl PyRc4 {
+    #[new]
+    fn py_new(key: &[u8]) -> PyResult<Self> {
+        Self::try_new(key).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// process(data: bytes) -> bytes
+    ///
+    /// Шифрует или расшифровывает данные, продвигая состояние шифра.
+    fn process<'py>(&mut self, py: Python<'py>, data: &[u8]) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.process_vec(data))
+    }
+
+    /// keystream(n: int) -> bytes
+    ///
+    /// Возвращает следующие `n` байт гаммы.
+    fn keystream<'py>(&mut self, py: Python<'py>, n: usize) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.keystream_vec(n))
+    }
+}
+
+#[pymodule]
+fn rc4(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRc4>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Обертка выдает известные тестовые векторы
+    #[test]
+    fn test_wrapper_known_vectors() {
+        let mut key = PyRc4::try_new(b"Key").unwrap();
+        assert_eq!(
+            key.process_vec(b"Plaintext"),
+            [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]
+        );
+
+        let mut wiki = PyRc4::try_new(b"Wiki").unwrap();
+        let keystream = wiki.keystream_vec(5);
+        let ciphertext: Vec<u8> = b"pedia"
+            .iter()
+            .zip(&keystream)
+            .map(|(p, k)| p ^ k)
+            .collect();
+        assert_eq!(ciphertext, [0x10, 0x21, 0xBF, 0x04, 0x20]);
+    }
+
+    /// Недопустимый ключ отклоняется до создания объекта Python
+    #[test]
+    fn test_wrapper_rejects_empty_key() {
+        assert!(PyRc4::try_new(b"").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! `Rc4Ratchet` — RC4 с автоматической сменой ключа через SHA-256.
+
+use crate::sha256::Sha256;
+use crate::Rc4;
+
+/// Шифр, который каждые `interval` байт заменяет ключ на SHA-256 от текущего
+/// состояния (S-box, затем `i` и `j`) и заново выполняет KSA на месте.
+///
+/// Смена ключа односторонняя: из состояния после нее нельзя восстановить
+/// гамму предыдущих интервалов, поэтому утечка текущего состояния не
+/// раскрывает уже переданные данные. Цена — KSA (около 256 перестановок)
+/// на каждые `interval` байт.
+///
+/// Граница интервала определяется только числом обработанных байт, поэтому
+/// стороны с одинаковыми ключом и интервалом остаются синхронными при любом
+/// разбиении данных на порции. Первые `interval` байт гаммы совпадают с
+/// `Rc4::new(key)`.
+#[derive(Clone)]
+pub struct Rc4Ratchet {
+    cipher: Rc4,
+    interval: usize,
+    until_ratchet: usize,
+    ratchets: u64,
+}
+
+impl Rc4Ratchet {
+    /// Паникует при недопустимой длине ключа (как `Rc4::new`) и при
+    /// нулевом `interval`.
+    pub fn new(key: impl AsRef<[u8]>, interval: usize) -> Self {
+        assert!(interval > 0, "ratchet interval must be positive");
+        Rc4Ratchet {
+            cipher: Rc4::new(key),
+            interval,
+            until_ratchet: interval,
+            ratchets: 0,
+        }
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Сколько раз ключ уже сменился.
+    pub fn ratchets(&self) -> u64 {
+        self.ratchets
+    }
+
+    /// Шифрует/расшифровывает `data` на месте, меняя ключ на каждой
+    /// границе интервала внутри буфера.
+    #[inline]
+    pub fn process<T: AsMut<[u8]> + ?Sized>(&mut self, data: &mut T) {
+        self.process_slice(data.as_mut());
+    }
+
+    fn process_slice(&mut self, mut data: &mut [u8]) {
+        while !data.is_empty() {
+            let n = data.len().min(self.until_ratchet);
+            let (now, rest) = std::mem::take(&mut data).split_at_mut(n);
+            self.cipher.process(now);
+            self.until_ratchet -= n;
+            if self.until_ratchet == 0 {
+                self.ratchet();
+            }
+            data = rest;
+        }
+    }
+
+    pub fn apply(&mut self, data: impl AsRef<[u8]>) -> Vec<u8> {
+        let mut output = data.as_ref().to_vec();
+        self.process_slice(&mut output);
+        output
+    }
+
+    pub fn keystream_to_vec(&mut self, len: usize) -> Vec<u8> {
+        let mut keystream = vec![0u8; len];
+        self.process_slice(&mut keystream);
+        keystream
+    }
+
+    fn ratchet(&mut self) {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.cipher.s);
+        hasher.update(&[self.cipher.i, self.cipher.j]);
+        let key = hasher.finalize();
+        self.cipher
+            .rekey(&key)
+            .expect("a 32-byte key is always valid");
+        self.until_ratchet = self.interval;
+        self.ratchets += 1;
+    }
+}
+
+impl Rc4 {
+    /// Шифр с автоматической сменой ключа каждые `ratchet_interval` байт;
+    /// см. `Rc4Ratchet`.
+    pub fn new_ratchet(key: &[u8], ratchet_interval: usize) -> Rc4Ratchet {
+        Rc4Ratchet::new(key, ratchet_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// До первой границы гамма совпадает с Rc4, после нее — отличается
+    #[test]
+    fn test_ratchet_changes_keystream() {
+        let mut ratchet = Rc4::new_ratchet(b"Key", 100);
+        let mut plain = Rc4::new(b"Key");
+
+        assert_eq!(ratchet.keystream_to_vec(100), plain.keystream_to_vec(100));
+        assert_eq!(ratchet.ratchets(), 1);
+        assert_ne!(ratchet.keystream_to_vec(100), plain.keystream_to_vec(100));
+        assert_eq!(ratchet.ratchets(), 2);
+    }
+
+    /// Стороны с одинаковыми параметрами синхронны при разном разбиении
+    #[test]
+    fn test_ratchet_peers_stay_in_sync() {
+        let message: Vec<u8> = (0..5000u32).map(|n| (n * 31 % 251) as u8).collect();
+        let mut sender = Rc4Ratchet::new(b"shared", 64);
+        let ciphertext = sender.apply(&message);
+
+        for chunk in [1, 63, 64, 65, 1000, 5000] {
+            let mut receiver = Rc4Ratchet::new(b"shared", 64);
+            let mut decrypted = ciphertext.clone();
+            for part in decrypted.chunks_mut(chunk) {
+                receiver.process(part);
+            }
+            assert!(decrypted == message, "chunk {}", chunk);
+            assert_eq!(receiver.ratchets(), sender.ratchets());
+        }
+        assert_eq!(sender.ratchets(), 5000 / 64);
+
+        let mut other = Rc4Ratchet::new(b"shared", 65);
+        assert!(other.apply(&ciphertext) != message);
+    }
+
+    /// Нулевой интервал отклоняется при создании
+    #[test]
+    #[should_panic(expected = "ratchet interval must be positive")]
+    fn test_zero_interval_panics() {
+        Rc4Ratchet::new(b"Key", 0);
+    }
+}
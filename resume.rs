@@ -0,0 +1,362 @@
+//! Возобновляемая обработка больших файлов.
+//!
+//! Результат пишется во временный файл `.<имя>.partial` (как в атомарном
+//! режиме CLI), а рядом с целевым файлом каждые `checkpoint_every` байт
+//! обновляется служебный файл `<выход>.progress`:
+//!
+//! ```text
+//! rc4-progress v1
+//! key-sha256 <64 hex>
+//! source-size <байт>
+//! source-mtime <наносекунды от UNIX_EPOCH>
+//! offset <байт>
+//! ```
+//!
+//! Состояние шифра в служебный файл не записывается: S-box эквивалентен
+//! ключу. При продолжении шифр восстанавливается через `skip(offset)`.
+//! Отпечаток ключа и размер/время изменения источника проверяются, поэтому
+//! продолжение с другим ключом или измененным источником завершается ошибкой.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::cli::{already_exists, partial_path, WriteOptions};
+use crate::sha256::sha256;
+use crate::{hex, Rc4};
+
+const HEADER: &str = "rc4-progress v1";
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Содержимое служебного файла прогресса.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub key_fingerprint: [u8; 32],
+    pub source_size: u64,
+    pub source_mtime_nanos: u128,
+    pub offset: u64,
+}
+
+impl Progress {
+    pub fn to_text(&self) -> String {
+        let fingerprint = hex::encode(&self.key_fingerprint);
+        format!(
+            "{}\nkey-sha256 {}\nsource-size {}\nsource-mtime {}\noffset {}\n",
+            HEADER, fingerprint, self.source_size, self.source_mtime_nanos, self.offset
+        )
+    }
+
+    pub fn parse(text: &str) -> io::Result<Progress> {
+        let invalid = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid progress file: {}", what),
+            )
+        };
+
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("unknown header"));
+        }
+
+        let mut field = |name: &str| -> io::Result<&str> {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(name))
+                .and_then(|rest| rest.strip_prefix(' '))
+                .ok_or_else(|| invalid(name))
+        };
+
+        let key_fingerprint: [u8; 32] = hex::decode(field("key-sha256")?)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| invalid("key-sha256"))?;
+
+        Ok(Progress {
+            key_fingerprint,
+            source_size: field("source-size")?
+                .parse()
+                .map_err(|_| invalid("source-size"))?,
+            source_mtime_nanos: field("source-mtime")?
+                .parse()
+                .map_err(|_| invalid("source-mtime"))?,
+            offset: field("offset")?.parse().map_err(|_| invalid("offset"))?,
+        })
+    }
+}
+
+/// Путь служебного файла прогресса для `output`.
+pub fn progress_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".progress");
+    PathBuf::from(name)
+}
+
+/// Шифрует `input` в `output` с контрольными точками, продолжая с последней
+/// из них, если для `output` уже есть служебный файл прогресса.
+///
+/// После каждой контрольной точки вызывается `on_checkpoint(offset)`;
+/// `ControlFlow::Break` останавливает обработку так же, как прерывание
+/// процесса, — временный и служебный файлы остаются для продолжения, а
+/// функция возвращает ошибку `Interrupted`. Ошибки ввода-вывода тоже
+/// оставляют их на месте. Возвращает размер обработанного источника.
+pub fn process_resumable(
+    input: &Path,
+    output: &Path,
+    key: &[u8],
+    write: WriteOptions,
+    mut on_checkpoint: impl FnMut(u64) -> ControlFlow<()>,
+) -> io::Result<u64> {
+    let mut cipher =
+        Rc4::try_new(key).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut source = File::open(input)?;
+    let metadata = source.metadata()?;
+    let source_mtime_nanos = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+
+    if !write.force && output.exists() {
+        return Err(already_exists(output));
+    }
+
+    let temp = partial_path(output)?;
+    let sidecar = progress_path(output);
+    let mut progress = Progress {
+        key_fingerprint: sha256(key),
+        source_size: metadata.len(),
+        source_mtime_nanos,
+        offset: 0,
+    };
+
+    let mut destination = match fs::read_to_string(&sidecar) {
+        Ok(text) => {
+            let saved = Progress::parse(&text)?;
+            validate(&saved, &progress, &sidecar)?;
+
+            let mut destination = OpenOptions::new().write(true).open(&temp)?;
+            if destination.metadata()?.len() < saved.offset {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is shorter than the recorded offset", temp.display()),
+                ));
+            }
+            // Байты после последней контрольной точки могли не дойти до диска
+            destination.set_len(saved.offset)?;
+            destination.seek(SeekFrom::Start(saved.offset))?;
+            source.seek(SeekFrom::Start(saved.offset))?;
+            skip(&mut cipher, saved.offset);
+            progress.offset = saved.offset;
+            destination
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => File::create(&temp)?,
+        Err(e) => return Err(e),
+    };
+
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut since_checkpoint = 0u64;
+    loop {
+        let n = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        cipher.process(&mut buffer[..n]);
+        destination.write_all(&buffer[..n])?;
+        progress.offset += n as u64;
+        since_checkpoint += n as u64;
+
+        if since_checkpoint >= write.checkpoint_every {
+            since_checkpoint = 0;
+            destination.sync_data()?;
+            save(&progress, &sidecar)?;
+            if on_checkpoint(progress.offset).is_break() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    format!(
+                        "stopped at offset {}; rerun with --resume to continue",
+                        progress.offset
+                    ),
+                ));
+            }
+        }
+    }
+
+    destination.sync_all()?;
+    fs::rename(&temp, output)?;
+    fs::remove_file(&sidecar).or_else(|e| match e.kind() {
+        io::ErrorKind::NotFound => Ok(()),
+        _ => Err(e),
+    })?;
+    Ok(progress.offset)
+}
+
+fn validate(saved: &Progress, current: &Progress, sidecar: &Path) -> io::Result<()> {
+    let mismatch = |what: &str| {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("cannot resume from {}: {}", sidecar.display(), what),
+        ))
+    };
+
+    if saved.key_fingerprint != current.key_fingerprint {
+        return mismatch("it was created with a different key");
+    }
+    if saved.source_size != current.source_size
+        || saved.source_mtime_nanos != current.source_mtime_nanos
+    {
+        return mismatch("the source file has changed");
+    }
+    if saved.offset > current.source_size {
+        return mismatch("the recorded offset is past the end of the source");
+    }
+    Ok(())
+}
+
+fn save(progress: &Progress, sidecar: &Path) -> io::Result<()> {
+    // Запись через временный файл: служебный файл всегда либо старый, либо новый
+    let mut temp = sidecar.as_os_str().to_os_string();
+    temp.push(".tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(progress.to_text().as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, sidecar)
+}
+
+fn skip(cipher: &mut Rc4, mut n: u64) {
+    while n > 0 {
+        let step = n.min(usize::MAX as u64) as usize;
+        cipher.skip(step);
+        n -= step as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::run;
+    use crate::cli::tests::{args, TempDir};
+
+    fn resumable(checkpoint_every: u64) -> WriteOptions {
+        WriteOptions {
+            resume: true,
+            checkpoint_every,
+            ..WriteOptions::default()
+        }
+    }
+
+    fn source(dir: &TempDir) -> (PathBuf, Vec<u8>) {
+        let data = Rc4::new(b"source").keystream_to_vec(1_000_003);
+        let path = dir.path("disk.img");
+        fs::write(&path, &data).unwrap();
+        (path, data)
+    }
+
+    /// Остановка на контрольной точке и продолжение дают тот же результат
+    #[test]
+    fn test_resume_matches_fresh_run() {
+        let dir = TempDir::new("resume");
+        let (input, data) = source(&dir);
+        let output = dir.path("disk.img.rc4");
+        let expected = Rc4::new(b"Key").apply(&data);
+
+        for stop_after in [1, 3, 7] {
+            let mut checkpoints = 0;
+            let err = process_resumable(&input, &output, b"Key", resumable(100_000), |_| {
+                checkpoints += 1;
+                if checkpoints == stop_after {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+            assert!(!output.exists());
+            assert!(progress_path(&output).exists());
+
+            // Хвост после контрольной точки, который должен быть отброшен
+            let temp = partial_path(&output).unwrap();
+            let mut partial = OpenOptions::new().append(true).open(&temp).unwrap();
+            partial.write_all(b"garbage past the checkpoint").unwrap();
+
+            let total = process_resumable(&input, &output, b"Key", resumable(100_000), |_| {
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+            assert_eq!(total, data.len() as u64);
+            assert_eq!(fs::read(&output).unwrap(), expected);
+            assert!(!progress_path(&output).exists());
+            assert!(!temp.exists());
+            fs::remove_file(&output).unwrap();
+        }
+    }
+
+    /// Продолжение с другим ключом или измененным источником — ошибка
+    #[test]
+    fn test_resume_rejects_wrong_key_and_changed_source() {
+        let dir = TempDir::new("resume-reject");
+        let (input, data) = source(&dir);
+        let output = dir.path("disk.img.rc4");
+
+        let stop = |_| ControlFlow::Break(());
+        assert!(process_resumable(&input, &output, b"Key", resumable(100_000), stop).is_err());
+
+        let err = process_resumable(&input, &output, b"Other", resumable(100_000), |_| {
+            ControlFlow::Continue(())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("different key"), "{}", err);
+
+        fs::write(&input, &data[..data.len() - 1]).unwrap();
+        let err = process_resumable(&input, &output, b"Key", resumable(100_000), |_| {
+            ControlFlow::Continue(())
+        })
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("source file has changed"),
+            "{}",
+            err
+        );
+        assert!(!output.exists());
+    }
+
+    /// Служебный файл разбирается обратно без потерь
+    #[test]
+    fn test_progress_text_roundtrip() {
+        let progress = Progress {
+            key_fingerprint: sha256(b"Key"),
+            source_size: 2 << 40,
+            source_mtime_nanos: 1_700_000_000_123_456_789,
+            offset: 12345,
+        };
+        assert_eq!(Progress::parse(&progress.to_text()).unwrap(), progress);
+        assert!(Progress::parse("rc4-progress v2\n").is_err());
+    }
+
+    /// Флаг --resume в CLI: полный прогон без прерываний
+    #[test]
+    fn test_cli_resume_flag() {
+        let dir = TempDir::new("resume-cli");
+        let (input, data) = source(&dir);
+
+        let code = run(&args(&[
+            "encrypt",
+            "--key",
+            "Key",
+            "--resume",
+            "--checkpoint-mib",
+            "1",
+            &input.to_string_lossy(),
+        ]));
+        assert_eq!(code, 0);
+        assert_eq!(
+            fs::read(dir.path("disk.img.rc4")).unwrap(),
+            Rc4::new(b"Key").apply(&data)
+        );
+        assert!(!dir.path("disk.img.rc4.progress").exists());
+    }
+}
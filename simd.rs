@@ -0,0 +1,116 @@
+//! Векторный путь `xor_in_place` (feature `simd`).
+//!
+//! На x86_64 используется AVX2, если процессор его поддерживает (проверка во
+//! время выполнения), иначе SSE2; на aarch64 — NEON. SSE2 и NEON входят в
+//! базовый набор своих архитектур и не требуют проверки. На остальных
+//! архитектурах векторного пути нет, и `xor_in_place` обходится словами
+//! по 8 байт.
+
+/// Длина, начиная с которой `xor_in_place` вызывает векторный путь.
+pub(crate) const MIN_LEN: usize = 256;
+
+/// XOR-ит наибольшее начало буферов, кратное ширине вектора, и возвращает
+/// его длину; остаток обрабатывает вызывающий. Длины буферов равны.
+pub(crate) fn xor_prefix(data: &mut [u8], keystream: &[u8]) -> usize {
+    debug_assert_eq!(data.len(), keystream.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::arch::is_x86_feature_detected!("avx2") {
+            // SAFETY: поддержка AVX2 проверена выше
+            return unsafe { x86::xor_avx2(data, keystream) };
+        }
+        // SAFETY: SSE2 есть на любом x86_64
+        unsafe { x86::xor_sse2(data, keystream) }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON есть на любом aarch64
+        unsafe { neon::xor_neon(data, keystream) }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let _ = (data, keystream);
+        0
+    }
+}
+
+// Загрузки и сохранения невыровненные (`loadu`/`storeu`, `vld1q`/`vst1q`),
+// поэтому срезы с любым выравниванием допустимы. Смещения не выходят за
+// `len`, а `len` не больше длины обоих буферов.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn xor_avx2(data: &mut [u8], keystream: &[u8]) -> usize {
+        let len = data.len() - data.len() % 32;
+        for offset in (0..len).step_by(32) {
+            let d = data.as_mut_ptr().add(offset) as *mut __m256i;
+            let k = keystream.as_ptr().add(offset) as *const __m256i;
+            _mm256_storeu_si256(
+                d,
+                _mm256_xor_si256(_mm256_loadu_si256(d), _mm256_loadu_si256(k)),
+            );
+        }
+        len
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn xor_sse2(data: &mut [u8], keystream: &[u8]) -> usize {
+        let len = data.len() - data.len() % 16;
+        for offset in (0..len).step_by(16) {
+            let d = data.as_mut_ptr().add(offset) as *mut __m128i;
+            let k = keystream.as_ptr().add(offset) as *const __m128i;
+            _mm_storeu_si128(d, _mm_xor_si128(_mm_loadu_si128(d), _mm_loadu_si128(k)));
+        }
+        len
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn xor_neon(data: &mut [u8], keystream: &[u8]) -> usize {
+        let len = data.len() - data.len() % 16;
+        for offset in (0..len).step_by(16) {
+            let d = data.as_mut_ptr().add(offset);
+            let k = keystream.as_ptr().add(offset);
+            vst1q_u8(d, veorq_u8(vld1q_u8(d), vld1q_u8(k)));
+        }
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rc4;
+
+    /// Векторное начало совпадает с побайтным XOR при любом выравнивании,
+    /// байты после него не затрагиваются
+    #[test]
+    fn test_xor_prefix_matches_bytewise() {
+        let backing = Rc4::new(b"data").keystream_to_vec(300);
+        let keystream = Rc4::new(b"Key").keystream_to_vec(300);
+        for offset in 0..8 {
+            for len in (0..=70).chain([255, 256, 257, 292]) {
+                let mut data = backing.clone();
+                let done = xor_prefix(&mut data[offset..offset + len], &keystream[..len]);
+                assert!(done <= len && len - done < 32, "len {}", len);
+                for n in 0..len {
+                    let expected = if n < done {
+                        backing[offset + n] ^ keystream[n]
+                    } else {
+                        backing[offset + n]
+                    };
+                    assert_eq!(data[offset + n], expected, "offset {} len {}", offset, len);
+                }
+            }
+        }
+    }
+}
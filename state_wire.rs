@@ -0,0 +1,196 @@
+//! Переносимый двоичный формат снимка состояния `Rc4State` — для передачи
+//! шифра посреди потока в процесс на другом языке (например, на C).
+//!
+//! Формат версии 1 — ровно `STATE_WIRE_LEN` = 259 байт без выравнивания
+//! и без зависимости от порядка байт платформы:
+//!
+//! ```text
+//! смещение  длина  поле
+//! 0         1      версия, 0x01
+//! 1         1      i
+//! 2         1      j
+//! 3         256    S[0], S[1], ..., S[255]
+//! ```
+//!
+//! Продолжение гаммы с такого состояния — обычный PRGA: `i = i + 1`,
+//! `j = j + S[i]`, обмен `S[i]` и `S[j]`, байт `S[S[i] + S[j]]` (сложение
+//! по модулю 256). S-box обязан быть перестановкой 0..=255, иначе снимок
+//! отвергается. Hex-форма — те же 259 байт, 518 цифр; `to_hex` пишет
+//! строчные, `from_hex` принимает любой регистр.
+//!
+//! Снимок эквивалентен ключу: закодированные байты и строки требуют того же
+//! обращения, что и сам ключ, и не затираются автоматически.
+
+use std::fmt;
+
+use crate::hex::{self, HexError};
+use crate::Rc4State;
+
+/// Версия формата, записываемая в первый байт.
+pub const STATE_WIRE_VERSION: u8 = 1;
+
+/// Длина закодированного снимка: версия, `i`, `j` и 256 байт S-box.
+pub const STATE_WIRE_LEN: usize = 3 + 256;
+
+/// Ошибка разбора закодированного снимка.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateParseError {
+    /// Длина не равна `STATE_WIRE_LEN` (в поле — фактическая длина).
+    Length(usize),
+    /// Неизвестная версия формата.
+    Version(u8),
+    /// S-box не перестановка: значение `value` встречается повторно.
+    NotPermutation { value: u8 },
+    /// Hex-строка не разобрана.
+    InvalidHex(HexError),
+}
+
+impl fmt::Display for StateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateParseError::Length(len) => write!(
+                f,
+                "cipher state must be {} bytes, got {}",
+                STATE_WIRE_LEN, len
+            ),
+            StateParseError::Version(version) => {
+                write!(f, "unsupported cipher state version {}", version)
+            }
+            StateParseError::NotPermutation { value } => write!(
+                f,
+                "S-box is not a permutation: value {:#04x} repeats",
+                value
+            ),
+            StateParseError::InvalidHex(e) => write!(f, "invalid hex: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StateParseError {}
+
+impl Rc4State {
+    /// Кодирует снимок в формат версии `STATE_WIRE_VERSION` (см. модуль).
+    pub fn to_bytes(&self) -> [u8; STATE_WIRE_LEN] {
+        let mut bytes = [0u8; STATE_WIRE_LEN];
+        bytes[0] = STATE_WIRE_VERSION;
+        bytes[1] = self.i;
+        bytes[2] = self.j;
+        bytes[3..].copy_from_slice(&self.s);
+        bytes
+    }
+
+    /// Разбирает снимок, проверяя длину, версию и то, что S-box —
+    /// перестановка.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Rc4State, StateParseError> {
+        if bytes.len() != STATE_WIRE_LEN {
+            return Err(StateParseError::Length(bytes.len()));
+        }
+        if bytes[0] != STATE_WIRE_VERSION {
+            return Err(StateParseError::Version(bytes[0]));
+        }
+
+        let mut seen = [false; 256];
+        for &value in &bytes[3..] {
+            if std::mem::replace(&mut seen[value as usize], true) {
+                return Err(StateParseError::NotPermutation { value });
+            }
+        }
+
+        Ok(Rc4State {
+            s: bytes[3..].try_into().unwrap(),
+            i: bytes[1],
+            j: bytes[2],
+        })
+    }
+
+    /// `to_bytes` строчными hex-цифрами (518 символов) — для текстовых
+    /// журналов и конфигураций.
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.to_bytes())
+    }
+
+    /// Разбирает результат `to_hex`; проверки — как у `from_bytes`.
+    pub fn from_hex(text: &str) -> Result<Rc4State, StateParseError> {
+        let bytes = hex::decode(text).map_err(StateParseError::InvalidHex)?;
+        Rc4State::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::sha256;
+    use crate::Rc4;
+
+    /// Формат закреплен: тождественная перестановка кодируется побайтно,
+    /// снимок после KSA("Key") и 3 байт гаммы — по SHA-256, посчитанному
+    /// независимо (Python hashlib)
+    #[test]
+    fn test_state_wire_golden() {
+        let identity = Rc4State {
+            s: std::array::from_fn(|n| n as u8),
+            i: 0x12,
+            j: 0xfe,
+        };
+        let bytes = identity.to_bytes();
+        assert_eq!(bytes[..5], [0x01, 0x12, 0xfe, 0x00, 0x01]);
+        assert_eq!(bytes[STATE_WIRE_LEN - 1], 0xff);
+        assert!(identity.to_hex().starts_with("0112fe000102"));
+        assert!(identity.to_hex().ends_with("fdfeff"));
+
+        let mut cipher = Rc4::new(b"Key");
+        cipher.skip(3);
+        let bytes = cipher.export_state().to_bytes();
+        assert_eq!(bytes[..3], [0x01, 0x03, 0x54]);
+        assert_eq!(
+            hex::encode(&sha256(&bytes)),
+            "adafd25124fbdc5c5a7c98f64158a754c53c9171d9e89ac05395e1c885cf987b"
+        );
+    }
+
+    /// Снимок переживает кодирование в байты и hex, гамма продолжается
+    #[test]
+    fn test_state_wire_roundtrip() {
+        let mut cipher = Rc4::new(b"Key");
+        cipher.skip(1000);
+        let state = cipher.export_state();
+
+        assert!(Rc4State::from_bytes(&state.to_bytes()).unwrap() == state);
+        let hex = state.to_hex();
+        assert_eq!(hex.len(), 2 * STATE_WIRE_LEN);
+        let restored = Rc4State::from_hex(&hex.to_uppercase()).unwrap();
+        assert_eq!(
+            Rc4::from_state(restored).keystream_to_vec(32),
+            cipher.keystream_to_vec(32)
+        );
+    }
+
+    /// Неверные длина, версия, S-box и hex отвергаются
+    #[test]
+    fn test_state_wire_rejects_invalid() {
+        let bytes = Rc4::new(b"Key").export_state().to_bytes();
+        assert_eq!(
+            Rc4State::from_bytes(&bytes[..258]).err(),
+            Some(StateParseError::Length(258))
+        );
+
+        let mut wrong_version = bytes;
+        wrong_version[0] = 2;
+        assert_eq!(
+            Rc4State::from_bytes(&wrong_version).err(),
+            Some(StateParseError::Version(2))
+        );
+
+        let mut repeated = bytes;
+        repeated[3 + 7] = repeated[3];
+        assert_eq!(
+            Rc4State::from_bytes(&repeated).err(),
+            Some(StateParseError::NotPermutation { value: repeated[3] })
+        );
+
+        assert!(matches!(
+            Rc4State::from_hex("01zz"),
+            Err(StateParseError::InvalidHex(_))
+        ));
+    }
+}
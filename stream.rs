@@ -0,0 +1,976 @@
+//! Потоковые адаптеры поверх `Rc4` для работы с `std::io`.
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use crate::{Rc4, Rc4Error};
+
+/// Итератор, читающий исходные данные блоками фиксированного размера
+/// и выдающий зашифрованные блоки.
+///
+/// Состояние шифра переносится между блоками, поэтому конкатенация всех
+/// выданных блоков — это ровно `apply` над всем содержимым источника.
+/// Все блоки, кроме последнего, имеют размер `chunk_size`.
+pub struct Rc4ChunkEncryptor<R: Read> {
+    reader: R,
+    cipher: Rc4,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<R: Read> Rc4ChunkEncryptor<R> {
+    /// Паникует, если `chunk_size == 0`.
+    pub fn new(reader: R, cipher: Rc4, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        Rc4ChunkEncryptor {
+            reader,
+            cipher,
+            chunk_size,
+            done: false,
+        }
+    }
+
+    /// Возвращает внутренний источник и шифр в текущем состоянии.
+    pub fn into_inner(self) -> (R, Rc4) {
+        (self.reader, self.cipher)
+    }
+}
+
+impl<R: Read> Iterator for Rc4ChunkEncryptor<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut chunk = vec![0u8; self.chunk_size];
+        let mut filled = 0;
+        // Дочитываем блок целиком: короткое чтение еще не означает конец потока
+        while filled < chunk.len() {
+            match self.reader.read(&mut chunk[filled..]) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Прочитанное до ошибки еще не шифровалось — состояние не сдвигаем
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        if filled == 0 {
+            return None;
+        }
+
+        chunk.truncate(filled);
+        self.cipher.process(&mut chunk);
+        Some(Ok(chunk))
+    }
+}
+
+/// Шифр с отслеживанием позиции в потоке гаммы и произвольным доступом
+/// через `Seek`, например для страниц зашифрованного файла базы данных.
+///
+/// Переход вперед выполняется через `skip`, переход назад — повторной
+/// инициализацией из сохраненного ключа и пропуском до нужной позиции,
+/// поэтому стоимость перехода — O(позиция). Длина потока не известна,
+/// поэтому `SeekFrom::End` не поддерживается.
+pub struct Rc4Seekable {
+    key: Vec<u8>,
+    cipher: Rc4,
+    position: u64,
+}
+
+impl Rc4Seekable {
+    pub fn new(key: &[u8]) -> Result<Self, Rc4Error> {
+        Ok(Rc4Seekable {
+            cipher: Rc4::try_new(key)?,
+            key: key.to_vec(),
+            position: 0,
+        })
+    }
+
+    /// Текущая позиция в потоке гаммы (в байтах от начала).
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Шифрует/расшифровывает `data` гаммой с текущей позиции.
+    pub fn process(&mut self, data: &mut [u8]) {
+        self.cipher.process(data);
+        self.position += data.len() as u64;
+    }
+
+    fn seek_to(&mut self, target: u64) {
+        if target < self.position {
+            // Вернуться назад в RC4 нельзя — начинаем поток заново
+            self.cipher = Rc4::new(&self.key);
+            self.position = 0;
+        }
+        advance(&mut self.cipher, target - self.position);
+        self.position = target;
+    }
+}
+
+// Ключ хранится в куче все время жизни обертки — затираем его при удалении
+#[cfg(feature = "zeroize")]
+impl Drop for Rc4Seekable {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.key);
+    }
+}
+
+impl Seek for Rc4Seekable {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "keystream length is unbounded, SeekFrom::End is not supported",
+                ))
+            }
+        };
+
+        let target = target.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        self.seek_to(target);
+        Ok(target)
+    }
+}
+
+/// Неизменяемый описатель для произвольного доступа к зашифрованному блобу,
+/// например для ответов на HTTP range-запросы.
+///
+/// Хранит состояние после KSA и отброса `drop` байт, поэтому каждый
+/// `decrypt_range` независим: копирует это состояние, пропускает `offset`
+/// байт и расшифровывает срез. Стоимость вызова — O(`offset`), KSA не
+/// повторяется. Описатель не меняется при вызовах, поэтому его можно
+/// разделять между потоками (`Sync`). Сохраненный S-box эквивалентен ключу.
+#[derive(Clone)]
+pub struct Rc4Cursor {
+    start: Rc4,
+}
+
+impl Rc4Cursor {
+    /// Ошибки — как у `Rc4::new_drop`.
+    pub fn new(key: &[u8], drop: usize) -> Result<Self, Rc4Error> {
+        Ok(Rc4Cursor {
+            start: Rc4::new_drop(key, drop)?,
+        })
+    }
+
+    /// Расшифровывает (или шифрует) на месте `data` — байты блоба, начиная
+    /// с позиции `offset`.
+    pub fn decrypt_range(&self, offset: u64, data: &mut [u8]) {
+        let mut cipher = self.start.clone();
+        advance(&mut cipher, offset);
+        cipher.process(data);
+    }
+}
+
+/// Источник сырой гаммы для `std::io`: бесконечный `Read`, например для
+/// передачи гаммы во внешние тесты случайности (`dieharder`, `ent`):
+///
+/// ```ignore
+/// io::copy(&mut KeystreamReader::new(key)?.take(1 << 30), &mut file)?;
+/// ```
+///
+/// `read` всегда заполняет весь буфер (пустой буфер — `Ok(0)`), поэтому и
+/// `read_exact` обходится одним вызовом `fill_keystream`, без побайтного
+/// цикла.
+pub struct KeystreamReader {
+    cipher: Rc4,
+    position: u64,
+}
+
+impl KeystreamReader {
+    /// Ошибки — как у `Rc4::try_new`.
+    pub fn new(key: &[u8]) -> Result<Self, Rc4Error> {
+        Self::with_drop(key, 0)
+    }
+
+    /// Гамма начинается после `drop` отброшенных байт (RC4-drop[n]).
+    pub fn with_drop(key: &[u8], drop: usize) -> Result<Self, Rc4Error> {
+        Ok(KeystreamReader {
+            cipher: Rc4::new_drop(key, drop)?,
+            position: 0,
+        })
+    }
+
+    /// Сколько байт гаммы выдано; отброшенные байты не считаются.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl Read for KeystreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.cipher.fill_keystream(buf);
+        self.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.read(buf).map(drop)
+    }
+}
+
+/// Максимальный объем открытого текста, шифруемый за один вызов `write`.
+const WRITE_CHUNK: usize = 8192;
+
+/// Отправляет во внутренний поток все отложенные зашифрованные байты.
+fn write_pending<W: Write>(inner: &mut W, pending: &mut Vec<u8>) -> io::Result<()> {
+    while !pending.is_empty() {
+        match inner.write(pending) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => {
+                pending.drain(..n);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Общая часть `write` у `Rc4Writer` и `Rc4Duplex`.
+fn write_encrypted<W: Write>(
+    inner: &mut W,
+    cipher: &mut Rc4,
+    pending: &mut Vec<u8>,
+    buf: &[u8],
+) -> io::Result<usize> {
+    write_pending(inner, pending)?;
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    let n = buf.len().min(WRITE_CHUNK);
+    pending.extend_from_slice(&buf[..n]);
+    cipher.process(pending);
+
+    // Байты уже зашифрованы и учтены в состоянии шифра, поэтому сообщаем
+    // о записи n байт; неотправленный остаток уйдет при следующем вызове.
+    if let Ok(written) = inner.write(pending) {
+        pending.drain(..written);
+    }
+    Ok(n)
+}
+
+/// Продвигает шифр на `n` байт гаммы, не ограничиваясь `usize`.
+fn advance(cipher: &mut Rc4, n: u64) {
+    for step in skip_steps(n, usize::MAX) {
+        cipher.skip(step);
+    }
+}
+
+/// Разбивает пропуск `n` байт на порции для `skip` не больше `max_step`:
+/// на 32-битных платформах `usize` меньше смещений в u64.
+fn skip_steps(mut n: u64, max_step: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (n > 0).then(|| {
+            let step = n.min(max_step as u64) as usize;
+            n -= step as u64;
+            step
+        })
+    })
+}
+
+/// Емкость буфера `Rc4Reader` по умолчанию.
+pub const DEFAULT_READER_CAPACITY: usize = 8 * 1024;
+
+/// Расшифровывает данные, читаемые из внутреннего источника.
+///
+/// Читатель буферизован и реализует `BufRead`, поэтому `read_line`, `lines`
+/// и `split` работают без дополнительного `BufReader`. Каждый прочитанный
+/// байт расшифровывается ровно один раз — на месте, в собственном буфере
+/// или, при чтении в большой буфер вызывающего, прямо в нем. Ошибки
+/// внутреннего источника передаются как есть.
+///
+/// Если источник реализует `Seek`, читатель тоже его реализует. Для этого
+/// шифр, переданный в `new`, должен соответствовать нулевой позиции
+/// источника: байт `n` потока расшифровывается байтом `n` гаммы. Переход
+/// вперед выполняется через `skip`, назад — с копии начального состояния,
+/// сохраненной в `new`, поэтому стоимость перехода — O(новая позиция), а
+/// для файлов в гигабайты — секунды. Переход сбрасывает буфер.
+pub struct Rc4Reader<R: Read> {
+    inner: R,
+    cipher: Rc4,
+    origin: Rc4,
+    /// Сколько байт прочитано из источника и расшифровано (включая буфер).
+    position: u64,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> Rc4Reader<R> {
+    pub fn new(inner: R, cipher: Rc4) -> Self {
+        Self::with_capacity(DEFAULT_READER_CAPACITY, inner, cipher)
+    }
+
+    /// Как `new`, но с буфером на `capacity` байт. Паникует при `capacity == 0`.
+    pub fn with_capacity(capacity: usize, inner: R, cipher: Rc4) -> Self {
+        assert!(capacity > 0, "buffer capacity must be non-zero");
+        Rc4Reader {
+            inner,
+            origin: cipher.clone(),
+            cipher,
+            position: 0,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Возвращает источник и шифр. Расшифрованные, но не выданные байты
+    /// буфера теряются.
+    pub fn into_inner(self) -> (R, Rc4) {
+        (self.inner, self.cipher)
+    }
+
+    fn buffered(&self) -> usize {
+        self.filled - self.pos
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<R: Read> Read for Rc4Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Буфер пуст, а запрошено не меньше его емкости: читаем и
+        // расшифровываем прямо в буфер вызывающего, без лишнего копирования
+        if self.buffered() == 0 && buf.len() >= self.buf.len() {
+            let n = self.inner.read(buf)?;
+            self.cipher.process(&mut buf[..n]);
+            self.position += n as u64;
+            return Ok(n);
+        }
+
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for Rc4Reader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            let n = self.inner.read(&mut self.buf)?;
+            self.cipher.process(&mut self.buf[..n]);
+            self.position += n as u64;
+            self.pos = 0;
+            self.filled = n;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: Read + Seek> Seek for Rc4Reader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // Источник опережает читателя на непрочитанную часть буфера
+        let pos = match pos {
+            SeekFrom::Current(delta) => {
+                let behind = self.buffered() as i64;
+                let delta = delta.checked_sub(behind).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "seek offset overflows")
+                })?;
+                SeekFrom::Current(delta)
+            }
+            other => other,
+        };
+
+        // Позицию определяет источник; при ошибке шифр и буфер не трогаем
+        let target = self.inner.seek(pos)?;
+        self.discard_buffer();
+        if target < self.position {
+            self.cipher = self.origin.clone();
+            self.position = 0;
+        }
+        advance(&mut self.cipher, target - self.position);
+        self.position = target;
+        Ok(target)
+    }
+}
+
+/// Шифрует данные перед записью во внутренний приемник.
+///
+/// Зашифрованные, но не принятые приемником байты хранятся во внутреннем
+/// буфере, поэтому частичная запись не рассинхронизирует шифр: `write`
+/// сообщает о принятии байтов сразу после шифрования, а ошибка приемника
+/// проявится при следующем `write` или `flush`.
+pub struct Rc4Writer<W: Write> {
+    inner: W,
+    cipher: Rc4,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Rc4Writer<W> {
+    pub fn new(inner: W, cipher: Rc4) -> Self {
+        Rc4Writer {
+            inner,
+            cipher,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Возвращает приемник и шифр. Неотправленные байты теряются —
+    /// перед вызовом следует выполнить `flush`.
+    pub fn into_inner(self) -> (W, Rc4) {
+        (self.inner, self.cipher)
+    }
+}
+
+impl<W: Write> Write for Rc4Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        write_encrypted(&mut self.inner, &mut self.cipher, &mut self.pending, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        write_pending(&mut self.inner, &mut self.pending)?;
+        self.inner.flush()
+    }
+}
+
+/// Двунаправленный поток с независимыми шифрами для каждого направления:
+/// запись шифруется состоянием `tx`, чтение расшифровывается состоянием `rx`.
+///
+/// Зашифрованные, но еще не принятые внутренним потоком байты хранятся во
+/// внутреннем буфере, поэтому частичная запись не рассинхронизирует `tx`:
+/// `write` сообщает о принятии байтов сразу после шифрования, а ошибки
+/// внутреннего потока проявятся при следующем `write` или `flush`.
+pub struct Rc4Duplex<S: Read + Write> {
+    inner: S,
+    tx: Rc4,
+    rx: Rc4,
+    pending: Vec<u8>,
+}
+
+impl<S: Read + Write> Rc4Duplex<S> {
+    /// У собеседника ключи должны быть зеркальными: его `tx_key` — наш `rx_key`.
+    pub fn new(inner: S, tx_key: &[u8], rx_key: &[u8]) -> Result<Self, Rc4Error> {
+        Ok(Rc4Duplex {
+            inner,
+            tx: Rc4::try_new(tx_key)?,
+            rx: Rc4::try_new(rx_key)?,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Выводит ключи направлений из общего секрета: ключ направления —
+    /// это `secret || label`. Метки должны различаться, иначе оба направления
+    /// получат одинаковую гамму. Суммарная длина не должна превышать 256 байт.
+    pub fn from_shared_secret(
+        inner: S,
+        secret: &[u8],
+        tx_label: &[u8],
+        rx_label: &[u8],
+    ) -> Result<Self, Rc4Error> {
+        let tx_key = [secret, tx_label].concat();
+        let rx_key = [secret, rx_label].concat();
+        Self::new(inner, &tx_key, &rx_key)
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Возвращает внутренний поток. Зашифрованные, но не отправленные байты
+    /// теряются — перед вызовом следует выполнить `flush`.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Read + Write> Read for Rc4Duplex<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.rx.process(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<S: Read + Write> Write for Rc4Duplex<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        write_encrypted(&mut self.inner, &mut self.tx, &mut self.pending, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        write_pending(&mut self.inner, &mut self.pending)?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Источник, отдающий данные маленькими порциями
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    /// Окна блоба, включая начало и последний байт, совпадают с полной
+    /// расшифровкой; описатель разделяется между потоками
+    #[test]
+    fn test_cursor_random_windows() {
+        let plaintext: Vec<u8> = (0..1 << 20u32).map(|n| (n * 31 % 251) as u8).collect();
+        let ciphertext = Rc4::new_drop(b"Key", 768).unwrap().apply(&plaintext);
+        let cursor = Rc4Cursor::new(b"Key", 768).unwrap();
+
+        let len = plaintext.len();
+        let mut windows = vec![(0, 1), (0, len), (len - 1, 1), (len - 100, 100)];
+        let mut seed = 0x2545_f491u32;
+        for _ in 0..32 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let offset = seed as usize % len;
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            windows.push((offset, seed as usize % 4096 % (len - offset) + 1));
+        }
+
+        std::thread::scope(|scope| {
+            for chunk in windows.chunks(9) {
+                let (cursor, ciphertext, plaintext) = (&cursor, &ciphertext, &plaintext);
+                scope.spawn(move || {
+                    for &(offset, n) in chunk {
+                        let mut data = ciphertext[offset..offset + n].to_vec();
+                        cursor.decrypt_range(offset as u64, &mut data);
+                        assert!(data == plaintext[offset..offset + n], "{}+{}", offset, n);
+                    }
+                });
+            }
+        });
+        assert!(matches!(Rc4Cursor::new(b"", 0), Err(Rc4Error::EmptyKey)));
+    }
+
+    /// 1 МиБ гаммы, прочитанный порциями случайного размера (включая
+    /// пустые), совпадает с keystream_to_vec; drop сдвигает начало
+    #[test]
+    fn test_keystream_reader_random_chunks() {
+        let expected = Rc4::new(b"Key").keystream_to_vec(1 << 20);
+        let mut reader = KeystreamReader::new(b"Key").unwrap();
+        let mut actual = vec![0u8; expected.len()];
+
+        let mut seed = 0x9e37_79b9u32;
+        let mut offset = 0;
+        while offset < actual.len() {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let n = (seed >> 8) as usize % 70_000;
+            let end = (offset + n).min(actual.len());
+            if seed & 1 == 0 {
+                assert_eq!(reader.read(&mut actual[offset..end]).unwrap(), end - offset);
+            } else {
+                reader.read_exact(&mut actual[offset..end]).unwrap();
+            }
+            offset = end;
+            assert_eq!(reader.position(), offset as u64);
+        }
+        assert!(actual == expected);
+
+        let mut taken = Vec::new();
+        let mut dropped = KeystreamReader::with_drop(b"Key", 768).unwrap();
+        io::copy(&mut (&mut dropped).take(100), &mut taken).unwrap();
+        assert_eq!(
+            taken,
+            Rc4::new_drop(b"Key", 768).unwrap().keystream_to_vec(100)
+        );
+        assert_eq!(dropped.position(), 100);
+        assert!(matches!(KeystreamReader::new(b""), Err(Rc4Error::EmptyKey)));
+    }
+
+    /// Смещение 2^32 + k не усекается до u32: порции пропуска в сумме дают
+    /// смещение целиком и при 64-битном, и при 32-битном `usize`, а
+    /// пропуск порциями равен пропуску разом. Гамма длиной 4 ГиБ для
+    /// этого не нужна
+    #[test]
+    fn test_cursor_range_beyond_u32() {
+        let offset = (1u64 << 32) + 5;
+        let native: Vec<usize> = skip_steps(offset, usize::MAX).collect();
+        assert_eq!(native.iter().map(|&n| n as u64).sum::<u64>(), offset);
+        if usize::BITS == 64 {
+            assert_eq!(native, [offset as usize]);
+        }
+        let narrow: Vec<usize> = skip_steps(offset, u32::MAX as usize).collect();
+        assert_eq!(narrow, [u32::MAX as usize, 6]);
+        assert_eq!(skip_steps(0, 1).count(), 0);
+
+        let mut chunked = Rc4::new(b"Key");
+        for step in skip_steps(1000, 7) {
+            chunked.skip(step);
+        }
+        let cursor = Rc4Cursor::new(b"Key", 0).unwrap();
+        let mut data = [0u8; 16];
+        cursor.decrypt_range(1000, &mut data);
+        assert_eq!(data, chunked.keystream_to_vec(16)[..]);
+    }
+
+    /// Блоки шифротекста собираются обратно в исходный текст
+    #[test]
+    fn test_chunks_reassemble() {
+        let plaintext: Vec<u8> = (0..1000u32).map(|n| (n % 251) as u8).collect();
+
+        let chunks: Vec<Vec<u8>> =
+            Rc4ChunkEncryptor::new(Trickle(&plaintext), Rc4::new(b"Key"), 64)
+                .collect::<io::Result<_>>()
+                .unwrap();
+
+        assert_eq!(chunks.len(), 16);
+        assert!(chunks[..15].iter().all(|c| c.len() == 64));
+        assert_eq!(chunks[15].len(), 1000 - 15 * 64);
+
+        let mut decrypted: Vec<u8> = chunks.into_iter().flatten().collect();
+        Rc4::new(b"Key").process(&mut decrypted);
+        assert_eq!(decrypted, plaintext);
+    }
+
+    /// Пустой источник не дает ни одного блока
+    #[test]
+    fn test_empty_source() {
+        let mut iter = Rc4ChunkEncryptor::new(&b""[..], Rc4::new(b"Key"), 16);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+    }
+
+    fn keystream_at(key: &[u8], position: usize, len: usize) -> Vec<u8> {
+        let mut reference = Rc4::new(key);
+        reference.skip(position);
+        reference.keystream_to_vec(len)
+    }
+
+    fn next_keystream(seekable: &mut Rc4Seekable, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        seekable.process(&mut buf);
+        buf
+    }
+
+    /// После seek гамма совпадает с гаммой свежего шифра на той же позиции
+    #[test]
+    fn test_seek_matches_reference() {
+        let key = b"SeekKey";
+        let mut seekable = Rc4Seekable::new(key).unwrap();
+
+        for &(pos, len) in &[(0u64, 16), (1000, 32), (10, 8), (4096, 64), (4096, 1)] {
+            assert_eq!(seekable.seek(SeekFrom::Start(pos)).unwrap(), pos);
+            assert_eq!(
+                next_keystream(&mut seekable, len),
+                keystream_at(key, pos as usize, len)
+            );
+            assert_eq!(seekable.position(), pos + len as u64);
+        }
+    }
+
+    /// SeekFrom::Current вперед и назад
+    #[test]
+    fn test_seek_current() {
+        let key = b"SeekKey";
+        let mut seekable = Rc4Seekable::new(key).unwrap();
+        next_keystream(&mut seekable, 100);
+
+        assert_eq!(seekable.seek(SeekFrom::Current(50)).unwrap(), 150);
+        assert_eq!(
+            next_keystream(&mut seekable, 10),
+            keystream_at(key, 150, 10)
+        );
+
+        assert_eq!(seekable.seek(SeekFrom::Current(-120)).unwrap(), 40);
+        assert_eq!(next_keystream(&mut seekable, 10), keystream_at(key, 40, 10));
+
+        assert_eq!(seekable.stream_position().unwrap(), 50);
+    }
+
+    /// SeekFrom::End и переход в отрицательную позицию — ошибки
+    #[test]
+    fn test_seek_errors() {
+        let mut seekable = Rc4Seekable::new(b"SeekKey").unwrap();
+        let err = seekable.seek(SeekFrom::End(0)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        let err = seekable.seek(SeekFrom::Current(-1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(seekable.position(), 0);
+    }
+
+    /// Две стороны с зеркальными ключами обмениваются данными одновременно
+    #[cfg(unix)]
+    #[test]
+    fn test_duplex_over_socket_pair() {
+        use std::os::unix::net::UnixStream;
+        use std::thread;
+
+        const ROUNDS: usize = 500;
+
+        // Общее для обеих сторон расписание: сколько байт каждая сторона
+        // отправляет в раунде. Внутри раунда объем мал, поэтому буфера сокета
+        // хватает, и стороны не блокируют друг друга на записи.
+        fn schedule() -> Vec<(usize, usize)> {
+            let sizes = Rc4::new(b"schedule").keystream_to_vec(ROUNDS * 2);
+            sizes
+                .chunks(2)
+                .map(|c| (c[0] as usize + 1, c[1] as usize + 1))
+                .collect()
+        }
+
+        fn run_side(
+            stream: UnixStream,
+            tx: &'static [u8],
+            rx: &'static [u8],
+            first: bool,
+        ) -> Vec<u8> {
+            let mut duplex = Rc4Duplex::from_shared_secret(stream, b"shared", tx, rx).unwrap();
+            let mut data = Rc4::new(tx);
+            let mut received = Vec::new();
+
+            for (a, b) in schedule() {
+                let (send, recv) = if first { (a, b) } else { (b, a) };
+                duplex.write_all(&data.keystream_to_vec(send)).unwrap();
+                duplex.flush().unwrap();
+
+                // Читаем порциями разного размера, чтобы проверить частичные чтения
+                let mut incoming = vec![0u8; recv];
+                let mut filled = 0;
+                while filled < recv {
+                    let end = (filled + 1 + filled % 17).min(recv);
+                    filled += duplex.read(&mut incoming[filled..end]).unwrap();
+                }
+                received.extend(incoming);
+            }
+            received
+        }
+
+        let (a, b) = UnixStream::pair().unwrap();
+        let side_a = thread::spawn(move || run_side(a, b"a->b", b"b->a", true));
+        let side_b = thread::spawn(move || run_side(b, b"b->a", b"a->b", false));
+
+        let total_a: usize = schedule().iter().map(|&(a, _)| a).sum();
+        let total_b: usize = schedule().iter().map(|&(_, b)| b).sum();
+        assert_eq!(
+            side_a.join().unwrap(),
+            Rc4::new(b"b->a").keystream_to_vec(total_b)
+        );
+        assert_eq!(
+            side_b.join().unwrap(),
+            Rc4::new(b"a->b").keystream_to_vec(total_a)
+        );
+    }
+
+    /// Эхо-сервер по TCP: сервер расшифровывает принятое и шифрует обратно
+    /// своим ключом, клиент получает исходный текст (то же, что
+    /// examples/tcp_echo.rs, но с порциями разного размера)
+    #[test]
+    fn test_tcp_echo() {
+        use std::net::{Shutdown, TcpListener, TcpStream};
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = Rc4Reader::new(stream.try_clone().unwrap(), Rc4::new("client"));
+            let mut writer = Rc4Writer::new(stream, Rc4::new("server"));
+            io::copy(&mut reader, &mut writer).unwrap();
+            writer.flush().unwrap();
+            let (stream, _) = writer.into_inner();
+            stream.shutdown(Shutdown::Write).unwrap();
+        });
+
+        // Больше буферов сокета, чтобы запись и чтение шли частями
+        let message = Rc4::new(b"message").keystream_to_vec(4 << 20);
+        let stream = TcpStream::connect(address).unwrap();
+        let mut writer = Rc4Writer::new(stream.try_clone().unwrap(), Rc4::new("client"));
+        let sent = message.clone();
+        let client = thread::spawn(move || {
+            for (n, chunk) in sent.chunks(65_521).enumerate() {
+                // Порции разного размера, включая пустые
+                let (head, tail) = chunk.split_at(n % chunk.len());
+                writer.write_all(head).unwrap();
+                writer.write_all(&[]).unwrap();
+                writer.write_all(tail).unwrap();
+            }
+            writer.flush().unwrap();
+            let (stream, _) = writer.into_inner();
+            stream.shutdown(Shutdown::Write).unwrap();
+        });
+
+        let mut echoed = Vec::new();
+        let mut reader = Rc4Reader::new(stream, Rc4::new("server"));
+        reader.read_to_end(&mut echoed).unwrap();
+        client.join().unwrap();
+        server.join().unwrap();
+        assert!(echoed == message, "echoed data differs");
+    }
+
+    /// После любого seek чтение совпадает с расшифровкой всего файла
+    #[test]
+    fn test_reader_seek_matches_full_decryption() {
+        use crate::cli::tests::TempDir;
+        use std::fs::{self, File};
+
+        let dir = TempDir::new("reader-seek");
+        let plaintext = Rc4::new(b"plaintext").keystream_to_vec(300_000);
+        let path = dir.path("media.rc4");
+        fs::write(&path, Rc4::new(b"Key").apply(&plaintext)).unwrap();
+
+        let mut reader = Rc4Reader::new(File::open(&path).unwrap(), Rc4::new(b"Key"));
+        let len = plaintext.len() as u64;
+        let offsets = Rc4::new(b"offsets").keystream_to_vec(200);
+        for (n, pair) in offsets.chunks(4).enumerate() {
+            let value = u32::from_le_bytes(pair.try_into().unwrap()) as u64;
+            let pos = match n % 4 {
+                0 => SeekFrom::Start(value % len),
+                1 => SeekFrom::Current((value % 2000) as i64 - 1000),
+                2 => SeekFrom::End(-((value % 5000) as i64)),
+                _ => SeekFrom::Current(0),
+            };
+            let Ok(target) = reader.seek(pos) else {
+                continue;
+            };
+            assert_eq!(reader.stream_position().unwrap(), target);
+
+            let mut buf = vec![0u8; (value % 700) as usize];
+            let n = reader.read(&mut buf).unwrap();
+            let start = target as usize;
+            assert_eq!(buf[..n], plaintext[start..start + n], "at offset {}", start);
+        }
+
+        // Ошибка источника (переход до начала) не сдвигает шифр
+        reader.seek(SeekFrom::Start(10)).unwrap();
+        assert!(reader.seek(SeekFrom::Current(-11)).is_err());
+        let mut buf = [0u8; 16];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, plaintext[10..26]);
+    }
+
+    /// read_line и lines() по зашифрованному файлу, в том числе строки,
+    /// пересекающие границу буфера
+    #[test]
+    fn test_reader_buf_read_lines() {
+        use crate::cli::tests::TempDir;
+        use std::fs::{self, File};
+
+        let dir = TempDir::new("reader-lines");
+        let mut lines: Vec<String> = (0..200)
+            .map(|n| format!("line {} {}", n, "x".repeat(n)))
+            .collect();
+        lines.insert(100, "y".repeat(3 * DEFAULT_READER_CAPACITY));
+        let text = lines.join("\n") + "\n";
+        let path = dir.path("notes.txt.rc4");
+        fs::write(&path, Rc4::new(b"Key").apply(text.as_bytes())).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let decrypted: Vec<String> = Rc4Reader::new(file, Rc4::new(b"Key"))
+            .lines()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(decrypted, lines);
+
+        // Маленький буфер: почти каждая строка пересекает его границу
+        let file = File::open(&path).unwrap();
+        let mut reader = Rc4Reader::with_capacity(16, file, Rc4::new(b"Key"));
+        let mut line = String::new();
+        for expected in &lines {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line.trim_end_matches('\n'), expected);
+        }
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+    }
+
+    /// Буферизованное и прямое чтение вперемешку с seek не расшифровывают
+    /// байты дважды
+    #[test]
+    fn test_reader_mixed_reads_and_seek() {
+        let plaintext = Rc4::new(b"plaintext").keystream_to_vec(10_000);
+        let ciphertext = Rc4::new(b"Key").apply(&plaintext);
+        let mut reader =
+            Rc4Reader::with_capacity(64, io::Cursor::new(ciphertext), Rc4::new(b"Key"));
+
+        let mut small = [0u8; 10];
+        reader.read_exact(&mut small).unwrap();
+        assert_eq!(small, plaintext[..10]);
+        assert_eq!(reader.stream_position().unwrap(), 10);
+
+        // Остаток буфера, затем прямое чтение в большой буфер
+        let mut large = vec![0u8; 1000];
+        reader.read_exact(&mut large).unwrap();
+        assert_eq!(large, plaintext[10..1010]);
+
+        reader.fill_buf().unwrap();
+        assert_eq!(reader.seek(SeekFrom::Current(-5)).unwrap(), 1005);
+        reader.read_exact(&mut small).unwrap();
+        assert_eq!(small, plaintext[1005..1015]);
+    }
+
+    /// Запись и чтение, перемежающиеся мелкими порциями, не влияют друг на друга
+    #[test]
+    fn test_duplex_interleaved_states_independent() {
+        // Петля: все записанное попадает в собственный буфер чтения
+        struct Loopback(std::collections::VecDeque<u8>);
+
+        impl Read for Loopback {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl Write for Loopback {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                // Принимаем не больше 3 байт за раз
+                let n = buf.len().min(3);
+                self.0.extend(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // Одинаковые ключи: то, что зашифровал tx, расшифрует rx
+        let mut duplex = Rc4Duplex::new(Loopback(Default::default()), b"Key", b"Key").unwrap();
+        let message: Vec<u8> = (0..200u8).collect();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 5];
+
+        for chunk in message.chunks(7) {
+            duplex.write_all(chunk).unwrap();
+            duplex.flush().unwrap();
+            let n = duplex.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        duplex.read_to_end(&mut received).unwrap();
+
+        assert_eq!(received, message);
+    }
+}
@@ -0,0 +1,77 @@
+//! `apply_to_writer` не выделяет память в куче, а `apply` выделяет буфер
+//! под весь шифротекст.
+//!
+//! Счетчик — глобальный аллокатор этого тестового бинарника; библиотека
+//! его не видит. Цифры для разных размеров печатает `benches/alloc.rs`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::{self, Write};
+
+use rust_rc4::Rc4;
+
+thread_local! {
+    // Счетчик на поток: раннер тестов выделяет память в других потоках
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count(size: usize) {
+    // При завершении потока счетчик уже может быть недоступен
+    let _ = ALLOCATED.try_with(|n| n.set(n.get() + size));
+}
+
+struct Counting;
+
+// SAFETY: все вызовы передаются System без изменений
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocated_by(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.with(Cell::get);
+    f();
+    ALLOCATED.with(Cell::get) - before
+}
+
+/// apply_to_writer пишет через буфер на стеке и ничего не выделяет
+#[test]
+fn test_apply_to_writer_does_not_allocate() {
+    let data = vec![0x5a; 1 << 20];
+    let mut sink = io::sink();
+    let mut cipher = Rc4::new(b"Key");
+    let allocated = allocated_by(|| {
+        assert_eq!(
+            cipher.apply_to_writer(&data, &mut sink).unwrap(),
+            data.len()
+        );
+    });
+    assert_eq!(allocated, 0);
+}
+
+/// apply выделяет не меньше длины данных: сравнение выше не пустое
+#[test]
+fn test_apply_allocates_the_ciphertext() {
+    let data = vec![0x5a; 1 << 20];
+    let mut sink = io::sink();
+    let mut cipher = Rc4::new(b"Key");
+    let allocated = allocated_by(|| {
+        let ciphertext = cipher.apply(&data);
+        sink.write_all(&ciphertext).unwrap();
+    });
+    assert!(allocated >= data.len(), "{} bytes", allocated);
+}
@@ -0,0 +1,204 @@
+//! Бинарник `rc4` целиком: коды возврата и диагностика в stderr.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rust_rc4::cli::{EXIT_CHECKSUM, EXIT_FAILURE, EXIT_USAGE};
+use rust_rc4::Rc4;
+
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rc4-bin-{}-{}-{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    fn arg(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Запускает `rc4` и возвращает код возврата и stderr.
+fn rc4(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rc4"))
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (output.status.code().unwrap(), stderr)
+}
+
+fn file_contents(n: usize) -> Vec<u8> {
+    Rc4::new(format!("content-{}", n).as_bytes()).keystream_to_vec(1000 + n * 4099)
+}
+
+/// Все файлы дерева шифруются параллельно и расшифровываются обратно;
+/// файл из аргументов и из --glob обрабатывается один раз
+#[test]
+fn test_multi_file_roundtrip() {
+    let dir = TempDir::new("multi");
+    fs::create_dir_all(dir.path("logs/nested/deeper")).unwrap();
+    fs::write(dir.path("k.bin"), b"file key").unwrap();
+
+    let names = [
+        "logs/a.log",
+        "logs/b.log",
+        "logs/nested/c.log",
+        "logs/nested/deeper/d.log",
+    ];
+    for (n, name) in names.iter().enumerate() {
+        fs::write(dir.path(name), file_contents(n)).unwrap();
+    }
+    fs::write(dir.path("logs/skip.txt"), b"not a log").unwrap();
+
+    let (code, output) = rc4(&[
+        "encrypt",
+        "--key-file",
+        &dir.arg("k.bin"),
+        "--threads",
+        "3",
+        &dir.arg("logs/a.log"),
+        "--glob",
+        &format!("{}/logs/**/*.log", dir.0.display()),
+    ]);
+    assert_eq!(code, 0, "{}", output);
+    assert!(output.contains("4 succeeded, 0 failed"), "{}", output);
+    assert!(!dir.path("logs/skip.txt.rc4").exists());
+
+    for (n, name) in names.iter().enumerate() {
+        let encrypted = fs::read(dir.path(&format!("{}.rc4", name))).unwrap();
+        assert_eq!(Rc4::new(b"file key").apply(&encrypted), file_contents(n));
+        fs::remove_file(dir.path(name)).unwrap();
+    }
+
+    let (code, output) = rc4(&[
+        "decrypt",
+        "--key-file",
+        &dir.arg("k.bin"),
+        "--glob",
+        &format!("{}/logs/**/*.rc4", dir.0.display()),
+    ]);
+    assert_eq!(code, 0, "{}", output);
+    for (n, name) in names.iter().enumerate() {
+        assert_eq!(fs::read(dir.path(name)).unwrap(), file_contents(n));
+    }
+}
+
+/// -q оставляет только ошибки, -v добавляет параметры, -vv — контрольные
+/// точки; ключ не попадает в вывод ни на каком уровне
+#[test]
+fn test_verbosity() {
+    let dir = TempDir::new("verbosity");
+    fs::write(dir.path("a.log"), b"first").unwrap();
+    let key = "VerbositySecret";
+    let encrypt = |flags: &[&str], input: &str| {
+        let mut list = vec!["encrypt", "--force", "--key", key];
+        list.extend_from_slice(flags);
+        let input = dir.arg(input);
+        list.push(&input);
+        rc4(&list)
+    };
+
+    let (code, output) = encrypt(&[], "a.log");
+    assert_eq!(code, 0);
+    assert!(output.contains("warning: --key exposes"), "{}", output);
+    assert!(output.contains("started "), "{}", output);
+    assert!(output.contains("(5 bytes, "), "{}", output);
+    assert!(output.contains("1 succeeded, 0 failed"), "{}", output);
+    assert!(!output.contains("debug:"), "{}", output);
+
+    let (code, output) = encrypt(&["-q"], "a.log");
+    assert_eq!((code, output.as_str()), (0, ""));
+    let (code, output) = encrypt(&["--quiet"], "missing.log");
+    assert_eq!(code, EXIT_FAILURE);
+    assert_eq!(output.lines().count(), 1, "{}", output);
+    assert!(output.starts_with("error: ") && output.contains("missing.log"));
+
+    let (code, output) = encrypt(&["-v", "--glob", "nothing/*.log"], "a.log");
+    assert_eq!(code, 0);
+    assert!(output.contains("warning: pattern 'nothing/*.log' matched no files"));
+    assert!(
+        output.contains("debug: key: 15 bytes from --key"),
+        "{}",
+        output
+    );
+    assert!(!output.contains("trace:"), "{}", output);
+
+    let (code, output) = encrypt(&["-vv", "--resume", "--checkpoint-mib", "1"], "a.log");
+    assert_eq!(code, 0);
+    assert!(output.contains("debug: resume enabled"), "{}", output);
+    for (code, output) in [(code, output), encrypt(&["-v", "-v"], "a.log")] {
+        assert_eq!(code, 0);
+        assert!(!output.contains(key), "{}", output);
+    }
+
+    let (code, output) = encrypt(&["-q", "-v"], "a.log");
+    assert_eq!(code, EXIT_USAGE);
+    assert!(
+        output.starts_with("error: --quiet cannot be combined"),
+        "{}",
+        output
+    );
+}
+
+/// Несовпавшая контрольная сумма дает код 2 и понятное сообщение
+#[test]
+fn test_checksum_mismatch_message() {
+    let dir = TempDir::new("checksum");
+    fs::write(dir.path("data.bin"), b"checksummed data").unwrap();
+    let (code, _) = rc4(&[
+        "encrypt",
+        "-q",
+        "--key",
+        "Key",
+        "--checksum",
+        &dir.arg("data.bin"),
+    ]);
+    assert_eq!(code, 0);
+
+    let input = dir.arg("data.bin.rc4");
+    let decrypt = ["decrypt", "--force", "--key", "Wrong", "--checksum", &input];
+    let (code, output) = rc4(&decrypt);
+    assert_eq!(code, EXIT_CHECKSUM);
+    assert!(output.contains("wrong key or corrupted data"), "{}", output);
+}
+
+/// Читатель закрыл stdout раньше времени: выход тихий и с кодом 0
+#[test]
+fn test_closed_stdout_exits_quietly() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rc4"))
+        .args(["encrypt", "-q", "--key", "Key", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    // Процесс может завершиться раньше, чем будет записан весь вход
+    let _ = child.stdin.take().unwrap().write_all(&vec![0u8; 4 << 20]);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty(), "{:?}", output.stderr);
+}
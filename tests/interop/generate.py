@@ -0,0 +1,110 @@
+#!/usr/bin/env python3
+"""Regenerates the golden RC4 fixtures in this directory.
+
+Usage (from the crate root):
+
+    python3 tests/interop/generate.py
+
+OpenSSL fixtures (40- and 128-bit keys, the only lengths `openssl enc`
+accepts) are produced with:
+
+    openssl enc -rc4-40 -nosalt -K <key hex> -provider legacy -provider default
+    openssl enc -rc4    -nosalt -K <key hex> -provider legacy -provider default
+
+Python fixtures (any key length) use PyCryptodome when it is installed:
+
+    from Crypto.Cipher import ARC4
+    ARC4.new(key).encrypt(message)
+
+and otherwise fall back to the textbook RC4 below; the generator actually
+used is recorded in the third column of manifest.txt.
+
+Messages are never stored: message byte i is (i % 251), so only the
+expected ciphertext is kept. Ciphertext does not compress, so the
+multi-megabyte case stores its SHA-256 instead of the bytes.
+"""
+
+import hashlib
+import os
+import subprocess
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+
+def message(length):
+    return bytes(i % 251 for i in range(length))
+
+
+def openssl_rc4(key, data):
+    cipher = {5: "-rc4-40", 16: "-rc4"}[len(key)]
+    return subprocess.run(
+        ["openssl", "enc", cipher, "-nosalt", "-K", key.hex(),
+         "-provider", "legacy", "-provider", "default"],
+        input=data, capture_output=True, check=True,
+    ).stdout
+
+
+def textbook_rc4(key, data):
+    s = list(range(256))
+    j = 0
+    for i in range(256):
+        j = (j + s[i] + key[i % len(key)]) % 256
+        s[i], s[j] = s[j], s[i]
+    out = bytearray()
+    i = j = 0
+    for byte in data:
+        i = (i + 1) % 256
+        j = (j + s[i]) % 256
+        s[i], s[j] = s[j], s[i]
+        out.append(byte ^ s[(s[i] + s[j]) % 256])
+    return bytes(out)
+
+
+def python_rc4(key, data):
+    try:
+        from Crypto.Cipher import ARC4
+    except ImportError:
+        return "python-textbook", textbook_rc4(key, data)
+    return "pycryptodome", ARC4.new(key).encrypt(data)
+
+
+# name, generator, key, message length
+CASES = [
+    ("openssl-40-1", "openssl", bytes.fromhex("0102030405"), 1),
+    ("openssl-40-4200", "openssl", bytes.fromhex("0102030405"), 4200),
+    ("openssl-128-1000", "openssl", bytes(range(1, 17)), 1000),
+    ("openssl-128-5mib", "openssl", bytes.fromhex("ff" * 16), 5 * 1024 * 1024),
+    ("python-1-100", "python", b"\x42", 100),
+    ("python-7-4097", "python", bytes(range(7)), 4097),
+    ("python-32-1", "python", bytes(range(32)), 1),
+    ("python-100-3000", "python", bytes((i * 7) % 256 for i in range(100)), 3000),
+    ("python-256-5000", "python", bytes(255 - i for i in range(256)), 5000),
+]
+
+
+def main():
+    lines = [
+        "# Generated by generate.py; see its docstring for the exact commands.",
+        "# name generator key-hex message-length expected",
+        "# expected is <name>.bin or sha256:<hex> of the ciphertext",
+    ]
+    for name, generator, key, length in CASES:
+        data = message(length)
+        if generator == "openssl":
+            ciphertext = openssl_rc4(key, data)
+        else:
+            generator, ciphertext = python_rc4(key, data)
+        if length > 64 * 1024:
+            expected = "sha256:" + hashlib.sha256(ciphertext).hexdigest()
+        else:
+            expected = name + ".bin"
+            with open(os.path.join(HERE, expected), "wb") as f:
+                f.write(ciphertext)
+        lines.append(" ".join([name, generator, key.hex(), str(length), expected]))
+
+    with open(os.path.join(HERE, "manifest.txt"), "w") as f:
+        f.write("\n".join(lines) + "\n")
+
+
+if __name__ == "__main__":
+    main()
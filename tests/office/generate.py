@@ -0,0 +1,86 @@
+#!/usr/bin/env python3
+"""Regenerates the Office binary RC4 fixtures in this directory.
+
+Usage (from the crate root):
+
+    python3 tests/office/generate.py
+
+Follows MS-OFFCRYPTO 2.3.6.2 (key derivation), 2.3.6.3 (password
+verifier) and 2.3.6.4 (per-block rekeying), written here independently of
+the Rust code with hashlib's MD5:
+
+    H0     = MD5(UTF-16LE(password))
+    H1     = MD5((H0[:5] || salt) repeated 16 times)
+    key(b) = MD5(H1[:5] || b as 32-bit little-endian)
+
+manifest.txt lists "password salt encrypted-verifier encrypted-verifier-hash"
+for each password; salts and verifiers are fixed so regeneration is stable.
+Office 97-2003 only accepts open passwords of 1 to 15 UTF-16 characters, so
+every entry stays within that range: "VelvetSweatshop" is the built-in
+password Excel uses for workbooks that are only write-protected, and is
+exactly 15 characters long.
+stream-512.bin and stream-1024.bin hold a 2600-byte message (byte i is
+i % 251) encrypted with the first manifest entry, rekeyed every 512 and
+1024 bytes respectively.
+"""
+
+import hashlib
+import os
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+ENTRIES = [
+    ("password", bytes(range(16)), bytes(range(0x10, 0x20))),
+    ("Pässwörd-Ω", bytes.fromhex("8f3a0c7b2e9d4f1165a0b3c4d5e6f708"), b"verifier bytes!!"),
+    ("VelvetSweatshop", bytes(16), bytes([0xAA] * 16)),
+]
+
+
+def rc4(key, data):
+    s = list(range(256))
+    j = 0
+    for i in range(256):
+        j = (j + s[i] + key[i % len(key)]) % 256
+        s[i], s[j] = s[j], s[i]
+    out = bytearray()
+    i = j = 0
+    for byte in data:
+        i = (i + 1) % 256
+        j = (j + s[i]) % 256
+        s[i], s[j] = s[j], s[i]
+        out.append(byte ^ s[(s[i] + s[j]) % 256])
+    return bytes(out)
+
+
+def block_key(password, salt, block):
+    h0 = hashlib.md5(password.encode("utf-16-le")).digest()
+    h1 = hashlib.md5((h0[:5] + salt) * 16).digest()
+    return hashlib.md5(h1[:5] + block.to_bytes(4, "little")).digest()
+
+
+def main():
+    lines = ["# Generated by generate.py; see its docstring.",
+             "# password salt encrypted-verifier encrypted-verifier-hash"]
+    for password, salt, verifier in ENTRIES:
+        assert 1 <= len(password.encode("utf-16-le")) // 2 <= 15, password
+        # Verifier and its hash are one continuous block-0 keystream
+        encrypted = rc4(block_key(password, salt, 0),
+                        verifier + hashlib.md5(verifier).digest())
+        lines.append("%s %s %s %s" % (
+            password, salt.hex(), encrypted[:16].hex(), encrypted[16:].hex()))
+    with open(os.path.join(HERE, "manifest.txt"), "w", encoding="utf-8") as f:
+        f.write("\n".join(lines) + "\n")
+
+    password, salt, _ = ENTRIES[0]
+    message = bytes(i % 251 for i in range(2600))
+    for block_size in (512, 1024):
+        out = bytearray()
+        for block, start in enumerate(range(0, len(message), block_size)):
+            out += rc4(block_key(password, salt, block),
+                       message[start:start + block_size])
+        with open(os.path.join(HERE, "stream-%d.bin" % block_size), "wb") as f:
+            f.write(out)
+
+
+if __name__ == "__main__":
+    main()
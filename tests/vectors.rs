@@ -0,0 +1,32 @@
+//! Прогон файлов тестовых векторов из `tests/data/` через
+//! `rust_rc4::testvec` (feature `test-utils`).
+
+use std::fs;
+use std::path::Path;
+
+use rust_rc4::testvec;
+
+/// Все файлы из tests/data проходят полностью
+#[test]
+fn test_vector_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+
+    for path in files {
+        let report = testvec::run_file(&path).unwrap();
+        assert!(report.passed > 0, "{}: no test cases", path.display());
+        let failures: Vec<String> = report.failures.iter().map(|f| f.to_string()).collect();
+        assert!(
+            failures.is_empty(),
+            "{}:\n{}",
+            path.display(),
+            failures.join("\n")
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Загрузка и прогон тестовых векторов в JSON-схеме в духе Wycheproof.
+//!
+//! ```text
+//! {
+//!   "algorithm": "RC4",
+//!   "testGroups": [{
+//!     "tests": [{
+//!       "tcId": 1,
+//!       "comment": "RFC 6229, 40-bit key, offset 0",
+//!       "key": "0102030405",     // hex
+//!       "drop": 768,             // необязательно: Rc4::new_drop
+//!       "nonce": "aabbcc",       // необязательно: Rc4::new_with_nonce
+//!       "offset": 0,             // байт гаммы пропускается перед проверкой
+//!       "keystream": "b2396305", // ожидаемая гамма, или
+//!       "msg": "00", "ct": "b2", // открытый текст и шифротекст
+//!       "result": "valid",       // или "invalid"
+//!       "error": "KeyTooLong"    // для invalid: ожидаемый вариант Rc4Error
+//!     }]
+//!   }]
+//! }
+//! ```
+//!
+//! Документ разбирается `serde_json`, поэтому модуль требует feature
+//! `test-utils` (включает `serde`). Файлы из `tests/data/` прогоняет
+//! интеграционный тест `tests/vectors.rs`.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{hex, Rc4, Rc4Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    algorithm: Option<String>,
+    test_groups: Vec<TestGroup>,
+}
+
+#[derive(Deserialize)]
+struct TestGroup {
+    tests: Vec<TestCase>,
+}
+
+/// Один случай; шестнадцатеричные поля декодируются при прогоне, чтобы
+/// ошибка в них попала в отчет с номером случая.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestCase {
+    tc_id: u64,
+    key: String,
+    drop: Option<usize>,
+    nonce: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    keystream: Option<String>,
+    msg: Option<String>,
+    ct: Option<String>,
+    result: Outcome,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Valid,
+    Invalid,
+}
+
+/// Непройденный тестовый случай.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub tc_id: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tcId {}: {}", self.tc_id, self.message)
+    }
+}
+
+/// Итог прогона одного файла.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub passed: usize,
+    pub failures: Vec<Failure>,
+}
+
+/// Имя варианта ошибки, как оно записывается в поле `error`.
+pub fn error_name(error: &Rc4Error) -> &'static str {
+    match error {
+        Rc4Error::EmptyKey => "EmptyKey",
+        Rc4Error::KeyTooLong(_) => "KeyTooLong",
+        Rc4Error::LengthMismatch { .. } => "LengthMismatch",
+        Rc4Error::WeakKey(_) => "WeakKey",
+        Rc4Error::RangeOutOfBounds { .. } => "RangeOutOfBounds",
+        Rc4Error::InputTooShort { .. } => "InputTooShort",
+        Rc4Error::NonceReused => "NonceReused",
+        Rc4Error::EmptyNonce => "EmptyNonce",
+        Rc4Error::NonceTooLong { .. } => "NonceTooLong",
+        Rc4Error::EnvVarMissing(_) => "EnvVarMissing",
+        Rc4Error::EnvVarInvalid(_) => "EnvVarInvalid",
+        Rc4Error::InvalidHex(_) => "InvalidHex",
+        Rc4Error::SizeMismatch { .. } => "SizeMismatch",
+        Rc4Error::UsageLimitExceeded { .. } => "UsageLimitExceeded",
+    }
+}
+
+/// Прогоняет файл с векторами.
+pub fn run_file(path: &Path) -> io::Result<Report> {
+    let text = fs::read_to_string(path)?;
+    run_json(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// Прогоняет все случаи документа. Ошибка возвращается только для
+/// документа, не соответствующего схеме; непройденные случаи попадают
+/// в `Report::failures`.
+pub fn run_json(text: &str) -> Result<Report, String> {
+    let document: Document = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if let Some(algorithm) = &document.algorithm {
+        if algorithm != "RC4" {
+            return Err(format!("unsupported algorithm '{}'", algorithm));
+        }
+    }
+
+    let mut report = Report::default();
+    for case in document.test_groups.iter().flat_map(|group| &group.tests) {
+        match run_case(case) {
+            Ok(()) => report.passed += 1,
+            Err(message) => report.failures.push(Failure {
+                tc_id: case.tc_id,
+                message,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+fn decode(name: &str, text: Option<&String>) -> Result<Option<Vec<u8>>, String> {
+    text.map(|text| hex::decode(text).map_err(|e| format!("{}: {}", name, e)))
+        .transpose()
+}
+
+fn run_case(case: &TestCase) -> Result<(), String> {
+    let key = hex::decode(&case.key).map_err(|e| format!("key: {}", e))?;
+    let nonce = decode("nonce", case.nonce.as_ref())?;
+
+    let constructed = match (&nonce, case.drop) {
+        (Some(_), Some(_)) => return Err("nonce and drop cannot be combined".into()),
+        (Some(nonce), None) => Rc4::new_with_nonce(&key, nonce),
+        (None, Some(drop)) => Rc4::new_drop(&key, drop),
+        (None, None) => Rc4::try_new(&key),
+    };
+
+    let mut cipher = match (constructed, case.result) {
+        (Ok(cipher), Outcome::Valid) => cipher,
+        (Ok(_), Outcome::Invalid) => {
+            return Err("expected an error, but the cipher was created".into())
+        }
+        (Err(e), Outcome::Valid) => return Err(format!("unexpected error: {}", e)),
+        (Err(e), Outcome::Invalid) => {
+            return match &case.error {
+                Some(expected) if expected != error_name(&e) => Err(format!(
+                    "expected {} error, got {}",
+                    expected,
+                    error_name(&e)
+                )),
+                _ => Ok(()),
+            };
+        }
+    };
+
+    cipher.skip(case.offset);
+    let mut checked = false;
+    if let Some(expected) = decode("keystream", case.keystream.as_ref())? {
+        compare(
+            "keystream",
+            &expected,
+            &cipher.keystream_to_vec(expected.len()),
+        )?;
+        checked = true;
+    }
+    match (
+        decode("msg", case.msg.as_ref())?,
+        decode("ct", case.ct.as_ref())?,
+    ) {
+        (Some(msg), Some(expected)) => {
+            let mut data = msg.clone();
+            cipher.process(&mut data);
+            compare("ciphertext", &expected, &data)?;
+            checked = true;
+        }
+        (None, None) => {}
+        _ => return Err("msg and ct must be given together".into()),
+    }
+    if !checked {
+        return Err("valid case has neither keystream nor msg/ct".into());
+    }
+    Ok(())
+}
+
+fn compare(what: &str, expected: &[u8], actual: &[u8]) -> Result<(), String> {
+    if actual == expected {
+        return Ok(());
+    }
+    Err(format!(
+        "{} mismatch: expected {}, got {}",
+        what,
+        hex::encode(expected),
+        hex::encode(actual)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Неверный вектор попадает в отчет со своим tcId
+    #[test]
+    fn test_failures_are_reported() {
+        let report = run_json(
+            r#"{"testGroups": [{"tests": [
+                {"tcId": 1, "key": "0102030405", "keystream": "b2396305", "result": "valid"},
+                {"tcId": 2, "key": "0102030405", "keystream": "00", "result": "valid"},
+                {"tcId": 3, "key": "", "result": "invalid", "error": "KeyTooLong"},
+                {"tcId": 4, "key": "01", "result": "invalid"}
+            ]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(report.passed, 1);
+        let ids: Vec<u64> = report.failures.iter().map(|f| f.tc_id).collect();
+        assert_eq!(ids, [2, 3, 4]);
+        assert!(report.failures[1]
+            .message
+            .contains("expected KeyTooLong error, got EmptyKey"));
+
+        assert!(run_json(r#"{"algorithm": "AES", "testGroups": []}"#).is_err());
+        assert!(run_json(r#"{"testGroups": [{"tests": [{"key": "01"}]}]}"#).is_err());
+    }
+}